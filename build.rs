@@ -1,25 +1,41 @@
 use dotenv::dotenv;
 use std::{env, fs::File, io::Write};
 
+/// 設定されていなくても空文字列で定義する環境変数
+const REQUIRED_KEYS: [&str; 2] = [
+    "SHOKEN_WEBAPI_GOOGLE_OAUTH_CLIENT_ID",
+    "SHOKEN_WEBAPI_REDIRECT_URL",
+];
+
 fn main() {
     println!("cargo:rerun-if-changed=.env");
     let dest_path = "./src/env.rs";
-    let mut f = File::create(&dest_path).unwrap();
+    let mut f = File::create(dest_path).unwrap();
 
     // use the dotenv crate to get the .env values
     dotenv().ok();
     f.write_all(b"// This file is automatically generated by build.rs\n\n")
         .unwrap();
     f.write_all("#![allow(dead_code)]\n".as_bytes()).unwrap();
+    for key in REQUIRED_KEYS {
+        println!("cargo:rerun-if-env-changed={}", key);
+        if env::var(key).is_err() {
+            write_const(&mut f, key, "");
+        }
+    }
     for (key, value) in env::vars() {
-        if key.starts_with("SHOKEN_WEBAPI_") {
-            let line = format!(
-                "pub const {}: &'static str = \"{}\";\n",
-                key,
-                value.replace("\"", "\\\"")
-            );
-
-            f.write_all(line.as_bytes()).unwrap();
+        // シークレットは WASM に埋め込むと誰でも読めるので書き出さない
+        if key.starts_with("SHOKEN_WEBAPI_") && !key.contains("SECRET") {
+            write_const(&mut f, &key, &value);
         }
     }
 }
+
+fn write_const(f: &mut File, key: &str, value: &str) {
+    let line = format!(
+        "pub const {}: &str = \"{}\";\n",
+        key,
+        value.replace("\"", "\\\"")
+    );
+    f.write_all(line.as_bytes()).unwrap();
+}
@@ -1,13 +1,24 @@
-use gloo::console;
-use url::Url;
+use chrono::Utc;
+use gloo::{
+    console,
+    timers::{callback::Timeout, future::TimeoutFuture},
+};
+use std::{cell::Cell, rc::Rc};
 use wasm_bindgen::JsValue;
+use wasm_bindgen_futures::spawn_local;
 use web_sys::{window, Window};
 use yew::prelude::*;
 use yew_router::prelude::*;
 
 use crate::{
-    data::use_info::UserInfo,
-    pages::{home::Home, receipts::Receipts, search::Search},
+    data::{link_provider::LinkProviders, use_info::UserInfo, watchlist::Watchlist},
+    pages::{
+        dividend_calendar::DividendCalendarPage, home::Home, layout::RequireLogin,
+        link_settings::LinkSettings, receipts::Receipts, search::Search, stock::Stock,
+        tax_report::TaxReport, watchlist::WatchlistPage,
+    },
+    services::oauth_google,
+    setting::{SESSION_REFRESH_RETRY_MAX_MS, SESSION_REFRESH_RETRY_MS},
 };
 
 #[derive(Clone, Routable, PartialEq)]
@@ -18,6 +29,16 @@ pub enum Route {
     Receipts,
     #[at("/shoken-webapp-wasm/search")]
     Search,
+    #[at("/shoken-webapp-wasm/stock/:code")]
+    Stock { code: String },
+    #[at("/shoken-webapp-wasm/tax-report")]
+    TaxReport,
+    #[at("/shoken-webapp-wasm/dividend-calendar")]
+    DividendCalendar,
+    #[at("/shoken-webapp-wasm/watchlist")]
+    Watchlist,
+    #[at("/shoken-webapp-wasm/links")]
+    LinkSettings,
     #[not_found]
     #[at("/shoken-webapp-wasm/404")]
     NotFound,
@@ -26,56 +47,145 @@ pub enum Route {
 pub fn switch(routes: Route) -> Html {
     match routes {
         Route::Home => html! { <Home /> },
-        Route::Receipts => html! { <Receipts /> },
+        Route::Receipts => html! { <RequireLogin><Receipts /></RequireLogin> },
         Route::Search => html! { <Search /> },
+        Route::Stock { code } => html! { <Stock code={code} /> },
+        Route::TaxReport => html! { <RequireLogin><TaxReport /></RequireLogin> },
+        Route::DividendCalendar => html! { <RequireLogin><DividendCalendarPage /></RequireLogin> },
+        Route::Watchlist => html! { <WatchlistPage /> },
+        Route::LinkSettings => html! { <LinkSettings /> },
         Route::NotFound => html! { <h1>{ "404 - Page not found" }</h1> },
     }
 }
 
 #[function_component]
 pub fn App() -> Html {
-    let user_info = use_state(initialize_user_info);
-    console::log!(format!("user_info: {:?}", user_info));
+    let user_info = use_reducer(initialize_user_info);
+    let watchlist = use_reducer(initialize_watchlist);
+    let link_providers = use_reducer(initialize_link_providers);
 
     use_effect(update_browser_history);
+    {
+        let user_info = user_info.clone();
+        use_effect_with((), move |_| consume_auth_callback(user_info));
+    }
+    {
+        let handle = user_info.clone();
+        use_effect_with((*user_info).clone(), move |user_info| {
+            save_user_info_to_storage(user_info);
+            schedule_session_refresh(handle, user_info)
+        });
+    }
+    use_effect_with((*watchlist).clone(), save_watchlist_to_storage);
+    use_effect_with((*link_providers).clone(), save_link_providers_to_storage);
 
     html! {
-        <ContextProvider<UserInfo> context={(*user_info).clone()}>
-            <BrowserRouter>
-                <Switch<Route> render={switch} />
-            </BrowserRouter>
-        </ContextProvider<UserInfo>>
+        <ContextProvider<UseReducerHandle<UserInfo>> context={user_info}>
+            <ContextProvider<UseReducerHandle<Watchlist>> context={watchlist}>
+                <ContextProvider<UseReducerHandle<LinkProviders>> context={link_providers}>
+                    <BrowserRouter>
+                        <Switch<Route> render={switch} />
+                    </BrowserRouter>
+                </ContextProvider<UseReducerHandle<LinkProviders>>>
+            </ContextProvider<UseReducerHandle<Watchlist>>>
+        </ContextProvider<UseReducerHandle<UserInfo>>>
     }
 }
 
+/// 保存済みのセッションを読み込む。期限が切れて更新もできないものは捨てる。
 fn initialize_user_info() -> UserInfo {
     window()
-        .and_then(|window| {
-            get_user_info_from_url(&window).or_else(|| get_user_info_from_storage(&window))
-        })
+        .and_then(|window| get_user_info_from_storage(&window))
+        .filter(|user_info| !user_info.is_expired(Utc::now()))
         .unwrap_or_default()
 }
 
-fn get_user_info_from_url(window: &Window) -> Option<UserInfo> {
-    window.location().search().ok().and_then(|search| {
-        Url::parse(&format!(
-            "http://localhost:8080/shoken-webapp-wasm/{}",
-            &search
-        ))
-        .ok()
-        .and_then(|url| {
-            url.query_pairs()
-                .find(|(key, _)| key == "code")
-                .map(|(_, auth_code)| {
-                    let new_info = UserInfo {
-                        auth_code: Some(auth_code.to_string()),
-                        ..Default::default()
-                    };
-                    save_user_info_to_storage(&new_info);
-                    new_info
-                })
+/// Google からのリダイレクトであれば、認可コードをバックエンドでトークンに交換する。
+///
+/// 認可コードはアドレスバーからすぐに消し、保存もしない。
+fn consume_auth_callback(user_info: UseReducerHandle<UserInfo>) {
+    let Some(window) = window() else {
+        return;
+    };
+    let search = window.location().search().unwrap_or_default();
+    let Some(callback) = oauth_google::parse_callback(&search) else {
+        return;
+    };
+    if let (Ok(pathname), Ok(history)) = (window.location().pathname(), window.history()) {
+        _ = history.replace_state_with_url(&JsValue::NULL, "", Some(&pathname));
+    }
+
+    let pending = oauth_google::take_pending_login();
+    let result = callback.and_then(|callback| {
+        oauth_google::verify_callback(pending, &callback).map(|verifier| (callback, verifier))
+    });
+    let (callback, code_verifier) = match result {
+        Ok(verified) => verified,
+        Err(err) => {
+            console::log!(err.to_string());
+            return;
+        }
+    };
+    spawn_local(async move {
+        match oauth_google::exchange_code(&callback, &code_verifier).await {
+            Ok(token) => user_info.dispatch(user_info.with_token(token, Utc::now())),
+            Err(err) => console::log!(err.to_string()),
+        }
+    });
+}
+
+/// アクセストークンの期限が切れる前に更新する。
+///
+/// リフレッシュトークンが無効になっていればログアウトする。通信エラーなどでは間隔を倍にしながら再試行する。
+fn schedule_session_refresh(
+    handle: UseReducerHandle<UserInfo>,
+    user_info: &UserInfo,
+) -> impl FnOnce() {
+    // ログアウトなどでセッションが変わったら、再試行中の更新も結果を反映しない
+    let cancelled = Rc::new(Cell::new(false));
+    let timeout = user_info.refresh_at().map(|refresh_at| {
+        let user_info = user_info.clone();
+        let cancelled = cancelled.clone();
+        // ブラウザは setTimeout の待ち時間を i32 として扱い、超えるとすぐに実行する
+        let delay_ms = (refresh_at - Utc::now())
+            .num_milliseconds()
+            .clamp(0, i32::MAX as i64);
+        Timeout::new(delay_ms as u32, move || {
+            let Some(refresh_token) = user_info.refresh_token.clone() else {
+                return;
+            };
+            spawn_local(async move {
+                let mut retry_ms = SESSION_REFRESH_RETRY_MS;
+                loop {
+                    let result = oauth_google::refresh_token(&refresh_token).await;
+                    if cancelled.get() {
+                        return;
+                    }
+                    match result {
+                        Ok(token) => {
+                            handle.dispatch(user_info.with_token(token, Utc::now()));
+                            return;
+                        }
+                        Err(err) if err.is_auth_failure() => {
+                            console::log!(err.to_string());
+                            handle.dispatch(UserInfo::default());
+                            return;
+                        }
+                        Err(err) => console::log!(err.to_string()),
+                    }
+                    TimeoutFuture::new(retry_ms).await;
+                    if cancelled.get() {
+                        return;
+                    }
+                    retry_ms = retry_ms.saturating_mul(2).min(SESSION_REFRESH_RETRY_MAX_MS);
+                }
+            });
         })
-    })
+    });
+    move || {
+        cancelled.set(true);
+        drop(timeout)
+    }
 }
 
 fn get_user_info_from_storage(window: &Window) -> Option<UserInfo> {
@@ -88,9 +198,42 @@ fn get_user_info_from_storage(window: &Window) -> Option<UserInfo> {
 }
 
 fn save_user_info_to_storage(user_info: &UserInfo) {
-    if let Ok(json) = serde_json::to_string(user_info) {
+    let Some(storage) = window().and_then(|w| w.local_storage().ok()).flatten() else {
+        return;
+    };
+    if *user_info == UserInfo::default() {
+        _ = storage.remove_item("user_info");
+    } else if let Ok(json) = serde_json::to_string(user_info) {
+        _ = storage.set_item("user_info", &json);
+    }
+}
+
+fn initialize_watchlist() -> Watchlist {
+    window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .and_then(|storage| storage.get_item("watchlist").ok().flatten())
+        .and_then(|data| Watchlist::from_json(&data).ok())
+        .unwrap_or_default()
+}
+
+fn save_watchlist_to_storage(watchlist: &Watchlist) {
+    if let Some(storage) = window().and_then(|w| w.local_storage().ok()).flatten() {
+        _ = storage.set_item("watchlist", &watchlist.to_json());
+    }
+}
+
+fn initialize_link_providers() -> LinkProviders {
+    window()
+        .and_then(|window| window.local_storage().ok().flatten())
+        .and_then(|storage| storage.get_item("link_providers").ok().flatten())
+        .and_then(|data| serde_json::from_str(&data).ok())
+        .unwrap_or_default()
+}
+
+fn save_link_providers_to_storage(link_providers: &LinkProviders) {
+    if let Ok(json) = serde_json::to_string(link_providers) {
         if let Some(storage) = window().and_then(|w| w.local_storage().ok()).flatten() {
-            _ = storage.set_item("user_info", &json);
+            _ = storage.set_item("link_providers", &json);
         }
     }
 }
@@ -1,2 +1,5 @@
+pub mod link_provider;
+pub mod money;
 pub mod stock;
 pub mod use_info;
+pub mod watchlist;
@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+use std::rc::Rc;
+use url::form_urlencoded;
+use yew::Reducible;
+
+use super::stock::StockData;
+use crate::setting::DEFAULT_LINK_PROVIDERS;
+
+/// 銘柄情報サイトへのリンク
+///
+/// `url_template` の `{code}`, `{name}`, `{market}` を銘柄の値に置き換えてリンクを作る。
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct LinkProvider {
+    pub name: String,
+    pub url_template: String,
+    pub enabled: bool,
+    #[serde(default)]
+    pub markets: Vec<String>, // 表示するマーケットカテゴリ (部分一致)。空の場合はすべて
+}
+
+impl LinkProvider {
+    pub fn new(name: &str, url_template: &str, markets: &[&str]) -> Self {
+        Self {
+            name: name.to_string(),
+            url_template: url_template.to_string(),
+            enabled: true,
+            markets: markets.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    /// 銘柄のマーケットカテゴリにリンクを表示するか
+    pub fn applies_to(&self, stock: &StockData) -> bool {
+        self.enabled
+            && (self.markets.is_empty()
+                || self
+                    .markets
+                    .iter()
+                    .any(|market| stock.market_category.contains(market.as_str())))
+    }
+
+    pub fn url(&self, stock: &StockData) -> String {
+        let encode =
+            |value: &str| form_urlencoded::byte_serialize(value.as_bytes()).collect::<String>();
+        self.url_template
+            // 以前の形式のテンプレート
+            .replace("{}", "{code}")
+            .replace("{code}", &encode(&stock.code))
+            .replace("{name}", &encode(&stock.name))
+            .replace("{market}", &encode(&stock.market_category))
+    }
+}
+
+/// 利用者が編集できるリンクの一覧。表示する順に並べる。
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct LinkProviders {
+    pub providers: Vec<LinkProvider>,
+}
+
+impl Default for LinkProviders {
+    fn default() -> Self {
+        Self {
+            providers: DEFAULT_LINK_PROVIDERS
+                .iter()
+                .map(|(name, url, markets)| LinkProvider::new(name, url, markets))
+                .collect(),
+        }
+    }
+}
+
+pub enum LinkProvidersAction {
+    Add(LinkProvider),
+    Update(usize, LinkProvider),
+    Remove(usize),
+    Move { from: usize, to: usize },
+    Reset,
+}
+
+impl LinkProviders {
+    /// 銘柄に表示するリンク
+    pub fn links_for<'a>(
+        &'a self,
+        stock: &'a StockData,
+    ) -> impl Iterator<Item = (&'a str, String)> {
+        self.providers
+            .iter()
+            .filter(|provider| provider.applies_to(stock))
+            .map(|provider| (provider.name.as_str(), provider.url(stock)))
+    }
+}
+
+impl Reducible for LinkProviders {
+    type Action = LinkProvidersAction;
+
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        let mut links = (*self).clone();
+        let len = links.providers.len();
+        match action {
+            LinkProvidersAction::Add(provider) => links.providers.push(provider),
+            LinkProvidersAction::Update(i, provider) if i < len => links.providers[i] = provider,
+            LinkProvidersAction::Remove(i) if i < len => {
+                links.providers.remove(i);
+            }
+            LinkProvidersAction::Move { from, to } if from < len && to < len => {
+                let provider = links.providers.remove(from);
+                links.providers.insert(to, provider);
+            }
+            LinkProvidersAction::Reset => links = LinkProviders::default(),
+            _ => return self,
+        }
+        links.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stock(market_category: &str) -> StockData {
+        StockData {
+            code: "7203".to_string(),
+            name: "トヨタ自動車".to_string(),
+            market_category: market_category.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_url() {
+        let provider = LinkProvider::new(
+            "test",
+            "https://example.com/{code}?q={name}&m={market}",
+            &[],
+        );
+        assert_eq!(
+            provider.url(&stock("プライム（内国株式）")),
+            "https://example.com/7203?q=%E3%83%88%E3%83%A8%E3%82%BF%E8%87%AA%E5%8B%95%E8%BB%8A&m=%E3%83%97%E3%83%A9%E3%82%A4%E3%83%A0%EF%BC%88%E5%86%85%E5%9B%BD%E6%A0%AA%E5%BC%8F%EF%BC%89"
+        );
+        let provider = LinkProvider::new("old", "https://example.com/{}/", &[]);
+        assert_eq!(provider.url(&stock("")), "https://example.com/7203/");
+    }
+
+    #[test]
+    fn test_applies_to_market() {
+        let mut provider = LinkProvider::new(
+            "SBI証券",
+            "https://example.com/{code}",
+            &["プライム", "スタンダード"],
+        );
+        assert!(provider.applies_to(&stock("プライム（内国株式）")));
+        assert!(!provider.applies_to(&stock("ETF・ETN")));
+
+        provider.enabled = false;
+        assert!(!provider.applies_to(&stock("プライム（内国株式）")));
+    }
+
+    #[test]
+    fn test_reduce() {
+        let links = Rc::new(LinkProviders::default());
+        let first = links.providers[0].clone();
+        let links = links
+            .reduce(LinkProvidersAction::Move { from: 0, to: 2 })
+            .reduce(LinkProvidersAction::Remove(99));
+
+        assert_eq!(links.providers[2], first);
+        assert_eq!(links.providers.len(), DEFAULT_LINK_PROVIDERS.len());
+        assert_eq!(
+            *links.reduce(LinkProvidersAction::Reset),
+            LinkProviders::default()
+        );
+    }
+}
@@ -0,0 +1,262 @@
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::{collections::BTreeMap, fmt};
+use strum::{EnumIter, EnumMessage, IntoEnumIterator};
+use thiserror::Error;
+
+use crate::services::formater::StrFormater;
+
+#[derive(Debug, Error, PartialEq)]
+pub enum MoneyError {
+    #[error("通貨が異なる金額は合計できません: {0} と {1}")]
+    CurrencyMismatch(&'static str, &'static str),
+}
+
+/// 配当を受け取る通貨
+#[derive(
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Debug,
+    Default,
+    Serialize,
+    EnumIter,
+    EnumMessage,
+)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum Currency {
+    #[default]
+    #[strum(message = "円")]
+    Jpy,
+
+    #[strum(message = "米ドル")]
+    Usd,
+
+    #[strum(message = "ユーロ")]
+    Eur,
+
+    #[strum(message = "英ポンド")]
+    Gbp,
+
+    #[strum(message = "豪ドル")]
+    Aud,
+
+    #[strum(message = "カナダドル")]
+    Cad,
+
+    #[strum(message = "香港ドル")]
+    Hkd,
+
+    #[strum(message = "人民元")]
+    Cny,
+
+    #[strum(message = "シンガポールドル")]
+    Sgd,
+}
+
+impl Currency {
+    /// ISO 4217 の通貨コード
+    pub fn code(&self) -> &'static str {
+        match self {
+            Currency::Jpy => "JPY",
+            Currency::Usd => "USD",
+            Currency::Eur => "EUR",
+            Currency::Gbp => "GBP",
+            Currency::Aud => "AUD",
+            Currency::Cad => "CAD",
+            Currency::Hkd => "HKD",
+            Currency::Cny => "CNY",
+            Currency::Sgd => "SGD",
+        }
+    }
+
+    /// 補助単位の桁数 (円は 0、ドルなどは 2)
+    pub fn precision(&self) -> u32 {
+        match self {
+            Currency::Jpy => 0,
+            _ => 2,
+        }
+    }
+
+    /// 通貨コードか、証券会社の CSV の表記 (`円`, `USドル` など) から判定する
+    pub fn parse(value: &str) -> Option<Currency> {
+        let value = value.trim();
+        if value.is_empty() || value == "円" {
+            return Some(Currency::Jpy);
+        }
+        let upper = value.to_ascii_uppercase();
+        Currency::iter().find(|currency| {
+            upper == currency.code()
+                || currency.get_message() == Some(value)
+                || (upper.starts_with(&currency.code()[..2]) && value.ends_with("ドル"))
+        })
+    }
+}
+
+/// 以前の形式 (CSV の表記のままの文字列) で保存された通貨も読み込む。
+/// 通貨がない (`null`) 場合は円とみなし、判定できない通貨はエラーにする。
+impl<'de> Deserialize<'de> for Currency {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            None => Ok(Currency::Jpy),
+            Some(value) => Currency::parse(&value)
+                .ok_or_else(|| de::Error::custom(format!("対応していない通貨です: {}", value))),
+        }
+    }
+}
+
+/// 通貨と、補助単位 (セント等) で表した金額
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Money {
+    pub minor: i64,
+    pub currency: Currency,
+}
+
+impl Money {
+    pub fn zero(currency: Currency) -> Self {
+        Self { minor: 0, currency }
+    }
+
+    /// 小数の金額から、通貨の桁数で丸めて作る
+    pub fn from_decimal(amount: f64, currency: Currency) -> Self {
+        let scale = 10_f64.powi(currency.precision() as i32);
+        Self {
+            minor: (amount * scale).round() as i64,
+            currency,
+        }
+    }
+
+    pub fn to_decimal(self) -> f64 {
+        self.minor as f64 / 10_f64.powi(self.currency.precision() as i32)
+    }
+
+    /// 通貨の桁数の小数で表した金額 (書き出し用で、通貨コードや桁区切りは付けない)
+    pub fn to_decimal_string(self) -> String {
+        format!(
+            "{:.*}",
+            self.currency.precision() as usize,
+            self.to_decimal()
+        )
+    }
+
+    pub fn checked_add(self, other: Money) -> Result<Money, MoneyError> {
+        if self.currency != other.currency {
+            return Err(MoneyError::CurrencyMismatch(
+                self.currency.code(),
+                other.currency.code(),
+            ));
+        }
+        Ok(Self {
+            minor: self.minor + other.minor,
+            currency: self.currency,
+        })
+    }
+
+    /// 1 単位あたりの円のレートで円に換算する (1 円未満は切り捨て)
+    pub fn to_jpy(self, rate: f64) -> Money {
+        if self.currency == Currency::Jpy {
+            return self;
+        }
+        Money {
+            minor: (self.to_decimal() * rate).trunc() as i64,
+            currency: Currency::Jpy,
+        }
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let number = self.to_decimal_string();
+        match self.currency {
+            Currency::Jpy => write!(f, "{}", number.as_str().format_yen()),
+            currency => write!(f, "{} {}", currency.code(), number.as_str().format_number()),
+        }
+    }
+}
+
+/// 利用者が入力した為替レート (1 単位あたりの円)
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug, Default)]
+pub struct ExchangeRates {
+    pub rates: BTreeMap<Currency, f64>,
+}
+
+impl ExchangeRates {
+    pub fn get(&self, currency: Currency) -> Option<f64> {
+        match currency {
+            Currency::Jpy => Some(1.0),
+            currency => self.rates.get(&currency).copied(),
+        }
+    }
+
+    /// 0 以下のレートは削除する
+    pub fn set(&mut self, currency: Currency, rate: f64) {
+        if rate > 0.0 && currency != Currency::Jpy {
+            self.rates.insert(currency, rate);
+        } else {
+            self.rates.remove(&currency);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_currency() {
+        assert_eq!(Currency::parse("円"), Some(Currency::Jpy));
+        assert_eq!(Currency::parse(""), Some(Currency::Jpy));
+        assert_eq!(Currency::parse("USドル"), Some(Currency::Usd));
+        assert_eq!(Currency::parse("usd"), Some(Currency::Usd));
+        assert_eq!(Currency::parse("香港ドル"), Some(Currency::Hkd));
+        assert_eq!(Currency::parse("ルピー"), None);
+
+        let legacy: Currency = serde_json::from_str(r#""USドル""#).unwrap();
+        assert_eq!(legacy, Currency::Usd);
+        assert_eq!(
+            serde_json::from_str::<Currency>("null").unwrap(),
+            Currency::Jpy
+        );
+        assert!(serde_json::from_str::<Currency>(r#""NZドル""#).is_err());
+        assert_eq!(serde_json::to_string(&Currency::Usd).unwrap(), r#""USD""#);
+    }
+
+    #[test]
+    fn test_money() {
+        let dollars = Money::from_decimal(12.346, Currency::Usd);
+        assert_eq!(dollars.minor, 1235);
+        assert_eq!(dollars.to_string(), "USD 12.35");
+        assert_eq!(dollars.to_decimal_string(), "12.35");
+        assert_eq!(
+            Money::from_decimal(1234.6, Currency::Jpy).to_string(),
+            "¥ 1,235"
+        );
+
+        let total = dollars.checked_add(Money::from_decimal(0.65, Currency::Usd));
+        assert_eq!(total.map(|m| m.minor), Ok(1300));
+        assert_eq!(
+            dollars.checked_add(Money::zero(Currency::Jpy)),
+            Err(MoneyError::CurrencyMismatch("USD", "JPY"))
+        );
+        assert_eq!(dollars.to_jpy(150.5).minor, 1858);
+    }
+
+    #[test]
+    fn test_exchange_rates() {
+        let mut rates = ExchangeRates::default();
+        rates.set(Currency::Usd, 150.0);
+        assert_eq!(rates.get(Currency::Usd), Some(150.0));
+        assert_eq!(rates.get(Currency::Jpy), Some(1.0));
+        assert_eq!(rates.get(Currency::Eur), None);
+
+        let json = serde_json::to_string(&rates).unwrap();
+        assert_eq!(json, r#"{"rates":{"USD":150.0}}"#);
+        assert_eq!(serde_json::from_str::<ExchangeRates>(&json).unwrap(), rates);
+
+        rates.set(Currency::Usd, 0.0);
+        assert_eq!(rates.get(Currency::Usd), None);
+    }
+}
@@ -1,10 +1,13 @@
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 
-#[derive(Clone, PartialEq, Deserialize, Serialize, Default)]
+#[derive(Clone, PartialEq, Deserialize, Serialize, Default, Debug)]
 pub struct StockData {
     pub date: String,
     pub code: String,
     pub name: String,
+    #[serde(default)]
+    pub kana: Option<String>, // 銘柄名の読み
     pub market_category: String,
     pub industry_code_33: Option<String>,
     pub industry_category_33: Option<String>,
@@ -13,3 +16,87 @@ pub struct StockData {
     pub size_code: Option<String>,
     pub size_category: Option<String>,
 }
+
+/// 日足 (始値・高値・安値・終値・出来高)
+#[derive(Clone, PartialEq, Deserialize, Serialize, Debug)]
+pub struct DailyPrice {
+    pub date: NaiveDate,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: u64,
+}
+
+/// 1 株あたりの配当の履歴
+#[derive(Clone, PartialEq, Deserialize, Serialize, Debug)]
+pub struct DividendHistory {
+    pub ex_date: NaiveDate, // 権利落ち日
+    pub amount: f64,        // 1 株あたり配当 [円]
+}
+
+impl StockData {
+    /// 入力に対する一致の度合い。小さいほど候補の上位に表示する。
+    ///
+    /// 銘柄コードの前方一致、銘柄名・読みの前方一致、部分一致の順。一致しない場合は `None`。
+    pub fn match_rank(&self, query: &str) -> Option<u8> {
+        let query = normalize(query);
+        if query.is_empty() {
+            return None;
+        }
+        let name = normalize(&self.name);
+        let kana = self.kana.as_deref().map(normalize).unwrap_or_default();
+
+        if normalize(&self.code).starts_with(&query) {
+            Some(0)
+        } else if name.starts_with(&query) || kana.starts_with(&query) {
+            Some(1)
+        } else if name.contains(&query) || kana.contains(&query) {
+            Some(2)
+        } else {
+            None
+        }
+    }
+}
+
+/// 全角英数字を半角に、カタカナをひらがなに、英字を大文字にそろえる。
+pub fn normalize(s: &str) -> String {
+    s.trim()
+        .chars()
+        .map(|c| match c {
+            // 全角英数字は半角から 0xFEE0 ずれている
+            '０'..='９' | 'Ａ'..='Ｚ' | 'ａ'..='ｚ' => {
+                char::from_u32(c as u32 - 0xFEE0).unwrap_or(c)
+            }
+            'ァ'..='ヶ' => char::from_u32(c as u32 - 'ァ' as u32 + 'ぁ' as u32).unwrap_or(c),
+            _ => c,
+        })
+        .flat_map(char::to_uppercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toyota() -> StockData {
+        StockData {
+            code: "7203".into(),
+            name: "トヨタ自動車".into(),
+            kana: Some("とよたじどうしゃ".into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_match_rank() {
+        let stock = toyota();
+        assert_eq!(stock.match_rank("72"), Some(0));
+        assert_eq!(stock.match_rank("７２０"), Some(0));
+        assert_eq!(stock.match_rank("トヨタ"), Some(1));
+        assert_eq!(stock.match_rank("とよた"), Some(1));
+        assert_eq!(stock.match_rank("ジドウシャ"), Some(2));
+        assert_eq!(stock.match_rank("ホンダ"), None);
+        assert_eq!(stock.match_rank(" "), None);
+    }
+}
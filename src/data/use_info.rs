@@ -1,14 +1,58 @@
+use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use std::rc::Rc;
 use yew::Reducible;
 
+use crate::{services::oauth_google::TokenResponse, setting::SESSION_REFRESH_MARGIN_SECS};
+
 #[derive(Clone, PartialEq, Serialize, Deserialize, Default, Debug)]
 pub struct UserInfo {
-    pub auth_code: Option<String>,
+    pub access_token: Option<String>,
+    pub refresh_token: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>, // アクセストークンの有効期限
     pub name: String,
     pub email: String,
 }
 
+impl UserInfo {
+    /// バックエンドから受け取ったトークンでログイン状態にする。
+    ///
+    /// 名前などが返ってこない場合 (更新時など) は `self` の値を引き継ぐ。
+    pub fn with_token(&self, token: TokenResponse, now: DateTime<Utc>) -> UserInfo {
+        let or_current = |value: String, current: &String| {
+            if value.is_empty() {
+                current.clone()
+            } else {
+                value
+            }
+        };
+        UserInfo {
+            access_token: Some(token.access_token),
+            refresh_token: token.refresh_token.or_else(|| self.refresh_token.clone()),
+            expires_at: Some(now + Duration::seconds(token.expires_in)),
+            name: or_current(token.name, &self.name),
+            email: or_current(token.email, &self.email),
+        }
+    }
+
+    /// 有効なアクセストークンを持っている
+    pub fn is_authenticated(&self, now: DateTime<Utc>) -> bool {
+        self.access_token.is_some() && self.expires_at.is_some_and(|expires_at| now < expires_at)
+    }
+
+    /// 期限切れで、更新もできない
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        !self.is_authenticated(now) && self.refresh_token.is_none()
+    }
+
+    /// アクセストークンを更新する日時
+    pub fn refresh_at(&self) -> Option<DateTime<Utc>> {
+        self.refresh_token.as_ref()?;
+        self.expires_at
+            .map(|expires_at| expires_at - Duration::seconds(SESSION_REFRESH_MARGIN_SECS))
+    }
+}
+
 impl Reducible for UserInfo {
     type Action = UserInfo;
 
@@ -21,13 +65,19 @@ impl Reducible for UserInfo {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_user_info_serialization() {
-        let user = UserInfo {
-            auth_code: Some("test_code".into()),
+    fn token(refresh_token: Option<&str>) -> TokenResponse {
+        TokenResponse {
+            access_token: "access".into(),
+            expires_in: 3600,
+            refresh_token: refresh_token.map(Into::into),
             name: "Taro".into(),
             email: "taro@example.com".into(),
-        };
+        }
+    }
+
+    #[test]
+    fn test_user_info_serialization() {
+        let user = UserInfo::default().with_token(token(Some("refresh")), Utc::now());
 
         let serialized = serde_json::to_string(&user).unwrap();
         let deserialized: UserInfo = serde_json::from_str(&serialized).unwrap();
@@ -39,12 +89,48 @@ mod tests {
     fn test_reduce_implementation() {
         let original = Rc::new(UserInfo::default());
         let new_user = UserInfo {
-            auth_code: Some("new_code".into()),
+            access_token: Some("new_token".into()),
             name: "Jiro".into(),
             email: "jiro@example.com".into(),
+            ..Default::default()
         };
 
         let reduced = original.reduce(new_user.clone());
         assert_eq!(*reduced, new_user);
     }
+
+    #[test]
+    fn test_session_expiry() {
+        let now = Utc::now();
+        let user = UserInfo::default().with_token(token(None), now);
+
+        assert!(user.is_authenticated(now + Duration::minutes(59)));
+        assert!(user.is_expired(now + Duration::hours(1)));
+        assert_eq!(user.refresh_at(), None);
+        assert!(UserInfo::default().is_expired(now));
+    }
+
+    #[test]
+    fn test_refresh_keeps_profile() {
+        let now = Utc::now();
+        let user = UserInfo::default().with_token(token(Some("refresh")), now);
+        let refreshed = user.with_token(
+            TokenResponse {
+                refresh_token: None,
+                name: String::new(),
+                email: String::new(),
+                ..token(None)
+            },
+            now + Duration::hours(1),
+        );
+
+        assert!(!user.is_expired(now + Duration::hours(2)));
+        assert_eq!(
+            user.refresh_at(),
+            Some(now + Duration::seconds(3600 - SESSION_REFRESH_MARGIN_SECS))
+        );
+        assert_eq!(refreshed.refresh_token, Some("refresh".to_string()));
+        assert_eq!(refreshed.name, "Taro");
+        assert_eq!(refreshed.expires_at, Some(now + Duration::hours(2)));
+    }
 }
@@ -0,0 +1,249 @@
+use serde::{Deserialize, Serialize};
+use std::rc::Rc;
+use thiserror::Error;
+use yew::Reducible;
+
+/// 最初から用意しておくリストの名前
+pub const DEFAULT_LIST_NAME: &str = "お気に入り";
+
+#[derive(Debug, Error, PartialEq)]
+pub enum WatchlistError {
+    #[error("ウォッチリストの JSON を読み込めません: {0}")]
+    JsonError(String),
+    #[error("リストの名前が空です")]
+    EmptyName,
+}
+
+/// 名前を付けた銘柄コードのリスト
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct WatchGroup {
+    pub name: String,
+    pub codes: Vec<String>, // 表示する順の銘柄コード
+}
+
+impl WatchGroup {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            codes: Vec::new(),
+        }
+    }
+}
+
+/// 気になる銘柄を名前付きのリストにまとめたもの
+#[derive(Clone, PartialEq, Serialize, Deserialize, Debug)]
+pub struct Watchlist {
+    pub lists: Vec<WatchGroup>,
+}
+
+impl Default for Watchlist {
+    fn default() -> Self {
+        Self {
+            lists: vec![WatchGroup::new(DEFAULT_LIST_NAME)],
+        }
+    }
+}
+
+pub enum WatchlistAction {
+    AddList(String),
+    RemoveList(usize),
+    Add { list: usize, code: String },
+    Remove { list: usize, code: String },
+    Move { list: usize, from: usize, to: usize },
+    Import(Watchlist),
+}
+
+impl Watchlist {
+    /// いずれかのリストに含まれている
+    pub fn contains(&self, code: &str) -> bool {
+        self.lists
+            .iter()
+            .any(|group| group.codes.iter().any(|c| c == code))
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, WatchlistError> {
+        let mut watchlist: Watchlist =
+            serde_json::from_str(json).map_err(|e| WatchlistError::JsonError(e.to_string()))?;
+        if watchlist.lists.is_empty() {
+            watchlist = Watchlist::default();
+        }
+        if watchlist
+            .lists
+            .iter()
+            .any(|group| group.name.trim().is_empty())
+        {
+            return Err(WatchlistError::EmptyName);
+        }
+        Ok(watchlist)
+    }
+
+    /// 読み込んだリストを同じ名前のリストに追加する。重複する銘柄コードは追加しない。
+    fn merge(&mut self, imported: Watchlist) {
+        for group in imported.lists {
+            match self.lists.iter_mut().find(|g| g.name == group.name) {
+                Some(existing) => {
+                    for code in group.codes {
+                        if !existing.codes.contains(&code) {
+                            existing.codes.push(code);
+                        }
+                    }
+                }
+                None => self.lists.push(group),
+            }
+        }
+    }
+}
+
+impl Reducible for Watchlist {
+    type Action = WatchlistAction;
+
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        let mut watchlist = (*self).clone();
+        match action {
+            WatchlistAction::AddList(name) => {
+                let name = name.trim();
+                if name.is_empty() || watchlist.lists.iter().any(|g| g.name == name) {
+                    return self;
+                }
+                watchlist.lists.push(WatchGroup::new(name));
+            }
+            WatchlistAction::RemoveList(list) => {
+                if list < watchlist.lists.len() {
+                    watchlist.lists.remove(list);
+                }
+                if watchlist.lists.is_empty() {
+                    watchlist = Watchlist::default();
+                }
+            }
+            WatchlistAction::Add { list, code } => {
+                let Some(group) = watchlist.lists.get_mut(list) else {
+                    return self;
+                };
+                if group.codes.contains(&code) {
+                    return self;
+                }
+                group.codes.push(code);
+            }
+            WatchlistAction::Remove { list, code } => {
+                let Some(group) = watchlist.lists.get_mut(list) else {
+                    return self;
+                };
+                group.codes.retain(|c| *c != code);
+            }
+            WatchlistAction::Move { list, from, to } => {
+                let Some(group) = watchlist.lists.get_mut(list) else {
+                    return self;
+                };
+                if from >= group.codes.len() || to >= group.codes.len() {
+                    return self;
+                }
+                let code = group.codes.remove(from);
+                group.codes.insert(to, code);
+            }
+            WatchlistAction::Import(imported) => watchlist.merge(imported),
+        }
+        watchlist.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reduce(watchlist: Watchlist, actions: Vec<WatchlistAction>) -> Watchlist {
+        let reduced = actions
+            .into_iter()
+            .fold(Rc::new(watchlist), |state, action| state.reduce(action));
+        (*reduced).clone()
+    }
+
+    #[test]
+    fn test_add_remove_and_move() {
+        let code = |c: &str| c.to_string();
+        let watchlist = reduce(
+            Watchlist::default(),
+            vec![
+                WatchlistAction::Add {
+                    list: 0,
+                    code: code("7203"),
+                },
+                WatchlistAction::Add {
+                    list: 0,
+                    code: code("6758"),
+                },
+                WatchlistAction::Add {
+                    list: 0,
+                    code: code("7203"),
+                },
+                WatchlistAction::Add {
+                    list: 0,
+                    code: code("9984"),
+                },
+                WatchlistAction::Move {
+                    list: 0,
+                    from: 2,
+                    to: 0,
+                },
+                WatchlistAction::Remove {
+                    list: 0,
+                    code: code("6758"),
+                },
+            ],
+        );
+
+        assert_eq!(watchlist.lists[0].codes, vec!["9984", "7203"]);
+        assert!(watchlist.contains("7203"));
+        assert!(!watchlist.contains("6758"));
+    }
+
+    #[test]
+    fn test_lists() {
+        let watchlist = reduce(
+            Watchlist::default(),
+            vec![
+                WatchlistAction::AddList("高配当".to_string()),
+                WatchlistAction::AddList(" ".to_string()),
+                WatchlistAction::AddList("高配当".to_string()),
+            ],
+        );
+        assert_eq!(watchlist.lists.len(), 2);
+
+        // 最後のリストを消しても空のリストを 1 つ残す
+        let watchlist = reduce(
+            watchlist,
+            vec![
+                WatchlistAction::RemoveList(1),
+                WatchlistAction::RemoveList(0),
+            ],
+        );
+        assert_eq!(watchlist, Watchlist::default());
+    }
+
+    #[test]
+    fn test_import_json() {
+        let mut watchlist = Watchlist::default();
+        watchlist.lists[0].codes.push("7203".to_string());
+
+        let imported = Watchlist::from_json(
+            r#"{"lists": [
+                {"name": "お気に入り", "codes": ["7203", "8306"]},
+                {"name": "高配当", "codes": ["9434"]}
+            ]}"#,
+        )
+        .unwrap();
+        let watchlist = reduce(watchlist, vec![WatchlistAction::Import(imported)]);
+
+        assert_eq!(watchlist.lists[0].codes, vec!["7203", "8306"]);
+        assert_eq!(watchlist.lists[1].name, "高配当");
+        assert_eq!(Watchlist::from_json(&watchlist.to_json()), Ok(watchlist));
+        assert!(Watchlist::from_json("[]").is_err());
+        assert_eq!(
+            Watchlist::from_json(r#"{"lists": [{"name": "", "codes": []}]}"#),
+            Err(WatchlistError::EmptyName)
+        );
+    }
+}
@@ -1,4 +1,9 @@
+pub mod dividend_calendar;
 pub mod home;
 pub mod layout;
+pub mod link_settings;
 pub mod receipts;
 pub mod search;
+pub mod stock;
+pub mod tax_report;
+pub mod watchlist;
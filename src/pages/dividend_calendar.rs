@@ -0,0 +1,406 @@
+use chrono::Local;
+use gloo::{
+    console,
+    storage::{LocalStorage, Storage},
+};
+use std::collections::HashMap;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+mod analytics;
+mod calendar;
+mod forecast;
+
+use analytics::HoldingAnalytics;
+use calendar::{DividendCalendar, Payment};
+use forecast::{ForecastPayment, Schedule};
+
+use super::{
+    layout::Layout,
+    receipts::{
+        dividend_list::DividendList, domestic_stock::DomesticStock, exchange_rates, receipt_storage,
+    },
+};
+use crate::{app::Route, services::formater::StrFormater};
+
+/// 予想で使う 1 株あたり配当を保存するキー
+const FORECAST_OVERRIDES_KEY: &str = "dividend_forecast_overrides";
+
+/// 配当の受け取りを年・月のヒートマップと銘柄別の受取月で表示する
+#[function_component]
+pub fn DividendCalendarPage() -> Html {
+    let dividends = use_state(Vec::<DividendList>::new);
+    let stocks = use_state(Vec::<DomesticStock>::new);
+    let selected = use_state(|| None::<(i32, u32)>);
+    let overrides = use_state(|| {
+        LocalStorage::get::<HashMap<String, f64>>(FORECAST_OVERRIDES_KEY).unwrap_or_default()
+    });
+
+    {
+        let dividends = dividends.clone();
+        let stocks = stocks.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                match receipt_storage::load::<DividendList>().await {
+                    Ok(loaded) => dividends.set(loaded),
+                    Err(err) => console::log!(err.to_string()),
+                }
+                match receipt_storage::load::<DomesticStock>().await {
+                    Ok(loaded) => stocks.set(loaded),
+                    Err(err) => console::log!(err.to_string()),
+                }
+            });
+        });
+    }
+
+    // 外貨の配当は円に換算して集計する
+    let rates = exchange_rates::load_rates();
+    let conversion = exchange_rates::convert_to_jpy(&dividends, &rates);
+    let dividends = conversion.converted;
+    let calendar = DividendCalendar::new(&dividends);
+    let today = Local::now().date_naive();
+    let schedules = forecast::build_schedules(&dividends, today);
+    let payments = forecast::forecast(&schedules, &overrides, today);
+    let analytics = analytics::analyze(&dividends, &stocks, today);
+
+    html! {
+        <Layout>
+            <div class="card shadow-sm mb-4">
+                <div class="card-header bg-info text-white">
+                    <h5 class="mb-0">{ "配当カレンダー" }</h5>
+                </div>
+                <div class="card-body">
+                    { exchange_rates::render_missing_rates(&conversion.missing) }
+                    if calendar.months.is_empty() {
+                        <p class="text-muted mb-0">{ "受取金ページで配当金の CSV を取り込んでください。" }</p>
+                    } else {
+                        { render_heatmap(&calendar, &selected) }
+                        if let Some((year, month)) = *selected {
+                            { render_payments(year, month, calendar.payments(year, month)) }
+                        }
+                    }
+                </div>
+            </div>
+            if !calendar.months.is_empty() {
+                { render_schedules(&calendar) }
+            }
+            if !schedules.is_empty() {
+                { render_forecast(&schedules, &payments, &overrides) }
+            }
+            if !analytics.is_empty() {
+                { render_analytics(&analytics) }
+            }
+        </Layout>
+    }
+}
+
+fn render_heatmap(
+    calendar: &DividendCalendar,
+    selected: &UseStateHandle<Option<(i32, u32)>>,
+) -> Html {
+    let max = calendar.max_month_total().max(1) as f64;
+    html! {
+        <div class="table-responsive">
+            <table class="table table-sm table-bordered text-end align-middle">
+                <thead>
+                    <tr>
+                        <th scope="col"></th>
+                        { for (1..=12).map(|month| html! { <th scope="col" class="text-center">{ format!("{}月", month) }</th> }) }
+                        <th scope="col" class="text-center">{ "年計" }</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    { for calendar.years().into_iter().rev().map(|year| html! {
+                        <tr>
+                            <th scope="row" class="text-nowrap">{ format!("{}年", year) }</th>
+                            { for (1..=12).map(|month| {
+                                let total = calendar.month_total(year, month);
+                                let is_selected = **selected == Some((year, month));
+                                let style = format!(
+                                    "background-color: rgba(13, 202, 240, {:.2}); cursor: pointer;",
+                                    total as f64 / max * 0.8
+                                );
+                                let class = if is_selected { "text-nowrap border border-2 border-dark" } else { "text-nowrap" };
+                                let selected = selected.clone();
+                                let onclick = Callback::from(move |_| {
+                                    selected.set((!is_selected && total > 0).then_some((year, month)))
+                                });
+                                html! {
+                                    <td {class} {style} {onclick}>
+                                        { if total > 0 { total.to_string().as_str().format_number() } else { String::new() } }
+                                    </td>
+                                }
+                            })}
+                            <td class="text-nowrap fw-bold">{ calendar.year_total(year).to_string().as_str().format_yen() }</td>
+                        </tr>
+                    })}
+                </tbody>
+            </table>
+        </div>
+    }
+}
+
+fn render_payments(year: i32, month: u32, payments: &[Payment]) -> Html {
+    html! {
+        <>
+            <h6 class="mt-3">{ format!("{}年{}月の受け取り", year, month) }</h6>
+            <table class="table table-sm table-hover">
+                <thead>
+                    <tr>
+                        <th scope="col">{ "銘柄コード" }</th>
+                        <th scope="col">{ "銘柄名" }</th>
+                        <th scope="col" class="text-end">{ "受取金額" }</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    { for payments.iter().map(|payment| html! {
+                        <tr>
+                            <td>{ render_code(&payment.code, &payment.name) }</td>
+                            <td>{ &payment.name }</td>
+                            <td class="text-end text-nowrap">{ payment.net.to_string().as_str().format_yen() }</td>
+                        </tr>
+                    })}
+                </tbody>
+            </table>
+        </>
+    }
+}
+
+fn render_schedules(calendar: &DividendCalendar) -> Html {
+    html! {
+        <div class="card shadow-sm">
+            <div class="card-header">
+                <h5 class="mb-0">{ "銘柄別の受取月" }</h5>
+            </div>
+            <div class="card-body table-responsive">
+                <table class="table table-sm table-hover text-center align-middle">
+                    <thead>
+                        <tr>
+                            <th scope="col" class="text-start">{ "銘柄" }</th>
+                            { for (1..=12).map(|month| html! { <th scope="col">{ format!("{}月", month) }</th> }) }
+                        </tr>
+                    </thead>
+                    <tbody>
+                        { for calendar.schedules().iter().map(|schedule| html! {
+                            <tr>
+                                <th scope="row" class="text-start text-nowrap">
+                                    { render_code(&schedule.code, &schedule.name) }
+                                    <span class="ms-2 fw-normal">{ &schedule.name }</span>
+                                </th>
+                                { for schedule.months.iter().map(|&net| html! {
+                                    <td title={(net > 0).then(|| net.to_string().as_str().format_yen())}>
+                                        { if net > 0 { html! { <span class="text-info">{ "●" }</span> } } else { html! {} } }
+                                    </td>
+                                })}
+                            </tr>
+                        })}
+                    </tbody>
+                </table>
+            </div>
+        </div>
+    }
+}
+
+fn render_forecast(
+    schedules: &[Schedule],
+    payments: &[ForecastPayment],
+    overrides: &UseStateHandle<HashMap<String, f64>>,
+) -> Html {
+    // (年, 月) ごとの税引前・税額・受取金額
+    let mut months: Vec<((i32, u32), [i64; 3])> = Vec::new();
+    for payment in payments {
+        let key = (payment.year, payment.month);
+        let index = match months.iter().position(|(k, _)| *k == key) {
+            Some(index) => index,
+            None => {
+                months.push((key, [0; 3]));
+                months.len() - 1
+            }
+        };
+        let totals = &mut months[index].1;
+        totals[0] += payment.gross;
+        totals[1] += payment.tax;
+        totals[2] += payment.net;
+    }
+    let total = |i: usize| months.iter().map(|(_, totals)| totals[i]).sum::<i64>();
+    let yen = |amount: i64| amount.to_string().as_str().format_yen();
+
+    html! {
+        <div class="card shadow-sm mt-4">
+            <div class="card-header">
+                <h5 class="mb-0">{ "今後 12 か月の配当予想" }</h5>
+            </div>
+            <div class="card-body">
+                <p class="text-muted small">
+                    { "直近 1 年の受取月と 1 株あたり配当、最後に受け取ったときの株数が続くと仮定しています。" }
+                </p>
+                <div class="table-responsive">
+                    <table class="table table-sm table-hover text-end align-middle">
+                        <thead>
+                            <tr>
+                                <th scope="col" class="text-start">{ "受取月" }</th>
+                                <th scope="col">{ "税引前" }</th>
+                                <th scope="col">{ "税額" }</th>
+                                <th scope="col">{ "受取金額" }</th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                            { for months.iter().map(|((year, month), totals)| html! {
+                                <tr>
+                                    <th scope="row" class="text-start">{ format!("{}年{}月", year, month) }</th>
+                                    <td>{ yen(totals[0]) }</td>
+                                    <td>{ yen(totals[1]) }</td>
+                                    <td>{ yen(totals[2]) }</td>
+                                </tr>
+                            })}
+                        </tbody>
+                        <tfoot>
+                            <tr class="fw-bold">
+                                <th scope="row" class="text-start">{ "年間合計" }</th>
+                                <td>{ yen(total(0)) }</td>
+                                <td>{ yen(total(1)) }</td>
+                                <td>{ yen(total(2)) }</td>
+                            </tr>
+                        </tfoot>
+                    </table>
+                </div>
+
+                <h6 class="mt-3">{ "銘柄ごとの 1 株あたり配当" }</h6>
+                <p class="text-muted small">
+                    { "予想に使う 1 株あたり配当は銘柄ごとに指定し、同じ銘柄の全ての口座に使います。" }
+                </p>
+                <table class="table table-sm align-middle">
+                    <thead>
+                        <tr>
+                            <th scope="col">{ "銘柄" }</th>
+                            <th scope="col" class="text-end">{ "株数" }</th>
+                            <th scope="col">{ "実績 (受取月: 1 株あたり)" }</th>
+                            <th scope="col">{ "予想に使う 1 株あたり配当" }</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        { for schedules.iter().enumerate().map(|(i, schedule)| {
+                            // 同じ銘柄の口座は並んでいるので、入力欄は最初の行にだけ置き、残りの行にまたがらせる
+                            let is_first = i == 0 || schedules[i - 1].code != schedule.code;
+                            let rowspan = schedules[i..]
+                                .iter()
+                                .take_while(|s| s.code == schedule.code)
+                                .count();
+                            let code = schedule.code.clone();
+                            let overrides_handle = overrides.clone();
+                            let onchange = Callback::from(move |e: Event| {
+                                let input: HtmlInputElement = e.target_unchecked_into();
+                                let mut overrides = (*overrides_handle).clone();
+                                match input.value().trim().parse::<f64>() {
+                                    Ok(value) if value >= 0.0 => overrides.insert(code.clone(), value),
+                                    _ => overrides.remove(&code),
+                                };
+                                let _ = LocalStorage::set(FORECAST_OVERRIDES_KEY, &overrides);
+                                overrides_handle.set(overrides);
+                            });
+                            let observed = schedule
+                                .months
+                                .iter()
+                                .map(|(month, per_share)| format!("{}月: {}円", month, per_share))
+                                .collect::<Vec<_>>()
+                                .join(" / ");
+                            html! {
+                                <tr>
+                                    <td class="text-nowrap">
+                                        { render_code(&schedule.code, &schedule.name) }
+                                        <span class="ms-2">{ &schedule.name }</span>
+                                        if schedule.tax_exempt {
+                                            <span class="badge bg-success ms-2">{ "NISA" }</span>
+                                        } else if !schedule.account.is_empty() {
+                                            <span class="badge bg-secondary ms-2">{ &schedule.account }</span>
+                                        }
+                                    </td>
+                                    <td class="text-end">{ schedule.shares.to_string().as_str().format_number() }</td>
+                                    <td class="small">{ observed }</td>
+                                    if is_first {
+                                        <td rowspan={rowspan.to_string()}>
+                                            <input
+                                                type="number"
+                                                class="form-control form-control-sm"
+                                                min="0"
+                                                step="0.01"
+                                                placeholder="実績を使う"
+                                                value={overrides.get(&schedule.code).map(|v| v.to_string()).unwrap_or_default()}
+                                                {onchange}
+                                            />
+                                        </td>
+                                    }
+                                </tr>
+                            }
+                        })}
+                    </tbody>
+                </table>
+            </div>
+        </div>
+    }
+}
+
+fn render_analytics(analytics: &[HoldingAnalytics]) -> Html {
+    let percent =
+        |rate: Option<f64>| rate.map_or("-".to_string(), |rate| format!("{:.2}%", rate * 100.0));
+    let per_share = |amount: f64| format!("{:.2}", amount).as_str().format_yen();
+
+    html! {
+        <div class="card shadow-sm mt-4">
+            <div class="card-header">
+                <h5 class="mb-0">{ "取得利回りランキング" }</h5>
+            </div>
+            <div class="card-body table-responsive">
+                <p class="text-muted small">
+                    { "取得利回りは直近 12 か月の 1 株あたり配当を、国内株式の取引の平均取得価額で割ったものです。" }
+                </p>
+                <table class="table table-sm table-hover text-end align-middle">
+                    <thead>
+                        <tr>
+                            <th scope="col">{ "順位" }</th>
+                            <th scope="col" class="text-start">{ "銘柄" }</th>
+                            <th scope="col">{ "1 株あたり配当 (直近 12 か月)" }</th>
+                            <th scope="col">{ "前年" }</th>
+                            <th scope="col">{ "増配率" }</th>
+                            <th scope="col">{ "平均取得価額" }</th>
+                            <th scope="col">{ "取得利回り" }</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        { for analytics.iter().enumerate().map(|(rank, holding)| {
+                            let growth_class = match holding.growth {
+                                Some(growth) if growth > 0.0 => "text-success",
+                                Some(growth) if growth < 0.0 => "text-danger",
+                                _ => "",
+                            };
+                            html! {
+                                <tr>
+                                    <td>{ rank + 1 }</td>
+                                    <th scope="row" class="text-start text-nowrap fw-normal">
+                                        { render_code(&holding.code, &holding.name) }
+                                        <span class="ms-2">{ &holding.name }</span>
+                                    </th>
+                                    <td>{ per_share(holding.ttm_per_share) }</td>
+                                    <td>{ if holding.previous_per_share > 0.0 { per_share(holding.previous_per_share) } else { "-".to_string() } }</td>
+                                    <td class={growth_class}>{ percent(holding.growth) }</td>
+                                    <td>{ holding.purchase_price.map_or("-".to_string(), per_share) }</td>
+                                    <td class="fw-bold">{ percent(holding.yield_on_cost) }</td>
+                                </tr>
+                            }
+                        })}
+                    </tbody>
+                </table>
+            </div>
+        </div>
+    }
+}
+
+/// 銘柄コードがあれば銘柄ページへのリンクにする
+fn render_code(code: &str, name: &str) -> Html {
+    if code == name {
+        return html! {};
+    }
+    html! { <Link<Route> to={Route::Stock { code: code.to_string() }}>{ code }</Link<Route>> }
+}
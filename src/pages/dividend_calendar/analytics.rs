@@ -0,0 +1,178 @@
+use chrono::{Months, NaiveDate};
+use std::collections::HashMap;
+
+use super::calendar::security_key;
+use crate::pages::receipts::{
+    dividend_list::DividendList, domestic_stock::DomesticStock, receipt_template::ReceiptProps,
+};
+
+/// 銘柄ごとの配当の指標
+#[derive(Clone, PartialEq, Debug)]
+pub struct HoldingAnalytics {
+    pub code: String,
+    pub name: String,
+    pub ttm_per_share: f64,          // 直近 12 か月の 1 株あたり配当
+    pub previous_per_share: f64,     // その前の 12 か月の 1 株あたり配当
+    pub purchase_price: Option<f64>, // 平均取得価額
+    pub growth: Option<f64>,         // 前年比の増配率
+    pub yield_on_cost: Option<f64>,  // 取得価額に対する利回り
+}
+
+/// 直近 12 か月に配当のあった銘柄の指標を、取得利回りの高い順に求める。
+///
+/// 同じ銘柄を複数の口座で持っている場合、同じ日の 1 株あたり配当は 1 回分として数える。
+/// 取得価額は国内株式の取引のうち、最も新しい取引の平均取得価額を使う。
+pub fn analyze(
+    dividends: &[DividendList],
+    stocks: &[DomesticStock],
+    today: NaiveDate,
+) -> Vec<HoldingAnalytics> {
+    let year_ago = today - Months::new(12);
+    let two_years_ago = today - Months::new(24);
+
+    // 口座ごとの行を (銘柄, 受取日) で 1 回分にまとめる
+    let mut payments: HashMap<(String, NaiveDate), (&DividendList, f64)> = HashMap::new();
+    for dividend in dividends {
+        let (Some(date), Some(per_share)) = (dividend.settlement_date, dividend.per_share()) else {
+            continue;
+        };
+        if date > today || date <= two_years_ago {
+            continue;
+        }
+        payments
+            .entry((security_key(dividend), date))
+            .or_insert((dividend, per_share));
+    }
+
+    let mut by_security: HashMap<String, (String, f64, f64)> = HashMap::new();
+    for ((key, date), (dividend, per_share)) in payments {
+        let (_, ttm, previous) = by_security
+            .entry(key)
+            .or_insert_with(|| (dividend.get_security_name().to_string(), 0.0, 0.0));
+        if date > year_ago {
+            *ttm += per_share;
+        } else {
+            *previous += per_share;
+        }
+    }
+
+    let mut purchase_prices: HashMap<&str, (Option<NaiveDate>, f64)> = HashMap::new();
+    for stock in stocks {
+        let Some(price) = stock.purchase_price.filter(|&price| price > 0.0) else {
+            continue;
+        };
+        let entry = purchase_prices
+            .entry(stock.get_security_code())
+            .or_insert((stock.trade_date, price));
+        if stock.trade_date >= entry.0 {
+            *entry = (stock.trade_date, price);
+        }
+    }
+
+    let mut analytics: Vec<HoldingAnalytics> = by_security
+        .into_iter()
+        .filter(|(_, (_, ttm, _))| *ttm > 0.0)
+        .map(|(code, (name, ttm, previous))| {
+            let purchase_price = purchase_prices.get(code.as_str()).map(|&(_, price)| price);
+            HoldingAnalytics {
+                growth: (previous > 0.0).then(|| ttm / previous - 1.0),
+                yield_on_cost: purchase_price.map(|price| ttm / price),
+                code,
+                name,
+                ttm_per_share: ttm,
+                previous_per_share: previous,
+                purchase_price,
+            }
+        })
+        .collect();
+    analytics.sort_by(|a, b| {
+        b.yield_on_cost
+            .unwrap_or(-1.0)
+            .total_cmp(&a.yield_on_cost.unwrap_or(-1.0))
+            .then_with(|| a.code.cmp(&b.code))
+    });
+    analytics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dividend(date: (i32, u32, u32), code: &str, unit_price: f64) -> DividendList {
+        DividendList {
+            settlement_date: NaiveDate::from_ymd_opt(date.0, date.1, date.2),
+            security_code: Some(code.into()),
+            security_name: Some(format!("銘柄{}", code)),
+            unit_price: Some(unit_price),
+            shares: Some(100),
+            ..Default::default()
+        }
+    }
+
+    fn stock(date: (i32, u32, u32), code: &str, purchase_price: f64) -> DomesticStock {
+        DomesticStock {
+            trade_date: NaiveDate::from_ymd_opt(date.0, date.1, date.2),
+            security_code: Some(code.into()),
+            purchase_price: Some(purchase_price),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_analyze() {
+        let today = NaiveDate::from_ymd_opt(2024, 12, 15).unwrap();
+        let dividends = vec![
+            dividend((2022, 12, 1), "7203", 10.0),
+            dividend((2023, 6, 1), "7203", 30.0),
+            dividend((2023, 12, 1), "7203", 30.0),
+            dividend((2024, 6, 1), "7203", 35.0),
+            dividend((2024, 12, 1), "7203", 40.0),
+            dividend((2024, 3, 1), "8306", 20.0),
+            dividend((2023, 3, 1), "9432", 5.0),
+        ];
+        let stocks = vec![
+            stock((2023, 1, 10), "7203", 1000.0),
+            stock((2024, 1, 10), "7203", 1500.0),
+            stock((2024, 1, 10), "8306", 1000.0),
+        ];
+        let analytics = analyze(&dividends, &stocks, today);
+
+        assert_eq!(analytics.len(), 2);
+        assert_eq!(analytics[0].code, "7203");
+        assert_eq!(analytics[0].ttm_per_share, 75.0);
+        assert_eq!(analytics[0].previous_per_share, 60.0);
+        assert_eq!(analytics[0].purchase_price, Some(1500.0));
+        assert_eq!(analytics[0].growth, Some(0.25));
+        assert_eq!(analytics[0].yield_on_cost, Some(0.05));
+
+        assert_eq!(analytics[1].code, "8306");
+        assert_eq!(analytics[1].growth, None);
+        assert_eq!(analytics[1].yield_on_cost, Some(0.02));
+    }
+
+    #[test]
+    fn test_analyze_multiple_accounts() {
+        let today = NaiveDate::from_ymd_opt(2024, 12, 15).unwrap();
+        let nisa = |date| DividendList {
+            account: Some("NISA".into()),
+            ..dividend(date, "7203", 40.0)
+        };
+        let dividends = vec![
+            dividend((2024, 6, 1), "7203", 35.0),
+            dividend((2024, 12, 1), "7203", 40.0),
+            nisa((2024, 12, 1)),
+        ];
+        let analytics = analyze(&dividends, &[], today);
+
+        assert_eq!(analytics[0].ttm_per_share, 75.0);
+    }
+
+    #[test]
+    fn test_analyze_without_purchase_price() {
+        let today = NaiveDate::from_ymd_opt(2024, 12, 15).unwrap();
+        let analytics = analyze(&[dividend((2024, 6, 1), "7203", 35.0)], &[], today);
+
+        assert_eq!(analytics[0].purchase_price, None);
+        assert_eq!(analytics[0].yield_on_cost, None);
+    }
+}
@@ -0,0 +1,168 @@
+use chrono::Datelike;
+use std::collections::BTreeMap;
+
+use crate::pages::receipts::{dividend_list::DividendList, receipt_template::ReceiptProps};
+
+/// 1 か月に 1 銘柄から受け取った配当
+#[derive(Clone, PartialEq, Debug)]
+pub struct Payment {
+    pub code: String,
+    pub name: String,
+    pub net: i64, // 受取金額 (税引後)
+}
+
+/// 年・月ごとの受取金額
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct DividendCalendar {
+    pub months: BTreeMap<(i32, u32), Vec<Payment>>, // (年, 月) ごとの銘柄別の受取金額 (金額の多い順)
+}
+
+/// 銘柄ごとの月別の受取金額
+#[derive(Clone, PartialEq, Debug)]
+pub struct SecuritySchedule {
+    pub code: String,
+    pub name: String,
+    pub months: [i64; 12], // 1 月から 12 月までの受取金額の合計
+}
+
+impl DividendCalendar {
+    pub fn new(dividends: &[DividendList]) -> Self {
+        let mut months: BTreeMap<(i32, u32), Vec<Payment>> = BTreeMap::new();
+        for dividend in dividends {
+            let (Some(date), Some(net)) = (dividend.get_date(), dividend.net_amount_received)
+            else {
+                continue;
+            };
+            let payments = months.entry((date.year(), date.month())).or_default();
+            let code = security_key(dividend);
+            match payments.iter_mut().find(|p| p.code == code) {
+                Some(payment) => payment.net += net as i64,
+                None => payments.push(Payment {
+                    code,
+                    name: dividend.get_security_name().to_string(),
+                    net: net as i64,
+                }),
+            }
+        }
+        for payments in months.values_mut() {
+            payments.sort_by(|a, b| b.net.cmp(&a.net).then_with(|| a.code.cmp(&b.code)));
+        }
+        Self { months }
+    }
+
+    /// 受け取りのあった年 (古い順)
+    pub fn years(&self) -> Vec<i32> {
+        let mut years: Vec<i32> = self.months.keys().map(|(year, _)| *year).collect();
+        years.dedup();
+        years
+    }
+
+    pub fn payments(&self, year: i32, month: u32) -> &[Payment] {
+        self.months
+            .get(&(year, month))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    pub fn month_total(&self, year: i32, month: u32) -> i64 {
+        self.payments(year, month).iter().map(|p| p.net).sum()
+    }
+
+    pub fn year_total(&self, year: i32) -> i64 {
+        (1..=12).map(|month| self.month_total(year, month)).sum()
+    }
+
+    /// ヒートマップの色の濃さの基準にする最大の月額
+    pub fn max_month_total(&self) -> i64 {
+        self.months
+            .values()
+            .map(|payments| payments.iter().map(|p| p.net).sum())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// 銘柄ごとに、何月に受け取っているか (受取金額の多い順)
+    pub fn schedules(&self) -> Vec<SecuritySchedule> {
+        let mut schedules: Vec<SecuritySchedule> = Vec::new();
+        for ((_, month), payments) in &self.months {
+            for payment in payments {
+                let index = schedules.iter().position(|s| s.code == payment.code);
+                let schedule = match index {
+                    Some(i) => &mut schedules[i],
+                    None => {
+                        schedules.push(SecuritySchedule {
+                            code: payment.code.clone(),
+                            name: payment.name.clone(),
+                            months: [0; 12],
+                        });
+                        schedules.last_mut().unwrap()
+                    }
+                };
+                schedule.months[*month as usize - 1] += payment.net;
+            }
+        }
+        schedules.sort_by_key(|s| std::cmp::Reverse(s.months.iter().sum::<i64>()));
+        schedules
+    }
+}
+
+/// 銘柄コードのない投資信託などは銘柄名でまとめる
+pub(super) fn security_key(dividend: &DividendList) -> String {
+    match dividend.get_security_code() {
+        "" => dividend.get_security_name().to_string(),
+        code => code.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn dividend(date: (i32, u32, u32), code: &str, net: i32) -> DividendList {
+        DividendList {
+            settlement_date: NaiveDate::from_ymd_opt(date.0, date.1, date.2),
+            security_code: (!code.is_empty()).then(|| code.to_string()),
+            security_name: Some(format!("銘柄{}", code)),
+            net_amount_received: Some(net as f64),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_calendar_by_month() {
+        let calendar = DividendCalendar::new(&[
+            dividend((2023, 6, 28), "7203", 3000),
+            dividend((2023, 6, 30), "8306", 1000),
+            dividend((2023, 6, 30), "7203", 500),
+            dividend((2024, 12, 1), "7203", 4000),
+            DividendList::default(),
+        ]);
+
+        assert_eq!(calendar.years(), vec![2023, 2024]);
+        assert_eq!(calendar.month_total(2023, 6), 4500);
+        assert_eq!(calendar.payments(2023, 6)[0].code, "7203");
+        assert_eq!(calendar.payments(2023, 6)[0].net, 3500);
+        assert!(calendar.payments(2023, 7).is_empty());
+        assert_eq!(calendar.year_total(2024), 4000);
+        assert_eq!(calendar.max_month_total(), 4500);
+    }
+
+    #[test]
+    fn test_schedules() {
+        let calendar = DividendCalendar::new(&[
+            dividend((2023, 6, 28), "7203", 3000),
+            dividend((2023, 12, 1), "7203", 3000),
+            dividend((2024, 6, 28), "7203", 3500),
+            dividend((2024, 3, 15), "", 100),
+        ]);
+        let schedules = calendar.schedules();
+
+        assert_eq!(schedules.len(), 2);
+        assert_eq!(schedules[0].code, "7203");
+        assert_eq!(schedules[0].months[5], 6500);
+        assert_eq!(schedules[0].months[11], 3000);
+        assert_eq!(schedules[1].code, "銘柄");
+        assert_eq!(schedules[1].months[2], 100);
+    }
+}
@@ -0,0 +1,214 @@
+use chrono::{Datelike, Months, NaiveDate};
+use std::collections::{BTreeMap, HashMap};
+
+use super::calendar::security_key;
+use crate::{
+    pages::receipts::{dividend_list::DividendList, receipt_template::ReceiptProps},
+    services::tax_rule::TaxRule,
+};
+
+/// 最後の受け取りからこの月数より前の銘柄は、売却済みとみなして予想しない
+const HELD_WITHIN_MONTHS: u32 = 13;
+
+/// 予想する 1 回分の受け取り
+#[derive(Clone, PartialEq, Debug)]
+pub struct ForecastPayment {
+    pub year: i32,
+    pub month: u32,
+    pub code: String,
+    pub name: String,
+    pub shares: i32,
+    pub per_share: f64, // 1 株あたり配当 [円]
+    pub gross: i64,     // 税引前
+    pub tax: i64,
+    pub net: i64,
+}
+
+/// 銘柄・口座ごとの直近 1 年の受け取り
+#[derive(Clone, PartialEq, Debug)]
+pub struct Schedule {
+    pub code: String,
+    pub name: String,
+    pub account: String,
+    pub shares: i32,                // 最後に受け取ったときの株数
+    pub tax_exempt: bool,           // NISA 口座
+    pub months: BTreeMap<u32, f64>, // 受け取った月と 1 株あたり配当
+}
+
+/// 銘柄・口座ごとに、最後の受け取りまでの 1 年間の受取月と 1 株あたり配当を求める。
+///
+/// 同じ銘柄を NISA と特定口座などで持っている場合は、口座ごとに株数と税額が異なるため別々に予想する。
+pub fn build_schedules(dividends: &[DividendList], today: NaiveDate) -> Vec<Schedule> {
+    let mut by_security: HashMap<(String, String), Vec<&DividendList>> = HashMap::new();
+    for dividend in dividends.iter().filter(|d| d.settlement_date.is_some()) {
+        let account = dividend.account.clone().unwrap_or_default();
+        by_security
+            .entry((security_key(dividend), account))
+            .or_default()
+            .push(dividend);
+    }
+
+    let held_since = today - Months::new(HELD_WITHIN_MONTHS);
+    let mut schedules: Vec<Schedule> = by_security
+        .into_iter()
+        .filter_map(|((code, account), mut rows)| {
+            rows.sort_by_key(|d| d.settlement_date);
+            let latest = *rows.last()?;
+            let latest_date = latest.settlement_date?;
+            if latest_date < held_since {
+                return None;
+            }
+            // 最後の受取月を含めて 12 か月分 (前年の同じ月は含めない)
+            let month_index = |date: NaiveDate| date.year() * 12 + date.month0() as i32;
+            let since = month_index(latest_date) - 12;
+            let mut months = BTreeMap::new();
+            for row in &rows {
+                let date = row.settlement_date?;
+                if month_index(date) <= since {
+                    continue;
+                }
+                let month = date.month();
+                if let Some(per_share) = row.per_share() {
+                    *months.entry(month).or_insert(0.0) += per_share;
+                }
+            }
+            Some(Schedule {
+                code,
+                name: latest.get_security_name().to_string(),
+                tax_exempt: account.contains("NISA"),
+                account,
+                shares: latest.shares.unwrap_or_default(),
+                months,
+            })
+        })
+        .collect();
+    schedules.sort_by(|a, b| a.code.cmp(&b.code).then_with(|| a.account.cmp(&b.account)));
+    schedules
+}
+
+/// 来月からの 12 か月の受け取りを予想する。
+///
+/// `overrides` に銘柄の 1 株あたり配当 (1 回分) があれば、直近の実績の代わりに使う。
+/// 1 株あたり配当は口座によらないため、銘柄コードごとに指定し、その銘柄の全ての口座に使う。
+pub fn forecast(
+    schedules: &[Schedule],
+    overrides: &HashMap<String, f64>,
+    today: NaiveDate,
+) -> Vec<ForecastPayment> {
+    let start =
+        NaiveDate::from_ymd_opt(today.year(), today.month(), 1).unwrap_or(today) + Months::new(1);
+    let mut payments = Vec::new();
+    for offset in 0..12 {
+        let date = start + Months::new(offset);
+        for schedule in schedules {
+            let Some(&observed) = schedule.months.get(&date.month()) else {
+                continue;
+            };
+            let per_share = overrides.get(&schedule.code).copied().unwrap_or(observed);
+            let gross = (per_share * schedule.shares as f64).floor() as i64;
+            let tax = if schedule.tax_exempt {
+                0
+            } else {
+                TaxRule::for_year(date.year()).withhold(gross).total()
+            };
+            payments.push(ForecastPayment {
+                year: date.year(),
+                month: date.month(),
+                code: schedule.code.clone(),
+                name: schedule.name.clone(),
+                shares: schedule.shares,
+                per_share,
+                gross,
+                tax,
+                net: gross - tax,
+            });
+        }
+    }
+    payments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dividend(date: (i32, u32, u32), unit_price: f64, shares: i32) -> DividendList {
+        DividendList {
+            settlement_date: NaiveDate::from_ymd_opt(date.0, date.1, date.2),
+            security_code: Some("7203".into()),
+            security_name: Some("トヨタ自動車".into()),
+            account: Some("特定口座".into()),
+            unit_price: Some(unit_price),
+            shares: Some(shares),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_forecast_uses_last_schedule_and_shares() {
+        let today = NaiveDate::from_ymd_opt(2024, 12, 15).unwrap();
+        let dividends = vec![
+            dividend((2023, 6, 1), 30.0, 100),
+            dividend((2023, 11, 28), 35.0, 100),
+            dividend((2024, 5, 28), 40.0, 100),
+            dividend((2024, 11, 27), 45.0, 200),
+        ];
+        let schedules = build_schedules(&dividends, today);
+        assert_eq!(schedules.len(), 1);
+        assert_eq!(schedules[0].shares, 200);
+        assert_eq!(schedules[0].months, BTreeMap::from([(5, 40.0), (11, 45.0)]));
+
+        let payments = forecast(&schedules, &HashMap::new(), today);
+        assert_eq!(payments.len(), 2);
+        assert_eq!((payments[0].year, payments[0].month), (2025, 5));
+        assert_eq!(payments[0].gross, 8000);
+        assert_eq!(payments[0].tax, 1625);
+        assert_eq!(payments[0].net, 6375);
+        assert_eq!((payments[1].year, payments[1].month), (2025, 11));
+
+        let overrides = HashMap::from([("7203".to_string(), 50.0)]);
+        let payments = forecast(&schedules, &overrides, today);
+        assert_eq!(payments[0].gross, 10000);
+    }
+
+    #[test]
+    fn test_forecast_by_account() {
+        let today = NaiveDate::from_ymd_opt(2024, 12, 15).unwrap();
+        let nisa = |date, shares| DividendList {
+            account: Some("NISA口座(成長投資枠)".into()),
+            ..dividend(date, 45.0, shares)
+        };
+        let dividends = vec![
+            dividend((2024, 5, 28), 40.0, 100),
+            dividend((2024, 11, 27), 45.0, 100),
+            nisa((2024, 5, 28), 300),
+            nisa((2024, 11, 27), 300),
+        ];
+        let schedules = build_schedules(&dividends, today);
+
+        // 口座ごとに分け、1 株あたり配当を口座の数だけ足し合わせない
+        assert_eq!(schedules.len(), 2);
+        assert_eq!(schedules[0].account, "NISA口座(成長投資枠)");
+        assert_eq!(schedules[0].months.get(&11), Some(&45.0));
+        assert_eq!(schedules[1].account, "特定口座");
+        assert_eq!(schedules[1].months, BTreeMap::from([(5, 40.0), (11, 45.0)]));
+
+        let payments = forecast(&schedules, &HashMap::new(), today);
+        let november: Vec<_> = payments.iter().filter(|p| p.month == 11).collect();
+        assert_eq!(november.len(), 2);
+        assert_eq!((november[0].gross, november[0].tax), (13_500, 0));
+        assert_eq!((november[1].gross, november[1].tax), (4_500, 914));
+
+        // 1 株あたり配当の指定は両方の口座に使う
+        let overrides = HashMap::from([("7203".to_string(), 50.0)]);
+        let payments = forecast(&schedules, &overrides, today);
+        let november: Vec<_> = payments.iter().filter(|p| p.month == 11).collect();
+        assert_eq!((november[0].gross, november[1].gross), (15_000, 5_000));
+    }
+
+    #[test]
+    fn test_forecast_skips_sold_securities() {
+        let today = NaiveDate::from_ymd_opt(2024, 12, 15).unwrap();
+        let dividends = vec![dividend((2022, 6, 1), 30.0, 100)];
+        assert!(build_schedules(&dividends, today).is_empty());
+    }
+}
@@ -1,14 +1,17 @@
+use chrono::Utc;
+use gloo::console;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::window;
 use yew::prelude::*;
 use yew_router::prelude::*;
 
-use crate::{app::Route, data::use_info::UserInfo};
+use crate::{app::Route, data::use_info::UserInfo, services::oauth_google};
 
 #[function_component]
 pub fn Layout(props: &yew::html::ChildrenProps) -> Html {
-    let _user_info = use_context::<UserInfo>();
+    let user_info = use_context::<UseReducerHandle<UserInfo>>();
     html! {
         <>
-            // { render_auth_component(user_info) }
             <nav class="navbar bg-dark navbar-expand-lg bg-body-tertiary" data-bs-theme="dark">
                 <div class="container-fluid" style="max-width: 1600px;">
                     <Link<Route> classes="navbar-brand" to={Route::Home}>{ "証券Web" }</Link<Route>>
@@ -20,10 +23,25 @@ pub fn Layout(props: &yew::html::ChildrenProps) -> Html {
                             <li class="nav-item">
                                 <Link<Route> classes="nav-link" to={Route::Search}>{ "銘柄検索" }</Link<Route>>
                             </li>
+                            <li class="nav-item">
+                                <Link<Route> classes="nav-link" to={Route::Watchlist}>{ "ウォッチリスト" }</Link<Route>>
+                            </li>
                             <li class="nav-item">
                                 <Link<Route> classes="nav-link" to={Route::Receipts}>{ "受取金" }</Link<Route>>
                             </li>
+                            <li class="nav-item">
+                                <Link<Route> classes="nav-link" to={Route::TaxReport}>{ "確定申告" }</Link<Route>>
+                            </li>
+                            <li class="nav-item">
+                                <Link<Route> classes="nav-link" to={Route::DividendCalendar}>{ "配当カレンダー" }</Link<Route>>
+                            </li>
+                            <li class="nav-item">
+                                <Link<Route> classes="nav-link" to={Route::LinkSettings}>{ "リンク設定" }</Link<Route>>
+                            </li>
                         </ul>
+                        <div class="ms-auto">
+                            { render_auth_component(user_info) }
+                        </div>
                     </div>
                 </div>
             </nav>
@@ -38,60 +56,92 @@ pub fn Layout(props: &yew::html::ChildrenProps) -> Html {
         </>
     }
 }
-/*
+
 #[function_component]
 fn Login() -> Html {
-    let on_click = Callback::from(move |_| {
-        let future = async {
-            match google_oauth().await {
-                Ok((auth_url, _csrf_token)) => {
-                    let window = window().unwrap();
-                    window.location().set_href(&auth_url.to_string()).unwrap();
-                }
-                Err(err) => console::log!(&err.to_string()),
+    let on_click = Callback::from(move |_| match oauth_google::google_oauth() {
+        Ok(auth_url) => {
+            if let Some(window) = window() {
+                let _ = window.location().set_href(auth_url.as_str());
             }
-        };
-        yew::platform::spawn_local(future);
+        }
+        Err(err) => console::log!(err.to_string()),
     });
 
     html! {
-    <div class="mb-3">
-        <button onclick={on_click} class="btn btn-primary btn-lg">
+        <button onclick={on_click} class="btn btn-outline-light btn-sm">
             <i class="fab fa-google me-2"/>
             { "Googleでログイン" }
         </button>
-    </div>
     }
 }
 
+#[derive(Properties, PartialEq)]
+struct LogoutProps {
+    user_info: UseReducerHandle<UserInfo>,
+}
+
 #[function_component]
-fn Logout() -> Html {
-    let on_click = Callback::from(move |_| {
-        if let Some(storage) = window().and_then(|w| w.local_storage().ok()).flatten() {
-            _ = storage.remove_item("user_info");
-            yew::platform::spawn_local(async move {
-                if let Some(window) = window() {
-                    let _ = window.location().set_href(&Route::Home.to_path());
-                }
-            });
-        }
-    });
+fn Logout(props: &LogoutProps) -> Html {
+    let on_click = {
+        let user_info = props.user_info.clone();
+        Callback::from(move |_| user_info.dispatch(UserInfo::default()))
+    };
 
     html! {
-    <div class="mb-3">
-        <button onclick={on_click} class="btn btn-outline-danger">
-            <i class="fas fa-sign-out-alt me-2"/>
+        <div class="d-flex align-items-center">
+            <span class="navbar-text me-2" title={props.user_info.email.clone()}>{ &props.user_info.name }</span>
+            <button onclick={on_click} class="btn btn-outline-danger btn-sm">
+                <i class="fas fa-sign-out-alt me-2"/>
                 { "ログアウト" }
-        </button>
-    </div>
+            </button>
+        </div>
     }
 }
 
-fn render_auth_component(user_info: Option<UserInfo>) -> Html {
-    let user_info = user_info.unwrap_or_default();
-    match user_info.auth_code {
-        Some(_) => html! { <Logout /> },
-        None => html! { <Login /> },
+fn render_auth_component(user_info: Option<UseReducerHandle<UserInfo>>) -> Html {
+    if !oauth_google::is_configured() {
+        return html! {};
+    }
+    match user_info {
+        Some(user_info) if user_info.is_authenticated(Utc::now()) => {
+            html! { <Logout {user_info} /> }
+        }
+        _ => html! { <Login /> },
+    }
+}
+
+/// バックエンドがログインを必須にしている場合、ログインするまで子要素を表示しない。
+#[function_component]
+pub fn RequireLogin(props: &yew::html::ChildrenProps) -> Html {
+    let user_info = use_context::<UseReducerHandle<UserInfo>>();
+    let login_required = use_state(|| None::<bool>);
+    {
+        let login_required = login_required.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                let config = oauth_google::fetch_auth_config().await;
+                login_required.set(Some(config.login_required));
+            });
+        });
+    }
+
+    let authenticated = user_info.is_some_and(|user_info| user_info.is_authenticated(Utc::now()));
+    match *login_required {
+        Some(false) => props.children.clone(),
+        Some(true) if authenticated => props.children.clone(),
+        Some(true) => html! {
+            <Layout>
+                <div class="alert alert-info d-flex align-items-center" role="alert">
+                    <span class="me-auto">{ "この画面を表示するにはログインしてください" }</span>
+                    <Login />
+                </div>
+            </Layout>
+        },
+        None => html! {
+            <Layout>
+                <div class="spinner-border spinner-border-sm text-info" role="status"></div>
+            </Layout>
+        },
     }
 }
- */
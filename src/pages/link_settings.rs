@@ -0,0 +1,133 @@
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use super::layout::Layout;
+use crate::data::link_provider::{LinkProvider, LinkProviders, LinkProvidersAction};
+
+/// 銘柄情報サイトへのリンクの設定
+#[function_component]
+pub fn LinkSettings() -> Html {
+    let link_providers =
+        use_context::<UseReducerHandle<LinkProviders>>().expect("LinkProviders context");
+
+    let on_add = {
+        let link_providers = link_providers.clone();
+        Callback::from(move |_| {
+            link_providers.dispatch(LinkProvidersAction::Add(LinkProvider::new(
+                "新しいリンク",
+                "https://example.com/{code}",
+                &[],
+            )))
+        })
+    };
+
+    let on_reset = {
+        let link_providers = link_providers.clone();
+        Callback::from(move |_| link_providers.dispatch(LinkProvidersAction::Reset))
+    };
+
+    let len = link_providers.providers.len();
+    html! {
+        <Layout>
+            <div class="card shadow-sm mb-4">
+                <div class="card-header bg-info text-white d-flex align-items-center">
+                    <h5 class="mb-0 me-auto">{ "リンク設定" }</h5>
+                    <button type="button" class="btn btn-sm btn-light me-2" onclick={on_add}>{ "リンクを追加" }</button>
+                    <button type="button" class="btn btn-sm btn-outline-light" onclick={on_reset}>{ "初期設定に戻す" }</button>
+                </div>
+                <div class="card-body">
+                    <p class="text-muted small">
+                        { "URL の {code} は銘柄コード、{name} は銘柄名、{market} はマーケットカテゴリに置き換えます。" }
+                        { "マーケットは「,」区切りで、いずれかを含む銘柄にだけリンクを表示します (空の場合はすべて)。" }
+                    </p>
+                    <div class="table-responsive">
+                        <table class="table align-middle">
+                            <thead>
+                                <tr>
+                                    <th>{ "表示" }</th>
+                                    <th>{ "名前" }</th>
+                                    <th>{ "URL" }</th>
+                                    <th>{ "マーケット" }</th>
+                                    <th></th>
+                                </tr>
+                            </thead>
+                            <tbody>
+                                { for link_providers.providers.iter().enumerate().map(|(i, provider)| {
+                                    render_row(&link_providers, i, len, provider)
+                                })}
+                            </tbody>
+                        </table>
+                    </div>
+                </div>
+            </div>
+        </Layout>
+    }
+}
+
+fn render_row(
+    link_providers: &UseReducerHandle<LinkProviders>,
+    i: usize,
+    len: usize,
+    provider: &LinkProvider,
+) -> Html {
+    // 入力した値で項目を書き換える
+    let update = |edit: fn(&mut LinkProvider, &HtmlInputElement)| {
+        let link_providers = link_providers.clone();
+        let provider = provider.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let mut provider = provider.clone();
+            edit(&mut provider, &input);
+            link_providers.dispatch(LinkProvidersAction::Update(i, provider));
+        })
+    };
+    let dispatch = |action: fn(usize) -> LinkProvidersAction| {
+        let link_providers = link_providers.clone();
+        Callback::from(move |_| link_providers.dispatch(action(i)))
+    };
+
+    html! {
+        <tr>
+            <td>
+                <input type="checkbox" class="form-check-input" checked={provider.enabled}
+                    onchange={update(|p, input| p.enabled = input.checked())} />
+            </td>
+            <td>
+                <input type="text" class="form-control form-control-sm" value={provider.name.clone()}
+                    onchange={update(|p, input| p.name = input.value())} />
+            </td>
+            <td class="w-50">
+                <input type="url" class="form-control form-control-sm" value={provider.url_template.clone()}
+                    onchange={update(|p, input| p.url_template = input.value())} />
+            </td>
+            <td>
+                <input type="text" class="form-control form-control-sm" value={provider.markets.join(",")}
+                    onchange={update(|p, input| p.markets = split_markets(&input.value()))} />
+            </td>
+            <td class="text-nowrap text-end">
+                <button type="button" class="btn btn-sm btn-outline-secondary me-1" title="上へ" disabled={i == 0}
+                    onclick={dispatch(|i| LinkProvidersAction::Move { from: i, to: i.saturating_sub(1) })}>
+                    { "↑" }
+                </button>
+                <button type="button" class="btn btn-sm btn-outline-secondary me-1" title="下へ" disabled={i + 1 == len}
+                    onclick={dispatch(|i| LinkProvidersAction::Move { from: i, to: i + 1 })}>
+                    { "↓" }
+                </button>
+                <button type="button" class="btn btn-sm btn-outline-danger" title="削除"
+                    onclick={dispatch(LinkProvidersAction::Remove)}>
+                    { "×" }
+                </button>
+            </td>
+        </tr>
+    }
+}
+
+/// 「,」や「、」で区切ったマーケットカテゴリ
+fn split_markets(value: &str) -> Vec<String> {
+    value
+        .split([',', '、'])
+        .map(str::trim)
+        .filter(|market| !market.is_empty())
+        .map(ToString::to_string)
+        .collect()
+}
@@ -1,10 +1,16 @@
 use strum::{EnumIter, EnumMessage, IntoEnumIterator};
+use web_sys::File;
 use yew::prelude::*;
 
-mod dividend_list;
-mod domestic_stock;
-mod mutual_fund;
-mod receipt_template;
+mod broker_profile;
+mod csv_import;
+pub(crate) mod dividend_list;
+pub(crate) mod domestic_stock;
+pub(crate) mod exchange_rates;
+pub(crate) mod mutual_fund;
+pub(crate) mod receipt_storage;
+mod receipt_sync;
+pub(crate) mod receipt_template;
 
 use dividend_list::DividendList;
 use domestic_stock::DomesticStock;
@@ -14,7 +20,7 @@ use receipt_template::{ReceiptProps, ReceiptTemplate};
 use super::layout::Layout;
 
 #[derive(Clone, PartialEq, Eq, Debug, EnumMessage, Copy, EnumIter)]
-enum ReceiptsType {
+pub enum ReceiptsType {
     #[strum(message = "配当金")]
     Dividend,
 
@@ -28,10 +34,22 @@ enum ReceiptsType {
 #[function_component]
 pub fn Receipts() -> Html {
     let selected_type = use_state(|| ReceiptsType::Dividend);
+    let detected_files = use_state(Vec::<File>::new);
 
     let on_click = {
         let selected_type = selected_type.clone();
+        let detected_files = detected_files.clone();
         Callback::from(move |new_type: ReceiptsType| {
+            detected_files.set(Vec::new());
+            selected_type.set(new_type);
+        })
+    };
+
+    let on_detect = {
+        let selected_type = selected_type.clone();
+        let detected_files = detected_files.clone();
+        Callback::from(move |(new_type, files): (ReceiptsType, Vec<File>)| {
+            detected_files.set(files);
             selected_type.set(new_type);
         })
     };
@@ -46,9 +64,9 @@ pub fn Receipts() -> Html {
             </nav>
             <div class="mt-4"> {
                 match *selected_type {
-                    ReceiptsType::Dividend =>      { render_receipt_template::<DividendList>(name) },
-                    ReceiptsType::DomesticStock => { render_receipt_template::<DomesticStock>(name) },
-                    ReceiptsType::MutualFund =>    { render_receipt_template::<MutualFund>(name) },
+                    ReceiptsType::Dividend =>      { render_receipt_template::<DividendList>(name, &detected_files, &on_detect) },
+                    ReceiptsType::DomesticStock => { render_receipt_template::<DomesticStock>(name, &detected_files, &on_detect) },
+                    ReceiptsType::MutualFund =>    { render_receipt_template::<MutualFund>(name, &detected_files, &on_detect) },
                 }}
             </div>
         </Layout>
@@ -72,6 +90,10 @@ fn render_nav_item(
     }
 }
 
-fn render_receipt_template<T: ReceiptProps>(name: &str) -> Html {
-    html! { <ReceiptTemplate::<T> name={ name.to_string() } /> }
+fn render_receipt_template<T: ReceiptProps>(
+    name: &str,
+    files: &[File],
+    on_detect: &Callback<(ReceiptsType, Vec<File>)>,
+) -> Html {
+    html! { <ReceiptTemplate::<T> name={ name.to_string() } files={ files.to_vec() } on_detect={ on_detect.clone() } /> }
 }
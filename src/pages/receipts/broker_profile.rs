@@ -0,0 +1,430 @@
+use csv::StringRecord;
+use strum::{EnumIter, EnumMessage};
+
+use super::ReceiptsType;
+use crate::services::csv_reader::{CSVColumns, CSVError};
+
+#[derive(Clone, PartialEq, Eq, Debug, EnumMessage, Copy, EnumIter)]
+pub enum Broker {
+    #[strum(message = "楽天証券")]
+    Rakuten,
+
+    #[strum(message = "SBI証券")]
+    Sbi,
+
+    #[strum(message = "マネックス証券")]
+    Monex,
+
+    #[strum(message = "松井証券")]
+    Matsui,
+}
+
+/// 証券会社ごとの CSV レイアウト。
+///
+/// `columns` は `(フィールド名, ヘッダー名)` の対応で、証券会社が出力しない
+/// フィールドは含めない (読み込み時は `None` になる)。
+#[derive(Debug, PartialEq)]
+pub struct BrokerProfile {
+    pub broker: Broker,
+    pub receipts_type: ReceiptsType,
+    pub columns: &'static [(&'static str, &'static str)],
+}
+
+pub static PROFILES: &[BrokerProfile] = &[
+    BrokerProfile {
+        broker: Broker::Rakuten,
+        receipts_type: ReceiptsType::Dividend,
+        columns: &[
+            ("settlement_date", "入金日"),
+            ("product", "商品"),
+            ("account", "口座"),
+            ("security_code", "銘柄コード"),
+            ("security_name", "銘柄"),
+            ("currency", "受取通貨"),
+            ("unit_price", "単価[円/現地通貨]"),
+            ("shares", "数量[株/口]"),
+            (
+                "dividends_before_tax",
+                "配当・分配金（税引前）[円/現地通貨]",
+            ),
+            ("taxes", "税額[円/現地通貨]"),
+            ("net_amount_received", "受取金額[円/現地通貨]"),
+        ],
+    },
+    // 外国株式の配当を含む場合は、外国源泉徴収税額と為替レートの列が加わる
+    BrokerProfile {
+        broker: Broker::Rakuten,
+        receipts_type: ReceiptsType::Dividend,
+        columns: &[
+            ("settlement_date", "入金日"),
+            ("product", "商品"),
+            ("account", "口座"),
+            ("security_code", "銘柄コード"),
+            ("security_name", "銘柄"),
+            ("currency", "受取通貨"),
+            ("unit_price", "単価[円/現地通貨]"),
+            ("shares", "数量[株/口]"),
+            (
+                "dividends_before_tax",
+                "配当・分配金（税引前）[円/現地通貨]",
+            ),
+            ("foreign_taxes", "外国源泉徴収税額[現地通貨]"),
+            ("taxes", "税額[円/現地通貨]"),
+            ("net_amount_received", "受取金額[円/現地通貨]"),
+            ("exchange_rate", "為替レート"),
+        ],
+    },
+    BrokerProfile {
+        broker: Broker::Rakuten,
+        receipts_type: ReceiptsType::DomesticStock,
+        columns: &[
+            ("trade_date", "約定日"),
+            ("settlement_date", "受渡日"),
+            ("security_code", "銘柄コード"),
+            ("security_name", "銘柄名"),
+            ("account", "口座"),
+            ("shares", "数量[株]"),
+            ("asked_price", "売却/決済単価[円]"),
+            ("proceeds", "売却/決済額[円]"),
+            ("purchase_price", "平均取得価額[円]"),
+            ("realized_profit_and_loss", "実現損益[円]"),
+        ],
+    },
+    BrokerProfile {
+        broker: Broker::Rakuten,
+        receipts_type: ReceiptsType::MutualFund,
+        columns: &[
+            ("trade_date", "約定日"),
+            ("settlement_date", "受渡日"),
+            ("fund_name", "ファンド名"),
+            ("dividends", "分配金"),
+            ("account", "口座"),
+            ("shares", "数量［口］"),
+            ("exchange_rate", "為替レート"),
+            ("cancellation_unit_price_yen", "解約単価［円］"),
+            ("cancellation_amount_yen", "解約額［円］"),
+            ("average_acquisition_price_yen", "平均取得価額［円］"),
+            ("realized_profit_and_loss", "実現損益［円］"),
+        ],
+    },
+    BrokerProfile {
+        broker: Broker::Sbi,
+        receipts_type: ReceiptsType::Dividend,
+        columns: &[
+            ("settlement_date", "受渡日"),
+            ("security_code", "銘柄コード"),
+            ("security_name", "銘柄名"),
+            ("account", "口座"),
+            ("shares", "数量"),
+            ("unit_price", "単価"),
+            ("dividends_before_tax", "配当金額（税引前）"),
+            ("taxes", "税額"),
+            ("net_amount_received", "受取額"),
+        ],
+    },
+    BrokerProfile {
+        broker: Broker::Sbi,
+        receipts_type: ReceiptsType::DomesticStock,
+        columns: &[
+            ("trade_date", "約定日"),
+            ("settlement_date", "受渡日"),
+            ("security_code", "銘柄コード"),
+            ("security_name", "銘柄名"),
+            ("account", "口座区分"),
+            ("shares", "数量"),
+            ("asked_price", "売却単価"),
+            ("proceeds", "売却金額"),
+            ("purchase_price", "取得単価"),
+            ("realized_profit_and_loss", "損益金額"),
+        ],
+    },
+    BrokerProfile {
+        broker: Broker::Sbi,
+        receipts_type: ReceiptsType::MutualFund,
+        columns: &[
+            ("trade_date", "約定日"),
+            ("settlement_date", "受渡日"),
+            ("fund_name", "ファンド名"),
+            ("account", "口座区分"),
+            ("shares", "数量"),
+            ("cancellation_unit_price_yen", "解約単価"),
+            ("cancellation_amount_yen", "解約金額"),
+            ("average_acquisition_price_yen", "取得単価"),
+            ("realized_profit_and_loss", "損益金額"),
+        ],
+    },
+    BrokerProfile {
+        broker: Broker::Monex,
+        receipts_type: ReceiptsType::Dividend,
+        columns: &[
+            ("settlement_date", "入金日"),
+            ("security_code", "銘柄コード"),
+            ("security_name", "銘柄名"),
+            ("account", "口座区分"),
+            ("shares", "株数"),
+            ("unit_price", "1株あたり配当金"),
+            ("dividends_before_tax", "配当金額(税引前)"),
+            ("taxes", "源泉徴収税額"),
+            ("net_amount_received", "受取金額"),
+        ],
+    },
+    BrokerProfile {
+        broker: Broker::Monex,
+        receipts_type: ReceiptsType::DomesticStock,
+        columns: &[
+            ("trade_date", "約定日"),
+            ("settlement_date", "受渡日"),
+            ("security_code", "銘柄コード"),
+            ("security_name", "銘柄名"),
+            ("account", "口座区分"),
+            ("shares", "数量"),
+            ("asked_price", "約定単価"),
+            ("proceeds", "受渡金額"),
+            ("purchase_price", "平均取得単価"),
+            ("realized_profit_and_loss", "実現損益"),
+        ],
+    },
+    BrokerProfile {
+        broker: Broker::Monex,
+        receipts_type: ReceiptsType::MutualFund,
+        columns: &[
+            ("trade_date", "約定日"),
+            ("settlement_date", "受渡日"),
+            ("fund_name", "ファンド名"),
+            ("account", "口座区分"),
+            ("shares", "口数"),
+            ("cancellation_unit_price_yen", "基準価額"),
+            ("cancellation_amount_yen", "受渡金額"),
+            ("average_acquisition_price_yen", "平均取得単価"),
+            ("realized_profit_and_loss", "実現損益"),
+        ],
+    },
+    BrokerProfile {
+        broker: Broker::Matsui,
+        receipts_type: ReceiptsType::Dividend,
+        columns: &[
+            ("settlement_date", "支払日"),
+            ("security_code", "銘柄コード"),
+            ("security_name", "銘柄名"),
+            ("account", "預り区分"),
+            ("shares", "数量"),
+            ("unit_price", "1株配当"),
+            ("dividends_before_tax", "配当金額"),
+            ("taxes", "源泉徴収税額"),
+            ("net_amount_received", "受取金額"),
+        ],
+    },
+    BrokerProfile {
+        broker: Broker::Matsui,
+        receipts_type: ReceiptsType::DomesticStock,
+        columns: &[
+            ("trade_date", "約定日"),
+            ("settlement_date", "受渡日"),
+            ("security_code", "銘柄コード"),
+            ("security_name", "銘柄名"),
+            ("account", "預り区分"),
+            ("shares", "数量"),
+            ("asked_price", "売却単価"),
+            ("proceeds", "売却代金"),
+            ("purchase_price", "取得単価"),
+            ("realized_profit_and_loss", "損益"),
+        ],
+    },
+    BrokerProfile {
+        broker: Broker::Matsui,
+        receipts_type: ReceiptsType::MutualFund,
+        columns: &[
+            ("trade_date", "約定日"),
+            ("settlement_date", "受渡日"),
+            ("fund_name", "ファンド名"),
+            ("account", "預り区分"),
+            ("shares", "口数"),
+            ("cancellation_unit_price_yen", "解約価額"),
+            ("cancellation_amount_yen", "解約代金"),
+            ("average_acquisition_price_yen", "取得単価"),
+            ("realized_profit_and_loss", "損益"),
+        ],
+    },
+];
+
+/// ヘッダー行から証券会社と帳票の種類を判定する。
+///
+/// 全ての列が揃うプロファイルのうち、列数の最も多いものを採用する。
+/// どれにも一致しない場合は、`preferred` の帳票で最も近いプロファイルの
+/// 不足列・不明列をエラーとして返す。
+pub fn detect(
+    headers: &StringRecord,
+    preferred: ReceiptsType,
+) -> Result<(&'static BrokerProfile, CSVColumns), CSVError> {
+    let mut matched: Option<(&'static BrokerProfile, CSVColumns)> = None;
+    let mut closest: Option<CSVError> = None;
+
+    for profile in PROFILES {
+        match CSVColumns::resolve(headers, profile.columns) {
+            Ok(columns) => {
+                if matched
+                    .as_ref()
+                    .is_none_or(|(m, _)| m.columns.len() < profile.columns.len())
+                {
+                    matched = Some((profile, columns));
+                }
+            }
+            Err(err @ CSVError::ColumnMismatchError { .. })
+                if profile.receipts_type == preferred =>
+            {
+                if closest
+                    .as_ref()
+                    .is_none_or(|c| missing_count(c) > missing_count(&err))
+                {
+                    closest = Some(err);
+                }
+            }
+            Err(_) => {}
+        }
+    }
+
+    matched.ok_or_else(|| closest.unwrap_or(CSVError::UnknownFormatError))
+}
+
+fn missing_count(err: &CSVError) -> usize {
+    match err {
+        CSVError::ColumnMismatchError { missing, .. } => missing.len(),
+        _ => usize::MAX,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data::money::Currency,
+        pages::receipts::{
+            dividend_list::DividendList, domestic_stock::DomesticStock, mutual_fund::MutualFund,
+            receipt_template::ReceiptProps,
+        },
+        services::csv_reader::read_csv,
+    };
+    use chrono::NaiveDate;
+    use encoding_rs::SHIFT_JIS;
+
+    fn load(
+        fixture: &str,
+        preferred: ReceiptsType,
+    ) -> (&'static BrokerProfile, Vec<StringRecord>, CSVColumns) {
+        let path = format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), fixture);
+        let content = std::fs::read_to_string(path).unwrap();
+        let (bytes, _, _) = SHIFT_JIS.encode(&content);
+        let content = read_csv(bytes.into_owned(), None).unwrap();
+        let (profile, columns) = detect(&content.headers, preferred).unwrap();
+        (profile, content.records, columns)
+    }
+
+    fn load_dividend(fixture: &str, broker: Broker) -> DividendList {
+        let (profile, records, columns) = load(fixture, ReceiptsType::Dividend);
+        assert_eq!(profile.broker, broker);
+        assert_eq!(profile.receipts_type, ReceiptsType::Dividend);
+        DividendList::new_from_string_record(&records[0], &columns)
+    }
+
+    fn load_domestic_stock(fixture: &str, broker: Broker) -> DomesticStock {
+        let (profile, records, columns) = load(fixture, ReceiptsType::DomesticStock);
+        assert_eq!(profile.broker, broker);
+        assert_eq!(profile.receipts_type, ReceiptsType::DomesticStock);
+        DomesticStock::new_from_string_record(&records[0], &columns)
+    }
+
+    fn load_mutual_fund(fixture: &str, broker: Broker) -> MutualFund {
+        let (profile, records, columns) = load(fixture, ReceiptsType::MutualFund);
+        assert_eq!(profile.broker, broker);
+        assert_eq!(profile.receipts_type, ReceiptsType::MutualFund);
+        MutualFund::new_from_string_record(&records[0], &columns)
+    }
+
+    #[test]
+    fn test_detect_dividend() {
+        for (fixture, broker) in [
+            ("rakuten_dividend.csv", Broker::Rakuten),
+            ("sbi_dividend.csv", Broker::Sbi),
+            ("monex_dividend.csv", Broker::Monex),
+            ("matsui_dividend.csv", Broker::Matsui),
+        ] {
+            let dividend = load_dividend(fixture, broker);
+            assert_eq!(
+                dividend.settlement_date,
+                NaiveDate::from_ymd_opt(2024, 3, 28)
+            );
+            assert_eq!(dividend.security_code.as_deref(), Some("7203"));
+            assert_eq!(dividend.shares, Some(100));
+            assert_eq!(dividend.dividends_before_tax, Some(3000.0));
+            assert_eq!(dividend.taxes, Some(609.0));
+            assert_eq!(dividend.net_amount_received, Some(2391.0));
+        }
+    }
+
+    #[test]
+    fn test_detect_foreign_dividend() {
+        let dividend = load_dividend("rakuten_foreign_dividend.csv", Broker::Rakuten);
+        assert_eq!(dividend.currency, Currency::Usd);
+        assert_eq!(dividend.unit_price, Some(0.24));
+        assert_eq!(dividend.dividends_before_tax, Some(24.0));
+        assert_eq!(dividend.foreign_taxes, Some(2.4));
+        assert_eq!(dividend.taxes, Some(4.38));
+        assert_eq!(dividend.net_amount_received, Some(17.22));
+        assert_eq!(dividend.exchange_rate, Some(151.2));
+    }
+
+    #[test]
+    fn test_detect_domestic_stock() {
+        for (fixture, broker) in [
+            ("rakuten_domestic_stock.csv", Broker::Rakuten),
+            ("sbi_domestic_stock.csv", Broker::Sbi),
+            ("monex_domestic_stock.csv", Broker::Monex),
+            ("matsui_domestic_stock.csv", Broker::Matsui),
+        ] {
+            let stock = load_domestic_stock(fixture, broker);
+            assert_eq!(stock.trade_date, NaiveDate::from_ymd_opt(2024, 2, 5));
+            assert_eq!(stock.settlement_date, NaiveDate::from_ymd_opt(2024, 2, 7));
+            assert_eq!(stock.security_code.as_deref(), Some("7203"));
+            assert_eq!(
+                stock.account.as_deref().map(|a| a.contains("特定")),
+                Some(true)
+            );
+            assert_eq!(stock.shares, Some(100));
+            assert_eq!(stock.purchase_price, Some(2800.0));
+            assert!(stock.realized_profit_and_loss.is_some());
+        }
+    }
+
+    #[test]
+    fn test_detect_mutual_fund() {
+        for (fixture, broker) in [
+            ("rakuten_mutual_fund.csv", Broker::Rakuten),
+            ("sbi_mutual_fund.csv", Broker::Sbi),
+            ("monex_mutual_fund.csv", Broker::Monex),
+            ("matsui_mutual_fund.csv", Broker::Matsui),
+        ] {
+            let fund = load_mutual_fund(fixture, broker);
+            assert_eq!(fund.trade_date, NaiveDate::from_ymd_opt(2024, 5, 13));
+            assert!(fund.fund_name.is_some());
+            assert!(fund.shares.is_some());
+            assert!(fund.realized_profit_and_loss.is_some());
+            assert!(fund.taxes.is_some());
+        }
+    }
+
+    #[test]
+    fn test_detect_other_kind_than_preferred() {
+        let (profile, _, _) = load("sbi_domestic_stock.csv", ReceiptsType::Dividend);
+        assert_eq!(profile.broker, Broker::Sbi);
+        assert_eq!(profile.receipts_type, ReceiptsType::DomesticStock);
+    }
+
+    #[test]
+    fn test_detect_unknown_format() {
+        let headers = StringRecord::from(vec!["入金日", "銘柄コード", "銘柄"]);
+        match detect(&headers, ReceiptsType::Dividend) {
+            Err(CSVError::ColumnMismatchError { missing, .. }) => assert!(!missing.is_empty()),
+            other => panic!("unexpected result: {:?}", other.map(|(p, _)| p)),
+        }
+    }
+}
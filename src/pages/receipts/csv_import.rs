@@ -0,0 +1,346 @@
+use csv::StringRecord;
+use std::collections::HashSet;
+use web_sys::File;
+
+use super::{
+    broker_profile::{self, BrokerProfile},
+    receipt_template::ReceiptProps,
+};
+use crate::{
+    services::csv_reader::{self, CSVColumns, CSVContent, CSVEncoding, CSVError, ImportIssue},
+    setting::*,
+};
+
+/// CSV の取り込み方法
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct ImportOptions {
+    /// 文字コード (None の場合は自動判定)
+    pub encoding: Option<CSVEncoding>,
+    /// 問題のある行を取り込まない
+    pub skip_invalid_rows: bool,
+}
+
+/// CSV ファイルごとの取り込み結果
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct ImportStatus {
+    pub file_name: String,
+    pub profile: Option<&'static BrokerProfile>,
+    pub encoding: Option<CSVEncoding>,
+    pub issues: Vec<ImportIssue>,
+    pub imported_rows: usize,
+    pub skipped_rows: usize,
+    pub duplicate_rows: usize,
+    pub error: Option<String>,
+}
+
+impl ImportStatus {
+    /// 判定された帳票が `T` と異なるか
+    pub fn is_other_type<T: ReceiptProps>(&self) -> bool {
+        self.profile
+            .is_some_and(|p| p.receipts_type != T::receipts_type())
+    }
+}
+
+/// 複数の CSV ファイルを順に読み込み、ファイルごとの取り込み結果と変換した行を返す。
+pub async fn import_files<T: ReceiptProps>(
+    files: &[File],
+    options: ImportOptions,
+) -> Vec<(ImportStatus, Vec<T>)> {
+    let mut results = Vec::new();
+    for file in files {
+        let result = match csv_reader::read_file(file).await {
+            Ok(bytes) => import_content(&file.name(), bytes, options),
+            Err(err) => (
+                ImportStatus {
+                    file_name: file.name(),
+                    error: Some(err.to_string()),
+                    ..Default::default()
+                },
+                Vec::new(),
+            ),
+        };
+        results.push(result);
+    }
+    results
+}
+
+/// CSV の内容を変換し、取り込み結果と行を返す。
+///
+/// 別の帳票と判定された場合は行を返さない。
+pub fn import_content<T: ReceiptProps>(
+    file_name: &str,
+    bytes: Vec<u8>,
+    options: ImportOptions,
+) -> (ImportStatus, Vec<T>) {
+    let (mut status, new_receipts) = match parse_content::<T>(bytes, options) {
+        Ok(result) => result,
+        Err(err) => (
+            ImportStatus {
+                error: Some(err.to_string()),
+                ..Default::default()
+            },
+            Vec::new(),
+        ),
+    };
+    status.file_name = file_name.to_string();
+    (status, new_receipts)
+}
+
+fn parse_content<T: ReceiptProps>(
+    bytes: Vec<u8>,
+    options: ImportOptions,
+) -> Result<(ImportStatus, Vec<T>), CSVError> {
+    let content = csv_reader::read_csv(bytes, options.encoding)?;
+    let (profile, columns) = broker_profile::detect(&content.headers, T::receipts_type())?;
+    let mut status = ImportStatus {
+        profile: Some(profile),
+        encoding: Some(content.encoding),
+        ..Default::default()
+    };
+    if status.is_other_type::<T>() {
+        return Ok((status, Vec::new()));
+    }
+
+    let new_receipts =
+        parse_records::<T>(&content, &columns, options.skip_invalid_rows, &mut status)?;
+    Ok((status, new_receipts))
+}
+
+/// データ行を変換し、値を解釈できなかったセルを `status` に記録する。
+///
+/// `skip_invalid_rows` が `false` の場合、読み込めない行があればファイル全体を失敗とし、
+/// 値を解釈できなかったセルは `None` のまま取り込む。
+/// `ReceiptProps::rejected_fields` に該当する行は、設定に関わらず取り込まない。
+fn parse_records<T: ReceiptProps>(
+    content: &CSVContent,
+    columns: &CSVColumns,
+    skip_invalid_rows: bool,
+    status: &mut ImportStatus,
+) -> Result<Vec<T>, CSVError> {
+    if let (false, Some(issue)) = (skip_invalid_rows, content.invalid_rows.first()) {
+        return Err(CSVError::InvalidRowError(issue.clone()));
+    }
+
+    status.issues = content.invalid_rows.clone();
+    status.skipped_rows = content.invalid_rows.len();
+
+    let mut new_receipts = Vec::new();
+    for (i, record) in content.records.iter().enumerate() {
+        // ヘッダー行が 1 行目
+        let row = record.position().map_or(i as u64 + 2, |p| p.line());
+        let receipt = T::new_from_string_record(record, columns);
+        let rejected = reject_issues::<T>(record, row, columns, &content.headers);
+        let issues = find_issues(&receipt, record, row, columns, &content.headers);
+        if !rejected.is_empty() || (skip_invalid_rows && !issues.is_empty()) {
+            status.skipped_rows += 1;
+        } else {
+            new_receipts.push(receipt);
+        }
+        status.issues.extend(rejected);
+        status.issues.extend(issues);
+    }
+    status.imported_rows = new_receipts.len();
+
+    Ok(new_receipts)
+}
+
+/// `ReceiptProps::rejected_fields` のセルを問題として返す。
+fn reject_issues<T: ReceiptProps>(
+    record: &StringRecord,
+    row: u64,
+    columns: &CSVColumns,
+    headers: &StringRecord,
+) -> Vec<ImportIssue> {
+    T::rejected_fields(record, columns)
+        .into_iter()
+        .map(|(key, reason)| {
+            let index = columns.iter().find(|(k, _)| *k == key).map(|(_, i)| i);
+            ImportIssue {
+                row,
+                column: index.and_then(|i| headers.get(i)).map(ToString::to_string),
+                value: columns.get(record, key).unwrap_or_default().to_string(),
+                reason: reason.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// 元のセルに値があるのに、変換後のフィールドが `None` になった列を探す。
+fn find_issues<T: ReceiptProps>(
+    receipt: &T,
+    record: &StringRecord,
+    row: u64,
+    columns: &CSVColumns,
+    headers: &StringRecord,
+) -> Vec<ImportIssue> {
+    let fields = receipt.get_all_fields();
+    columns
+        .iter()
+        .filter_map(|(key, index)| {
+            let raw = record.get(index)?.trim();
+            if raw.is_empty() || raw == "-" {
+                return None;
+            }
+            let (_, value) = fields.iter().find(|(k, _)| *k == key)?;
+            value.is_none().then(|| ImportIssue {
+                row,
+                column: headers.get(index).map(ToString::to_string),
+                value: raw.to_string(),
+                reason: if DATE_FORMAT_KEYS.contains(key) {
+                    "日付として解釈できません".to_string()
+                } else {
+                    "数値として解釈できません".to_string()
+                },
+            })
+        })
+        .collect()
+}
+
+/// 取り込み済みの行と `row_key` が一致する行を除いて追加し、`status` に重複行数を記録する。
+///
+/// 同じファイル内の同一行 (同日・同額の約定など) は重複とみなさない。
+pub fn merge_receipts<T: ReceiptProps>(
+    receipts: &mut Vec<T>,
+    new_receipts: Vec<T>,
+    status: &mut ImportStatus,
+) {
+    let existing_keys: HashSet<String> = receipts.iter().map(|r| r.row_key()).collect();
+    let total = new_receipts.len();
+    let new_receipts: Vec<T> = new_receipts
+        .into_iter()
+        .filter(|r| !existing_keys.contains(&r.row_key()))
+        .map(|mut r| {
+            r.set_source_file(Some(&status.file_name));
+            r
+        })
+        .collect();
+    status.duplicate_rows = total - new_receipts.len();
+    status.imported_rows = new_receipts.len();
+
+    receipts.extend(new_receipts);
+    receipts.sort_by(|a, b| {
+        a.get_date()
+            .unwrap_or_default()
+            .cmp(&b.get_date().unwrap_or_default())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pages::receipts::{
+        dividend_list::DividendList, domestic_stock::DomesticStock, ReceiptsType,
+    };
+
+    const CSV: &str = "約定日,受渡日,銘柄コード,銘柄名,口座,数量[株],売却/決済単価[円],売却/決済額[円],平均取得価額[円],実現損益[円]
+2024/02/05,2024/02/07,7203,トヨタ自動車,特定,100,3250,325000,2800,45000
+2024/13/01,2024/02/07,9984,ソフトバンクグループ,特定,abc,8000,80000,9000,-10000
+";
+
+    fn parse(
+        csv: &str,
+        skip_invalid_rows: bool,
+    ) -> Result<(Vec<DomesticStock>, ImportStatus), CSVError> {
+        let content = csv_reader::read_csv(csv.as_bytes().to_vec(), None).unwrap();
+        let (_, columns) =
+            broker_profile::detect(&content.headers, ReceiptsType::DomesticStock).unwrap();
+        let mut status = ImportStatus::default();
+        let receipts = parse_records(&content, &columns, skip_invalid_rows, &mut status)?;
+        Ok((receipts, status))
+    }
+
+    #[test]
+    fn test_parse_records_reports_invalid_values() {
+        let (receipts, status) = parse(CSV, false).unwrap();
+
+        assert_eq!(receipts.len(), 2);
+        assert_eq!(status.imported_rows, 2);
+        assert_eq!(status.skipped_rows, 0);
+        assert_eq!(
+            status.issues,
+            vec![
+                ImportIssue {
+                    row: 3,
+                    column: Some("約定日".to_string()),
+                    value: "2024/13/01".to_string(),
+                    reason: "日付として解釈できません".to_string(),
+                },
+                ImportIssue {
+                    row: 3,
+                    column: Some("数量[株]".to_string()),
+                    value: "abc".to_string(),
+                    reason: "数値として解釈できません".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_records_skips_invalid_rows() {
+        let csv = format!("{}1,2\n", CSV);
+        assert!(matches!(
+            parse(&csv, false),
+            Err(CSVError::InvalidRowError(ImportIssue { row: 4, .. }))
+        ));
+
+        let (receipts, status) = parse(&csv, true).unwrap();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].security_code.as_deref(), Some("7203"));
+        assert_eq!(status.imported_rows, 1);
+        assert_eq!(status.skipped_rows, 2);
+        assert_eq!(status.issues.len(), 3);
+    }
+
+    #[test]
+    fn test_parse_records_rejects_unknown_currency() {
+        let csv = "入金日,商品,口座,銘柄コード,銘柄,受取通貨,単価[円/現地通貨],数量[株/口],配当・分配金（税引前）[円/現地通貨],外国源泉徴収税額[現地通貨],税額[円/現地通貨],受取金額[円/現地通貨],為替レート
+2024/05/15,米国株式,特定口座,KO,コカ・コーラ,USドル,0.24,100,24.00,2.40,4.38,17.22,151.20
+2024/05/20,外国株式,特定口座,NPI,ニュージーランド銘柄,NZドル,0.10,100,10.00,1.00,2.00,7.00,90.00
+";
+        let content = csv_reader::read_csv(csv.as_bytes().to_vec(), None).unwrap();
+        let (_, columns) =
+            broker_profile::detect(&content.headers, ReceiptsType::Dividend).unwrap();
+        let mut status = ImportStatus::default();
+        // 問題のある行を取り込む設定でも、円とみなして取り込まない
+        let receipts: Vec<DividendList> =
+            parse_records(&content, &columns, false, &mut status).unwrap();
+
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(status.skipped_rows, 1);
+        assert_eq!(
+            status.issues,
+            vec![ImportIssue {
+                row: 3,
+                column: Some("受取通貨".to_string()),
+                value: "NZドル".to_string(),
+                reason: "対応していない通貨です".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_merge_receipts_without_duplicates() {
+        let header = "約定日,受渡日,銘柄コード,銘柄名,口座,数量[株],売却/決済単価[円],売却/決済額[円],平均取得価額[円],実現損益[円]\n";
+        let toyota = "2024/02/05,2024/02/07,7203,トヨタ自動車,特定,100,3250,325000,2800,45000\n";
+        let sony =
+            "2024/01/10,2024/01/12,6758,ソニーグループ,特定,100,13000,1300000,12000,100000\n";
+        let options = ImportOptions::default();
+
+        let mut receipts: Vec<DomesticStock> = Vec::new();
+        // 同じファイル内の同一行は別の約定として残す
+        let first = format!("{header}{toyota}{toyota}");
+        let (mut status, new_receipts) = import_content("2024_a.csv", first.into_bytes(), options);
+        merge_receipts(&mut receipts, new_receipts, &mut status);
+        assert_eq!((status.imported_rows, status.duplicate_rows), (2, 0));
+
+        let second = format!("{header}{sony}{toyota}");
+        let (mut status, new_receipts) = import_content("2024_b.csv", second.into_bytes(), options);
+        merge_receipts(&mut receipts, new_receipts, &mut status);
+        assert_eq!((status.imported_rows, status.duplicate_rows), (1, 1));
+
+        assert_eq!(receipts.len(), 3);
+        assert_eq!(receipts[0].security_code.as_deref(), Some("6758"));
+        assert_eq!(receipts[0].source_file.as_deref(), Some("2024_b.csv"));
+        assert_eq!(receipts[1].source_file.as_deref(), Some("2024_a.csv"));
+    }
+}
@@ -1,26 +1,148 @@
 use chrono::{Datelike, NaiveDate};
 use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use yew::prelude::*;
 
-use super::receipt_template::ReceiptProps;
-use crate::services::parser::*;
+use super::{exchange_rates::ExchangeRateSummary, receipt_template::ReceiptProps, ReceiptsType};
+use crate::{
+    data::money::{Currency, ExchangeRates, Money},
+    services::{csv_reader::CSVColumns, formater, parser::*},
+    setting::HEADERS,
+};
 
-#[derive(PartialEq, Properties, Debug, Clone, Default)]
+#[derive(PartialEq, Properties, Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DividendList {
-    pub settlement_date: Option<NaiveDate>,      // 入金日(受渡日)
-    pub product: Option<String>,                 // 商品
-    pub account: Option<String>,                 // 口座
-    pub security_code: Option<String>,           // 銘柄コード
-    pub security_name: Option<String>,           // 銘柄
-    pub currency: Option<String>,                // 受取通貨
-    pub unit_price: Option<String>,              // 単価[円/現地通貨]
-    pub shares: Option<i32>,                     // 数量[株/口]
-    pub dividends_before_tax: Option<i32>,       // 配当・分配金（税引前）[円/現地通貨]
-    pub taxes: Option<i32>,                      // 税額[円/現地通貨]
-    pub net_amount_received: Option<i32>,        // 受取金額[円/現地通貨]
-    pub total_dividends_before_tax: Option<i32>, // 配当・分配金合計（税引前）[円/現地通貨]
-    pub total_taxes: Option<i32>,                // 税額合計[円/現地通貨]
-    pub total_net_amount_received: Option<i32>,  // 受取金額合計[円/現地通貨]
+    pub settlement_date: Option<NaiveDate>, // 入金日(受渡日)
+    pub product: Option<String>,            // 商品
+    pub account: Option<String>,            // 口座
+    pub security_code: Option<String>,      // 銘柄コード
+    pub security_name: Option<String>,      // 銘柄
+    pub currency: Currency,                 // 受取通貨
+    #[serde(deserialize_with = "deserialize_decimal")]
+    pub unit_price: Option<f64>, // 単価[円/現地通貨]
+    pub shares: Option<i32>,                // 数量[株/口]
+    pub dividends_before_tax: Option<f64>,  // 配当・分配金（税引前）[円/現地通貨]
+    pub foreign_taxes: Option<f64>,         // 外国源泉徴収税額[現地通貨]
+    pub taxes: Option<f64>,                 // 国内の税額[円/現地通貨]
+    pub net_amount_received: Option<f64>,   // 受取金額[円/現地通貨]
+    pub exchange_rate: Option<f64>,         // 取り込んだ為替レート [円/現地通貨]
+    pub total_dividends_before_tax: Option<f64>, // 配当・分配金合計（税引前）[円/現地通貨]
+    pub total_taxes: Option<f64>,           // 税額合計[円/現地通貨]
+    pub total_net_amount_received: Option<f64>, // 受取金額合計[円/現地通貨]
+    pub source_file: Option<String>,        // 取込元ファイル
+}
+
+/// 通貨ごとの合計
+#[derive(Clone, PartialEq, Debug)]
+pub struct CurrencySubtotal {
+    pub currency: Currency,
+    pub dividends_before_tax: Money,
+    pub foreign_taxes: Money,
+    pub taxes: Money,
+    pub net_amount_received: Money,
+}
+
+/// 受取通貨で表す金額の列
+const MONEY_KEYS: [&str; 7] = [
+    "dividends_before_tax",
+    "foreign_taxes",
+    "taxes",
+    "net_amount_received",
+    "total_dividends_before_tax",
+    "total_taxes",
+    "total_net_amount_received",
+];
+
+impl DividendList {
+    /// 1 株あたり配当。単価がない場合は配当金額 (税引前) と株数から求める。
+    pub fn per_share(&self) -> Option<f64> {
+        self.unit_price.or_else(|| {
+            let shares = self.shares.filter(|&shares| shares > 0)?;
+            Some(self.dividends_before_tax? / shares as f64)
+        })
+    }
+
+    fn money(&self, amount: Option<f64>) -> Money {
+        Money::from_decimal(amount.unwrap_or(0.0), self.currency)
+    }
+
+    /// 受取通貨の桁数で表した金額 (書き出しや表示に使う)
+    fn amount_string(&self, amount: Option<f64>) -> Option<String> {
+        amount.map(|amount| Money::from_decimal(amount, self.currency).to_decimal_string())
+    }
+
+    /// 円に換算した行。
+    ///
+    /// 取り込んだ為替レートを優先し、なければ `rates` のレートを使う。どちらもなければ `None`。
+    pub fn in_jpy(&self, rates: &ExchangeRates) -> Option<Self> {
+        if self.currency == Currency::Jpy {
+            return Some(self.clone());
+        }
+        let rate = self.exchange_rate.or_else(|| rates.get(self.currency))?;
+        let convert = |amount: Option<f64>| {
+            amount.map(|amount| {
+                Money::from_decimal(amount, self.currency)
+                    .to_jpy(rate)
+                    .to_decimal()
+            })
+        };
+        Some(Self {
+            currency: Currency::Jpy,
+            unit_price: self.unit_price.map(|price| price * rate),
+            dividends_before_tax: convert(self.dividends_before_tax),
+            foreign_taxes: convert(self.foreign_taxes),
+            taxes: convert(self.taxes),
+            net_amount_received: convert(self.net_amount_received),
+            total_dividends_before_tax: convert(self.total_dividends_before_tax),
+            total_taxes: convert(self.total_taxes),
+            total_net_amount_received: convert(self.total_net_amount_received),
+            ..self.clone()
+        })
+    }
+
+    /// 通貨ごとの合計 (円が先頭)
+    pub fn subtotals(receipts: &[Self]) -> Vec<CurrencySubtotal> {
+        let mut subtotals: BTreeMap<Currency, CurrencySubtotal> = BTreeMap::new();
+        for receipt in receipts {
+            let zero = Money::zero(receipt.currency);
+            let subtotal = subtotals
+                .entry(receipt.currency)
+                .or_insert_with(|| CurrencySubtotal {
+                    currency: receipt.currency,
+                    dividends_before_tax: zero,
+                    foreign_taxes: zero,
+                    taxes: zero,
+                    net_amount_received: zero,
+                });
+            // 同じ通貨の金額なので合計できる
+            let add = |total: Money, amount: Option<f64>| {
+                total.checked_add(receipt.money(amount)).unwrap_or(total)
+            };
+            subtotal.dividends_before_tax =
+                add(subtotal.dividends_before_tax, receipt.dividends_before_tax);
+            subtotal.foreign_taxes = add(subtotal.foreign_taxes, receipt.foreign_taxes);
+            subtotal.taxes = add(subtotal.taxes, receipt.taxes);
+            subtotal.net_amount_received =
+                add(subtotal.net_amount_received, receipt.net_amount_received);
+        }
+        subtotals.into_values().collect()
+    }
+
+    pub fn render_money_th_td(key: &str, value: Money) -> Html {
+        let class = if value.minor < 0 {
+            "text-nowrap text-danger"
+        } else {
+            "text-nowrap"
+        };
+        html! {
+        <>
+            <th class="bg-info text-white text-nowrap" style="max-width: 20px;">{HEADERS.get(key).unwrap_or(&key)}</th>
+            <td class={class} style="max-width: 30px;">{value.to_string()}</td>
+        </>
+        }
+    }
 }
 
 impl ReceiptProps for DividendList {
@@ -31,80 +153,130 @@ impl ReceiptProps for DividendList {
             account: None,
             security_code: None,
             security_name: None,
-            currency: None,
+            currency: Currency::Jpy,
             unit_price: None,
             shares: None,
             dividends_before_tax: None,
+            foreign_taxes: None,
             taxes: None,
             net_amount_received: None,
+            exchange_rate: None,
             total_dividends_before_tax: None,
             total_taxes: None,
             total_net_amount_received: None,
+            source_file: None,
         }
     }
 
+    /// 受取通貨ごとの小計。通貨が混在する場合は、取り込んだ為替レートで円に換算できる行を合計し、
+    /// 換算できずに除いた行数を銘柄名の欄に表示する。
     fn new_summary(receipts: &[&Self]) -> Option<Self> {
+        let currency = receipts.first()?.currency;
+        let total_rows = receipts.len();
+        let receipts: Vec<Self> = if receipts.iter().all(|r| r.currency == currency) {
+            receipts.iter().map(|r| (*r).clone()).collect()
+        } else {
+            receipts
+                .iter()
+                .filter_map(|r| r.in_jpy(&ExchangeRates::default()))
+                .collect()
+        };
+        let excluded = total_rows - receipts.len();
+        // 浮動小数点の誤差が出ないよう、通貨の補助単位で合計する
+        let zero = Money::zero(receipts.first().map(|r| r.currency).unwrap_or(currency));
         let (total_dividends_before_tax, total_taxes, total_net_amount_received) =
-            receipts.iter().fold(
-                (0, 0, 0),
+            receipts.iter().try_fold(
+                (zero, zero, zero),
                 |(total_dividends_before_tax, total_taxes, total_net_amount_received), dividend| {
-                    if let (Some(dividends_before_tax), Some(taxes), Some(net_amount_received)) = (
+                    let (Some(_), Some(_), Some(_)) = (
                         dividend.dividends_before_tax,
                         dividend.taxes,
                         dividend.net_amount_received,
-                    ) {
-                        (
-                            total_dividends_before_tax + dividends_before_tax,
-                            total_taxes + taxes,
-                            total_net_amount_received + net_amount_received,
-                        )
-                    } else {
-                        (
+                    ) else {
+                        return Some((
                             total_dividends_before_tax,
                             total_taxes,
                             total_net_amount_received,
-                        )
-                    }
+                        ));
+                    };
+                    Some((
+                        total_dividends_before_tax
+                            .checked_add(dividend.money(dividend.dividends_before_tax))
+                            .ok()?,
+                        total_taxes
+                            .checked_add(dividend.money(dividend.taxes))
+                            .ok()?,
+                        total_net_amount_received
+                            .checked_add(dividend.money(dividend.net_amount_received))
+                            .ok()?,
+                    ))
                 },
-            );
+            )?;
 
         Some(Self {
             settlement_date: None,
             product: None,
             account: None,
             security_code: None,
-            security_name: None,
-            currency: None,
+            security_name: (excluded > 0)
+                .then(|| format!("為替レートがない {} 行を除く", excluded)),
+            currency: zero.currency,
             unit_price: None,
             shares: None,
             dividends_before_tax: None,
+            foreign_taxes: None,
             taxes: None,
             net_amount_received: None,
-            total_dividends_before_tax: Some(total_dividends_before_tax),
-            total_taxes: Some(total_taxes),
-            total_net_amount_received: Some(total_net_amount_received),
+            exchange_rate: None,
+            total_dividends_before_tax: Some(total_dividends_before_tax.to_decimal()),
+            total_taxes: Some(total_taxes.to_decimal()),
+            total_net_amount_received: Some(total_net_amount_received.to_decimal()),
+            source_file: None,
         })
     }
 
-    fn new_from_string_record(record: StringRecord) -> Self {
+    fn new_from_string_record(record: &StringRecord, columns: &CSVColumns) -> Self {
         DividendList {
-            settlement_date: record.get(0).try_parse_date(),
-            product: record.get(1).try_parse_string(),
-            account: record.get(2).try_parse_string(),
-            security_code: record.get(3).try_parse_string(),
-            security_name: record.get(4).try_parse_string(),
-            currency: record.get(5).try_parse_string(),
-            unit_price: record.get(6).try_parse_string(),
-            shares: record.get(7).try_parse_num(),
-            dividends_before_tax: record.get(8).try_parse_num(),
-            taxes: record.get(9).try_parse_num(),
-            net_amount_received: record.get(10).try_parse_num(),
+            settlement_date: columns.get(record, "settlement_date").try_parse_date(),
+            product: columns.get(record, "product").try_parse_string(),
+            account: columns.get(record, "account").try_parse_string(),
+            security_code: columns.get(record, "security_code").try_parse_string(),
+            security_name: columns.get(record, "security_name").try_parse_string(),
+            // 未対応の通貨の行は rejected_fields で取り込まない
+            currency: columns
+                .get(record, "currency")
+                .and_then(Currency::parse)
+                .unwrap_or(Currency::Jpy),
+            unit_price: columns.get(record, "unit_price").try_parse_decimal(),
+            shares: columns.get(record, "shares").try_parse_num(),
+            dividends_before_tax: columns.get(record, "dividends_before_tax").try_parse_num(),
+            foreign_taxes: columns.get(record, "foreign_taxes").try_parse_num(),
+            taxes: columns.get(record, "taxes").try_parse_num(),
+            net_amount_received: columns.get(record, "net_amount_received").try_parse_num(),
+            exchange_rate: columns.get(record, "exchange_rate").try_parse_num(),
             total_dividends_before_tax: None,
             total_taxes: None,
             total_net_amount_received: None,
+            source_file: None,
+        }
+    }
+
+    fn rejected_fields(
+        record: &StringRecord,
+        columns: &CSVColumns,
+    ) -> Vec<(&'static str, &'static str)> {
+        match columns.get(record, "currency") {
+            Some(currency) if Currency::parse(currency).is_none() => {
+                vec![("currency", "対応していない通貨です")]
+            }
+            _ => Vec::new(),
         }
     }
 
+    fn receipts_type() -> ReceiptsType {
+        ReceiptsType::Dividend
+    }
+
     fn get_all_fields(&self) -> Vec<(&'static str, Option<String>)> {
         vec![
             (
@@ -115,59 +287,98 @@ impl ReceiptProps for DividendList {
             ("account", self.account.clone()),
             ("security_code", self.security_code.clone()),
             ("security_name", self.security_name.clone()),
-            // ("currency", self.currency.clone()),
-            ("unit_price", self.unit_price.clone()),
+            ("currency", Some(self.currency.code().to_string())),
+            ("unit_price", self.unit_price.map(|p| p.to_string())),
             ("shares", self.shares.map(|s| s.to_string())),
             (
                 "dividends_before_tax",
-                self.dividends_before_tax.map(|t| t.to_string()),
+                self.amount_string(self.dividends_before_tax),
             ),
-            ("taxes", self.taxes.map(|t| t.to_string())),
+            ("foreign_taxes", self.amount_string(self.foreign_taxes)),
+            ("taxes", self.amount_string(self.taxes)),
             (
                 "net_amount_received",
-                self.net_amount_received.map(|t| t.to_string()),
+                self.amount_string(self.net_amount_received),
             ),
+            ("exchange_rate", self.exchange_rate.map(|r| r.to_string())),
             (
                 "total_dividends_before_tax",
-                self.total_dividends_before_tax.map(|t| t.to_string()),
+                self.amount_string(self.total_dividends_before_tax),
             ),
-            ("total_taxes", self.total_taxes.map(|t| t.to_string())),
+            ("total_taxes", self.amount_string(self.total_taxes)),
             (
                 "total_net_amount_received",
-                self.total_net_amount_received.map(|t| t.to_string()),
+                self.amount_string(self.total_net_amount_received),
             ),
+            ("source_file", self.source_file.clone()),
         ]
     }
 
+    fn row_key(&self) -> String {
+        format!(
+            "{:?}|{}|{}|{:?}|{:?}",
+            self.settlement_date,
+            self.security_code
+                .as_deref()
+                .or(self.security_name.as_deref())
+                .unwrap_or_default(),
+            self.account.as_deref().unwrap_or_default(),
+            self.shares,
+            self.dividends_before_tax,
+        )
+    }
+
+    fn set_source_file(&mut self, file_name: Option<&str>) {
+        self.source_file = file_name.map(str::to_string);
+    }
+
+    fn get_source_file(&self) -> Option<&str> {
+        self.source_file.as_deref()
+    }
+
     fn get_date(&self) -> Option<NaiveDate> {
-        let date = self.settlement_date.unwrap();
+        let date = self.settlement_date?;
         NaiveDate::from_ymd_opt(date.year(), date.month(), 1)
     }
 
+    /// 通貨ごとの合計と、外貨がある場合は円に換算した合計
     fn view_summary(receipts: &[Self]) -> Html {
-        let (total_dividends_before_tax, total_taxes, total_net_amount_received) =
-            receipts.iter().fold(
-                (0, 0, 0),
-                |(total_dividends_before_tax, total_taxes, total_net_amount_received), dividend| {
-                    (
-                        total_dividends_before_tax + dividend.dividends_before_tax.unwrap_or(0),
-                        total_taxes + dividend.taxes.unwrap_or(0),
-                        total_net_amount_received + dividend.net_amount_received.unwrap_or(0),
-                    )
-                },
-            );
+        let subtotals = Self::subtotals(receipts);
+        let has_foreign = subtotals.iter().any(|s| s.currency != Currency::Jpy);
 
         html! {
             <tbody>
-                <tr>
-                    { Self::render_summary_th_td("total_dividends_before_tax", total_dividends_before_tax) }
-                    { Self::render_summary_th_td("total_taxes", total_taxes) }
-                    { Self::render_summary_th_td("total_net_amount_received", total_net_amount_received) }
-                </tr>
+                { for subtotals.into_iter().map(|subtotal| html! {
+                    <tr>
+                        <th class="bg-info text-white text-nowrap" style="max-width: 20px;">{ HEADERS.get("currency").unwrap_or(&"currency") }</th>
+                        <td class="text-nowrap">{ subtotal.currency.code() }</td>
+                        { Self::render_money_th_td("total_dividends_before_tax", subtotal.dividends_before_tax) }
+                        if subtotal.currency != Currency::Jpy {
+                            { Self::render_money_th_td("foreign_taxes", subtotal.foreign_taxes) }
+                        }
+                        { Self::render_money_th_td("total_taxes", subtotal.taxes) }
+                        { Self::render_money_th_td("total_net_amount_received", subtotal.net_amount_received) }
+                    </tr>
+                })}
+                if has_foreign {
+                    <ExchangeRateSummary receipts={receipts.to_vec()} />
+                }
             </tbody>
         }
     }
 
+    /// 外貨の行の金額は通貨コードを付けて表示する
+    fn format_field(&self, key: &str, value: &str) -> String {
+        match (self.currency, value.parse::<f64>()) {
+            (Currency::Jpy, _) | (_, Err(_)) => formater::format_value(key, value),
+            (currency, Ok(_)) if key == "unit_price" => format!("{} {}", currency.code(), value),
+            (currency, Ok(amount)) if MONEY_KEYS.contains(&key) => {
+                Money::from_decimal(amount, currency).to_string()
+            }
+            _ => formater::format_value(key, value),
+        }
+    }
+
     fn search(&self, query: &str) -> bool {
         self.security_code.as_deref().unwrap_or_default() == query
             || self.security_name.as_deref().unwrap_or_default() == query
@@ -181,3 +392,129 @@ impl ReceiptProps for DividendList {
         self.security_name.as_deref().unwrap_or_default()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_per_share() {
+        let dividend = DividendList {
+            unit_price: Some(Some("1,000円").try_parse_decimal().unwrap()),
+            shares: Some(100),
+            dividends_before_tax: Some(4500.0),
+            ..Default::default()
+        };
+        assert_eq!(dividend.per_share(), Some(1000.0));
+
+        let without_price = DividendList {
+            unit_price: None,
+            ..dividend
+        };
+        assert_eq!(without_price.per_share(), Some(45.0));
+    }
+
+    fn usd_dividend(exchange_rate: Option<f64>) -> DividendList {
+        DividendList {
+            currency: Currency::Usd,
+            dividends_before_tax: Some(24.0),
+            foreign_taxes: Some(2.4),
+            taxes: Some(4.38),
+            net_amount_received: Some(17.22),
+            exchange_rate,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_in_jpy() {
+        let mut rates = ExchangeRates::default();
+        assert_eq!(usd_dividend(None).in_jpy(&rates), None);
+
+        rates.set(Currency::Usd, 150.0);
+        let converted = usd_dividend(None).in_jpy(&rates).unwrap();
+        assert_eq!(converted.currency, Currency::Jpy);
+        assert_eq!(converted.dividends_before_tax, Some(3600.0));
+        assert_eq!(converted.net_amount_received, Some(2583.0));
+
+        // 取り込んだ為替レートを優先する
+        let imported = usd_dividend(Some(151.2)).in_jpy(&rates).unwrap();
+        assert_eq!(imported.dividends_before_tax, Some(3628.0));
+    }
+
+    #[test]
+    fn test_subtotals_by_currency() {
+        let yen = DividendList {
+            dividends_before_tax: Some(3000.0),
+            taxes: Some(609.0),
+            net_amount_received: Some(2391.0),
+            ..Default::default()
+        };
+        let receipts = vec![usd_dividend(None), yen.clone(), usd_dividend(None)];
+        let subtotals = DividendList::subtotals(&receipts);
+
+        assert_eq!(subtotals.len(), 2);
+        assert_eq!(subtotals[0].currency, Currency::Jpy);
+        assert_eq!(subtotals[0].net_amount_received.to_string(), "¥ 2,391");
+        assert_eq!(subtotals[1].dividends_before_tax.to_string(), "USD 48.00");
+        assert_eq!(subtotals[1].foreign_taxes.minor, 480);
+
+        // 通貨が混在する小計は、為替レートを取り込んだ行だけを円で合計する
+        let usd = usd_dividend(Some(150.0));
+        let summary = DividendList::new_summary(&[&yen, &usd, &usd_dividend(None)]).unwrap();
+        assert_eq!(summary.currency, Currency::Jpy);
+        assert_eq!(summary.total_dividends_before_tax, Some(6600.0));
+        assert_eq!(
+            summary.security_name.as_deref(),
+            Some("為替レートがない 1 行を除く")
+        );
+        let yen_summary = DividendList::new_summary(&[&yen, &yen]).unwrap();
+        assert_eq!(yen_summary.security_name, None);
+        assert_eq!(yen.format_field("dividends_before_tax", "3000"), "¥ 3,000");
+        assert_eq!(usd.format_field("dividends_before_tax", "24"), "USD 24.00");
+    }
+
+    #[test]
+    fn test_summary_sums_in_minor_units() {
+        let cents = |amount| DividendList {
+            currency: Currency::Usd,
+            dividends_before_tax: Some(amount),
+            taxes: Some(0.0),
+            net_amount_received: Some(amount),
+            ..Default::default()
+        };
+        let (a, b) = (cents(0.1), cents(0.2));
+        let summary = DividendList::new_summary(&[&a, &b]).unwrap();
+
+        assert_eq!(summary.total_dividends_before_tax, Some(0.3));
+        let fields: BTreeMap<_, _> = summary.get_all_fields().into_iter().collect();
+        assert_eq!(
+            fields["total_dividends_before_tax"].as_deref(),
+            Some("0.30")
+        );
+        assert_eq!(fields["total_taxes"].as_deref(), Some("0.00"));
+
+        // 円は小数を付けずに書き出す
+        let yen = DividendList {
+            dividends_before_tax: Some(3000.0),
+            ..Default::default()
+        };
+        let fields: BTreeMap<_, _> = yen.get_all_fields().into_iter().collect();
+        assert_eq!(fields["dividends_before_tax"].as_deref(), Some("3000"));
+    }
+
+    #[test]
+    fn test_deserialize_legacy_unit_price() {
+        let legacy: DividendList = serde_json::from_str(r#"{"unit_price":"0.24"}"#).unwrap();
+        assert_eq!(legacy.unit_price, Some(0.24));
+
+        let dividend: DividendList = serde_json::from_str(r#"{"unit_price":45.5}"#).unwrap();
+        assert_eq!(dividend.unit_price, Some(45.5));
+        assert_eq!(
+            serde_json::from_str::<DividendList>("{}")
+                .unwrap()
+                .unit_price,
+            None
+        );
+    }
+}
@@ -1,12 +1,14 @@
 use chrono::NaiveDate;
 use csv::StringRecord;
 use itertools::Itertools;
+use serde::{Deserialize, Serialize};
 use yew::prelude::*;
 
-use super::receipt_template::ReceiptProps;
-use crate::{services::parser::*, setting::*};
+use super::{receipt_template::ReceiptProps, ReceiptsType};
+use crate::services::{csv_reader::CSVColumns, parser::*, tax_rule::YearToDateLedger};
 
-#[derive(PartialEq, Properties, Debug, Clone, Default)]
+#[derive(PartialEq, Properties, Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DomesticStock {
     pub trade_date: Option<NaiveDate>,                         // 約定日
     pub settlement_date: Option<NaiveDate>,                    // 受渡日
@@ -18,9 +20,19 @@ pub struct DomesticStock {
     pub proceeds: Option<i32>,                                 // 売却/決済額[円]
     pub purchase_price: Option<f64>,                           // 平均取得価額[円]
     pub realized_profit_and_loss: Option<i32>,                 // 実現損益[円]
+    pub withholding_tax: Option<i32>,                          // 源泉徴収税額 (負の場合は還付)
+    pub withholding_balance: Option<i32>,                      // 年初来の源泉徴収税額
     pub total_realized_profit_and_loss: Option<i32>,           // 合計実現損益[円]
-    pub total_taxes: Option<u32>,                              // 源泉徴収税額
+    pub total_taxes: Option<i32>,                              // 源泉徴収税額
     pub total_realized_profit_and_loss_after_tax: Option<i32>, // 損益
+    pub source_file: Option<String>,                           // 取込元ファイル
+}
+
+impl DomesticStock {
+    /// 譲渡損益を計上する日 (受渡日、なければ約定日)
+    pub fn tax_date(&self) -> Option<NaiveDate> {
+        self.settlement_date.or(self.trade_date)
+    }
 }
 
 impl ReceiptProps for DomesticStock {
@@ -36,9 +48,12 @@ impl ReceiptProps for DomesticStock {
             proceeds: None,
             purchase_price: None,
             realized_profit_and_loss: None,
+            withholding_tax: None,
+            withholding_balance: None,
             total_realized_profit_and_loss: None,
             total_taxes: None,
             total_realized_profit_and_loss_after_tax: None,
+            source_file: None,
         }
     }
 
@@ -62,7 +77,11 @@ impl ReceiptProps for DomesticStock {
                 },
             );
 
-        let total_taxes = ((specific_account_total.max(0) as f64) * TAX_RATE) as u32;
+        // 特定口座の源泉徴収税額は `recalculate` で年初来の損益通算から求めた差額の合計
+        let total_taxes = receipts
+            .iter()
+            .filter_map(|domestic_stock| domestic_stock.withholding_tax)
+            .sum::<i32>();
         let total = specific_account_total + nisa_account_total;
 
         Some(Self {
@@ -76,27 +95,79 @@ impl ReceiptProps for DomesticStock {
             proceeds: None,
             purchase_price: None,
             realized_profit_and_loss: None,
+            withholding_tax: None,
+            withholding_balance: None,
             total_realized_profit_and_loss: Some(total),
             total_taxes: Some(total_taxes),
-            total_realized_profit_and_loss_after_tax: Some(total - total_taxes as i32),
+            total_realized_profit_and_loss_after_tax: Some(total - total_taxes),
+            source_file: None,
         })
     }
 
-    fn new_from_string_record(record: StringRecord) -> Self {
+    fn new_from_string_record(record: &StringRecord, columns: &CSVColumns) -> Self {
         Self {
-            trade_date: record.get(0).try_parse_date(),
-            settlement_date: record.get(1).try_parse_date(),
-            security_code: record.get(2).try_parse_string(),
-            security_name: record.get(3).try_parse_string(),
-            account: record.get(4).try_parse_string(),
-            shares: record.get(7).try_parse_num(),
-            asked_price: record.get(8).try_parse_num(),
-            proceeds: record.get(9).try_parse_num(),
-            purchase_price: record.get(10).try_parse_num(),
-            realized_profit_and_loss: record.get(11).try_parse_num(),
+            trade_date: columns.get(record, "trade_date").try_parse_date(),
+            settlement_date: columns.get(record, "settlement_date").try_parse_date(),
+            security_code: columns.get(record, "security_code").try_parse_string(),
+            security_name: columns.get(record, "security_name").try_parse_string(),
+            account: columns.get(record, "account").try_parse_string(),
+            shares: columns.get(record, "shares").try_parse_num(),
+            asked_price: columns.get(record, "asked_price").try_parse_num(),
+            proceeds: columns.get(record, "proceeds").try_parse_num(),
+            purchase_price: columns.get(record, "purchase_price").try_parse_num(),
+            realized_profit_and_loss: columns
+                .get(record, "realized_profit_and_loss")
+                .try_parse_num(),
+            withholding_tax: None,
+            withholding_balance: None,
             total_realized_profit_and_loss: None,
             total_taxes: None,
             total_realized_profit_and_loss_after_tax: None,
+            source_file: None,
+        }
+    }
+
+    fn receipts_type() -> ReceiptsType {
+        ReceiptsType::DomesticStock
+    }
+
+    /// 特定口座の譲渡損益を受渡日順に通算し、源泉徴収税額を求める。
+    ///
+    /// 譲渡損益は受渡日の年に計上されるため、年末に約定して年明けに受け渡した売却は翌年の通算に入る。
+    /// 証券会社は同じ特定口座の投資信託の解約損益も合わせて通算するが、ここでは国内株式の取引だけを
+    /// 通算するため、投資信託の解約がある年は証券会社の年間取引報告書と一致しないことがある。
+    fn recalculate(receipts: &mut [Self]) {
+        let mut ledger = YearToDateLedger::default();
+        let mut order: Vec<usize> = (0..receipts.len()).collect();
+        order.sort_by_key(|&i| (receipts[i].tax_date(), receipts[i].trade_date));
+        for i in order {
+            let receipt = &mut receipts[i];
+            let is_specific = receipt
+                .account
+                .as_deref()
+                .is_some_and(|account| account.contains("特定"));
+            match (
+                is_specific,
+                receipt.tax_date(),
+                receipt.realized_profit_and_loss,
+            ) {
+                (true, Some(date), Some(profit)) => {
+                    receipt.withholding_tax = Some(ledger.post(date, profit as i64) as i32);
+                    receipt.withholding_balance = Some(ledger.withheld() as i32);
+                }
+                _ => {
+                    receipt.withholding_tax = None;
+                    receipt.withholding_balance = None;
+                }
+            }
+        }
+    }
+
+    fn without_derived(&self) -> Self {
+        Self {
+            withholding_tax: None,
+            withholding_balance: None,
+            ..self.clone()
         }
     }
 
@@ -104,6 +175,25 @@ impl ReceiptProps for DomesticStock {
         self.trade_date
     }
 
+    fn row_key(&self) -> String {
+        format!(
+            "{:?}|{}|{}|{:?}|{:?}",
+            self.trade_date,
+            self.security_code.as_deref().unwrap_or_default(),
+            self.account.as_deref().unwrap_or_default(),
+            self.shares,
+            self.proceeds,
+        )
+    }
+
+    fn set_source_file(&mut self, file_name: Option<&str>) {
+        self.source_file = file_name.map(str::to_string);
+    }
+
+    fn get_source_file(&self) -> Option<&str> {
+        self.source_file.as_deref()
+    }
+
     fn get_all_fields(&self) -> Vec<(&'static str, Option<String>)> {
         vec![
             ("trade_date", self.trade_date.map(|d| d.to_string())),
@@ -122,6 +212,14 @@ impl ReceiptProps for DomesticStock {
                 "realized_profit_and_loss",
                 self.realized_profit_and_loss.map(|p| p.to_string()),
             ),
+            (
+                "withholding_tax",
+                self.withholding_tax.map(|p| p.to_string()),
+            ),
+            (
+                "withholding_balance",
+                self.withholding_balance.map(|p| p.to_string()),
+            ),
             (
                 "total_realized_profit_and_loss",
                 self.total_realized_profit_and_loss.map(|p| p.to_string()),
@@ -132,14 +230,15 @@ impl ReceiptProps for DomesticStock {
                 self.total_realized_profit_and_loss_after_tax
                     .map(|p| p.to_string()),
             ),
+            ("source_file", self.source_file.clone()),
         ]
     }
 
     fn view_summary(receipts: &[Self]) -> Html {
         let (total_realized_profit_and_loss, total_taxes, total_realized_profit_and_loss_after_tax) =
             receipts
-                .into_iter()
-                .chunk_by(|receipt| receipt.get_date().expect("get_date error"))
+                .iter()
+                .chunk_by(|receipt| receipt.get_date())
                 .into_iter()
                 .filter_map(|(_, group)| Self::new_summary(&group.collect::<Vec<_>>()))
                 .fold(
@@ -159,7 +258,7 @@ impl ReceiptProps for DomesticStock {
             <tbody>
                 <tr>
                     { Self::render_summary_th_td("total_realized_profit_and_loss", total_realized_profit_and_loss) }
-                    { Self::render_summary_th_td("total_taxes", total_taxes as i32) }
+                    { Self::render_summary_th_td("total_taxes", total_taxes) }
                     { Self::render_summary_th_td("total_realized_profit_and_loss_after_tax", total_realized_profit_and_loss_after_tax) }
                 </tr>
             </tbody>
@@ -178,3 +277,63 @@ impl ReceiptProps for DomesticStock {
         self.security_name.as_deref().unwrap_or_default()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stock(month: u32, account: &str, profit: i32) -> DomesticStock {
+        DomesticStock {
+            trade_date: NaiveDate::from_ymd_opt(2024, month, 10),
+            account: Some(account.to_string()),
+            realized_profit_and_loss: Some(profit),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_recalculate_by_settlement_year() {
+        // 2024 年末に約定し 2025 年に受け渡した利益は、2025 年の損失と通算する
+        let mut receipts = vec![
+            DomesticStock {
+                trade_date: NaiveDate::from_ymd_opt(2024, 12, 27),
+                settlement_date: NaiveDate::from_ymd_opt(2025, 1, 6),
+                ..stock(12, "特定", 100_000)
+            },
+            DomesticStock {
+                trade_date: NaiveDate::from_ymd_opt(2025, 1, 8),
+                settlement_date: NaiveDate::from_ymd_opt(2025, 1, 10),
+                ..stock(1, "特定", -60_000)
+            },
+            stock(12, "特定", 10_000),
+        ];
+        DomesticStock::recalculate(&mut receipts);
+
+        assert_eq!(receipts[0].withholding_tax, Some(20_315));
+        assert_eq!(receipts[1].withholding_tax, Some(-12_189));
+        assert_eq!(receipts[2].withholding_tax, Some(2_031));
+        assert_eq!(receipts[2].withholding_balance, Some(2_031));
+    }
+
+    #[test]
+    fn test_recalculate_nets_losses_year_to_date() {
+        let mut receipts = vec![
+            stock(1, "特定", 100_000),
+            stock(2, "NISA", -50_000),
+            stock(3, "特定", -60_000),
+        ];
+        DomesticStock::recalculate(&mut receipts);
+
+        assert_eq!(receipts[0].withholding_tax, Some(20_315));
+        assert_eq!(receipts[1].withholding_tax, None);
+        assert_eq!(receipts[2].withholding_tax, Some(-12_189));
+        assert_eq!(receipts[2].withholding_balance, Some(8_126));
+
+        // 月ごとの小計を合計すると年初来の源泉徴収税額になる
+        let total_taxes: i32 = receipts
+            .iter()
+            .filter_map(|r| DomesticStock::new_summary(&[r])?.total_taxes)
+            .sum();
+        assert_eq!(total_taxes, 8_126);
+    }
+}
@@ -0,0 +1,161 @@
+use gloo::storage::{LocalStorage, Storage};
+use itertools::Itertools;
+use std::collections::{BTreeMap, BTreeSet};
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+use super::dividend_list::DividendList;
+use crate::data::money::{Currency, ExchangeRates, Money};
+
+/// 入力した為替レートを保存するキー
+const EXCHANGE_RATES_KEY: &str = "exchange_rates";
+
+/// 保存済みの為替レート
+pub fn load_rates() -> ExchangeRates {
+    LocalStorage::get(EXCHANGE_RATES_KEY).unwrap_or_default()
+}
+
+/// 円に換算した行と、為替レートがないため換算できなかった通貨ごとの行数
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct JpyConversion {
+    pub converted: Vec<DividendList>,
+    pub missing: BTreeMap<Currency, usize>,
+}
+
+/// 円に換算する。換算できない行は除き、`missing` に数える。
+pub fn convert_to_jpy(receipts: &[DividendList], rates: &ExchangeRates) -> JpyConversion {
+    let mut conversion = JpyConversion::default();
+    for receipt in receipts {
+        match receipt.in_jpy(rates) {
+            Some(converted) => conversion.converted.push(converted),
+            None => *conversion.missing.entry(receipt.currency).or_default() += 1,
+        }
+    }
+    conversion
+}
+
+/// 換算できずに集計から除いた行の警告
+pub fn render_missing_rates(missing: &BTreeMap<Currency, usize>) -> Html {
+    if missing.is_empty() {
+        return html! {};
+    }
+    let rows = missing
+        .iter()
+        .map(|(currency, count)| format!("{} {} 行", currency.code(), count))
+        .join("、");
+    html! {
+        <div class="alert alert-warning py-2" role="alert">
+            { format!("為替レートがないため、{} を円換算の集計に含めていません。受取金ページで為替レートを入力してください。", rows) }
+        </div>
+    }
+}
+
+/// 取り込んだ行の為替レートの平均 (入力欄の目安に表示する)
+fn imported_rate(receipts: &[DividendList], currency: Currency) -> Option<f64> {
+    let rates: Vec<f64> = receipts
+        .iter()
+        .filter(|r| r.currency == currency)
+        .filter_map(|r| r.exchange_rate)
+        .collect();
+    (!rates.is_empty()).then(|| rates.iter().sum::<f64>() / rates.len() as f64)
+}
+
+#[derive(Properties, PartialEq)]
+pub struct ExchangeRateSummaryProps {
+    pub receipts: Vec<DividendList>,
+}
+
+/// 外貨ごとの為替レートの入力欄と、円に換算した合計
+#[function_component]
+pub fn ExchangeRateSummary(props: &ExchangeRateSummaryProps) -> Html {
+    let rates = use_state(load_rates);
+
+    let currencies: BTreeSet<Currency> = props
+        .receipts
+        .iter()
+        .map(|r| r.currency)
+        .filter(|&currency| currency != Currency::Jpy)
+        .collect();
+    let conversion = convert_to_jpy(&props.receipts, &rates);
+    let total = DividendList::subtotals(&conversion.converted)
+        .into_iter()
+        .next();
+    let zero = Money::zero(Currency::Jpy);
+
+    html! {
+        <>
+            { for currencies.into_iter().map(|currency| {
+                let value = rates.rates.get(&currency).map(|rate| rate.to_string()).unwrap_or_default();
+                let rates = rates.clone();
+                let onchange = Callback::from(move |e: Event| {
+                    let input: HtmlInputElement = e.target_unchecked_into();
+                    let mut updated = (*rates).clone();
+                    updated.set(currency, input.value().trim().parse().unwrap_or(0.0));
+                    let _ = LocalStorage::set(EXCHANGE_RATES_KEY, &updated);
+                    rates.set(updated);
+                });
+                let placeholder = imported_rate(&props.receipts, currency)
+                    .map_or("未入力".to_string(), |rate| format!("取込時 {:.2}", rate));
+                html! {
+                    <tr>
+                        <th class="bg-info text-white text-nowrap">{ format!("為替レート [円/{}]", currency.code()) }</th>
+                        <td colspan="7">
+                            <input
+                                type="number"
+                                class="form-control form-control-sm"
+                                style="max-width: 12rem;"
+                                min="0"
+                                step="0.01"
+                                {placeholder}
+                                {value}
+                                {onchange}
+                            />
+                        </td>
+                    </tr>
+                }
+            })}
+            <tr>
+                <th class="bg-info text-white text-nowrap">{ "円換算" }</th>
+                <td class="text-nowrap">{ Currency::Jpy.code() }</td>
+                { DividendList::render_money_th_td("total_dividends_before_tax", total.as_ref().map_or(zero, |t| t.dividends_before_tax)) }
+                { DividendList::render_money_th_td("foreign_taxes", total.as_ref().map_or(zero, |t| t.foreign_taxes)) }
+                { DividendList::render_money_th_td("total_taxes", total.as_ref().map_or(zero, |t| t.taxes)) }
+                { DividendList::render_money_th_td("total_net_amount_received", total.as_ref().map_or(zero, |t| t.net_amount_received)) }
+            </tr>
+            if !conversion.missing.is_empty() {
+                <tr>
+                    <td colspan="10" class="p-0">{ render_missing_rates(&conversion.missing) }</td>
+                </tr>
+            }
+        </>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_to_jpy_counts_missing_rates() {
+        let dividend = |currency, exchange_rate| DividendList {
+            currency,
+            dividends_before_tax: Some(10.0),
+            exchange_rate,
+            ..Default::default()
+        };
+        let receipts = vec![
+            dividend(Currency::Jpy, None),
+            dividend(Currency::Usd, Some(150.0)),
+            dividend(Currency::Usd, None),
+            dividend(Currency::Eur, None),
+            dividend(Currency::Eur, None),
+        ];
+        let conversion = convert_to_jpy(&receipts, &ExchangeRates::default());
+
+        assert_eq!(conversion.converted.len(), 2);
+        assert_eq!(
+            conversion.missing,
+            BTreeMap::from([(Currency::Usd, 1), (Currency::Eur, 2)])
+        );
+    }
+}
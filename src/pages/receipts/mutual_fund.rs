@@ -1,11 +1,13 @@
 use chrono::NaiveDate;
 use csv::StringRecord;
+use serde::{Deserialize, Serialize};
 use yew::prelude::*;
 
-use super::receipt_template::ReceiptProps;
-use crate::{services::parser::*, setting::*};
+use super::{receipt_template::ReceiptProps, ReceiptsType};
+use crate::services::{csv_reader::CSVColumns, parser::*, tax_rule::TaxRule};
 
-#[derive(PartialEq, Properties, Debug, Clone, Default)]
+#[derive(PartialEq, Properties, Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MutualFund {
     pub trade_date: Option<NaiveDate>,                   // 約定日
     pub settlement_date: Option<NaiveDate>,              // 受渡日
@@ -20,6 +22,7 @@ pub struct MutualFund {
     pub realized_profit_and_loss: Option<i32>,           // 実現損益［円］
     pub taxes: Option<i32>,                              // 税額
     pub realized_profit_and_loss_after_tax: Option<i32>, // 実現損益(税引)
+    pub source_file: Option<String>,                     // 取込元ファイル
 }
 
 impl ReceiptProps for MutualFund {
@@ -27,14 +30,19 @@ impl ReceiptProps for MutualFund {
         MutualFund::default()
     }
 
-    fn new_from_string_record(record: StringRecord) -> Self {
-        let tmp_account = record.get(4).try_parse_string().unwrap();
-        let tmp_realized_profit_and_loss = record.get(11).try_parse_num();
+    fn new_from_string_record(record: &StringRecord, columns: &CSVColumns) -> Self {
+        let tmp_account = columns.get(record, "account").try_parse_string().unwrap();
+        let trade_date = columns.get(record, "trade_date").try_parse_date();
+        let tmp_realized_profit_and_loss = columns
+            .get(record, "realized_profit_and_loss")
+            .try_parse_num();
         let (taxes, tmp_realized_profit_and_loss_after_tax) =
             tmp_realized_profit_and_loss.map_or((None, None), |profit| {
                 if profit > 0 {
                     if tmp_account.contains("特定") {
-                        let tmp_taxes = (profit as f64 * TAX_RATE) as i32;
+                        let tmp_taxes = trade_date.map_or(0, |date| {
+                            TaxRule::for_date(date).withhold(profit as i64).total() as i32
+                        });
                         (Some(tmp_taxes), Some(profit - tmp_taxes))
                     } else {
                         (Some(0), Some(profit))
@@ -45,26 +53,56 @@ impl ReceiptProps for MutualFund {
             });
 
         Self {
-            trade_date: record.get(0).try_parse_date(),
-            settlement_date: record.get(1).try_parse_date(),
-            fund_name: record.get(2).try_parse_string(),
-            dividends: record.get(3).try_parse_string(),
+            trade_date,
+            settlement_date: columns.get(record, "settlement_date").try_parse_date(),
+            fund_name: columns.get(record, "fund_name").try_parse_string(),
+            dividends: columns.get(record, "dividends").try_parse_string(),
             account: Some(tmp_account),
-            shares: record.get(6).try_parse_num(),
-            exchange_rate: record.get(7).try_parse_num(),
-            cancellation_unit_price_yen: record.get(8).try_parse_num(),
-            cancellation_amount_yen: record.get(9).try_parse_num(),
-            average_acquisition_price_yen: record.get(10).try_parse_num(),
+            shares: columns.get(record, "shares").try_parse_num(),
+            exchange_rate: columns.get(record, "exchange_rate").try_parse_num(),
+            cancellation_unit_price_yen: columns
+                .get(record, "cancellation_unit_price_yen")
+                .try_parse_num(),
+            cancellation_amount_yen: columns
+                .get(record, "cancellation_amount_yen")
+                .try_parse_num(),
+            average_acquisition_price_yen: columns
+                .get(record, "average_acquisition_price_yen")
+                .try_parse_num(),
             realized_profit_and_loss: tmp_realized_profit_and_loss,
-            taxes: taxes,
+            taxes,
             realized_profit_and_loss_after_tax: tmp_realized_profit_and_loss_after_tax,
+            source_file: None,
         }
     }
 
+    fn receipts_type() -> ReceiptsType {
+        ReceiptsType::MutualFund
+    }
+
     fn get_date(&self) -> Option<NaiveDate> {
         self.trade_date
     }
 
+    fn row_key(&self) -> String {
+        format!(
+            "{:?}|{}|{}|{:?}|{:?}",
+            self.trade_date,
+            self.fund_name.as_deref().unwrap_or_default(),
+            self.account.as_deref().unwrap_or_default(),
+            self.shares,
+            self.cancellation_amount_yen,
+        )
+    }
+
+    fn set_source_file(&mut self, file_name: Option<&str>) {
+        self.source_file = file_name.map(str::to_string);
+    }
+
+    fn get_source_file(&self) -> Option<&str> {
+        self.source_file.as_deref()
+    }
+
     fn get_all_fields(&self) -> Vec<(&'static str, Option<String>)> {
         vec![
             ("trade_date", self.trade_date.map(|d| d.to_string())),
@@ -99,6 +137,7 @@ impl ReceiptProps for MutualFund {
                 self.realized_profit_and_loss_after_tax
                     .map(|s| s.to_string()),
             ),
+            ("source_file", self.source_file.clone()),
         ]
     }
 
@@ -108,9 +147,9 @@ impl ReceiptProps for MutualFund {
                 (0, 0, 0),
                 |(total_realized_profit_and_loss, withholding_tax, profit_and_loss), p| {
                     (
-                        total_realized_profit_and_loss + p.realized_profit_and_loss.unwrap(),
-                        withholding_tax + p.taxes.unwrap(),
-                        profit_and_loss + p.realized_profit_and_loss_after_tax.unwrap(),
+                        total_realized_profit_and_loss + p.realized_profit_and_loss.unwrap_or(0),
+                        withholding_tax + p.taxes.unwrap_or(0),
+                        profit_and_loss + p.realized_profit_and_loss_after_tax.unwrap_or(0),
                     )
                 },
             );
@@ -119,7 +158,7 @@ impl ReceiptProps for MutualFund {
             <tbody>
                 <tr>
                     { Self::render_summary_th_td("total_realized_profit_and_loss", total_realized_profit_and_loss) }
-                    { Self::render_summary_th_td("total_taxes", total_taxes as i32) }
+                    { Self::render_summary_th_td("total_taxes", total_taxes) }
                     { Self::render_summary_th_td("total_realized_profit_and_loss_after_tax", total_realized_profit_and_loss_after_tax) }
                 </tr>
             </tbody>
@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use thiserror::Error;
+
+use super::receipt_template::ReceiptProps;
+use crate::services::indexed_db::{self, IndexedDbError, RECEIPTS_STORE};
+
+/// 保存形式のバージョン
+const DATA_VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum ReceiptStorageError {
+    #[error(transparent)]
+    IndexedDb(#[from] IndexedDbError),
+
+    #[error("保存済みデータを読み込めません: {0}")]
+    InvalidData(String),
+
+    #[error("新しいバージョン ({0}) で保存されたデータのため読み込めません")]
+    UnsupportedVersion(u32),
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredReceipts<R> {
+    version: u32,
+    receipts: R,
+}
+
+fn storage_key<T: ReceiptProps>() -> String {
+    format!("{:?}", T::receipts_type())
+}
+
+/// 保存済みの受取金データを読み込む。
+///
+/// 読み込めないデータは空とみなさずエラーにする (上書きして失わないようにするため)。
+pub async fn load<T: ReceiptProps>() -> Result<Vec<T>, ReceiptStorageError> {
+    match indexed_db::get(RECEIPTS_STORE, &storage_key::<T>()).await? {
+        Some(json) => decode(&json),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// 受取金データを保存する。空の場合は保存済みデータを削除する。
+pub async fn save<T: ReceiptProps>(receipts: &[T]) -> Result<(), IndexedDbError> {
+    if receipts.is_empty() {
+        return indexed_db::delete(RECEIPTS_STORE, &storage_key::<T>()).await;
+    }
+    indexed_db::put(RECEIPTS_STORE, &storage_key::<T>(), &encode(receipts)).await
+}
+
+fn encode<T: ReceiptProps>(receipts: &[T]) -> String {
+    serde_json::to_string(&StoredReceipts {
+        version: DATA_VERSION,
+        receipts,
+    })
+    .unwrap_or_default()
+}
+
+fn decode<T: ReceiptProps>(json: &str) -> Result<Vec<T>, ReceiptStorageError> {
+    let invalid = |err: serde_json::Error| ReceiptStorageError::InvalidData(err.to_string());
+    let value = serde_json::from_str::<Value>(json).map_err(invalid)?;
+    let version = value
+        .get("version")
+        .and_then(Value::as_u64)
+        .ok_or_else(|| ReceiptStorageError::InvalidData("バージョンがありません".to_string()))?
+        as u32;
+    let value = migrate(version, value)?;
+    serde_json::from_value::<StoredReceipts<Vec<T>>>(value)
+        .map(|stored| stored.receipts)
+        .map_err(invalid)
+}
+
+/// 保存形式を現在のバージョンに変換する。
+///
+/// フィールドの追加は `#[serde(default)]` で吸収できるため、
+/// 名前や型を変えたときにここへ変換処理を追加する。
+/// 新しいバージョンで保存されたデータは読み込まない。
+fn migrate(version: u32, value: Value) -> Result<Value, ReceiptStorageError> {
+    match version {
+        DATA_VERSION => Ok(value),
+        _ => Err(ReceiptStorageError::UnsupportedVersion(version)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pages::receipts::dividend_list::DividendList;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_encode_decode() {
+        let receipts = vec![DividendList {
+            settlement_date: NaiveDate::from_ymd_opt(2024, 3, 28),
+            security_code: Some("7203".into()),
+            dividends_before_tax: Some(3000.0),
+            source_file: Some("dividend_2024.csv".into()),
+            ..Default::default()
+        }];
+
+        assert_eq!(
+            decode::<DividendList>(&encode(&receipts)).unwrap(),
+            receipts
+        );
+    }
+
+    #[test]
+    fn test_decode_fills_missing_fields() {
+        let json =
+            r#"{"version":1,"receipts":[{"settlement_date":"2024-03-28","security_code":"7203"}]}"#;
+        let receipts = decode::<DividendList>(json).unwrap();
+
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].security_code.as_deref(), Some("7203"));
+        assert_eq!(receipts[0].source_file, None);
+    }
+
+    #[test]
+    fn test_decode_rejects_unknown_version() {
+        let json = r#"{"version":999,"receipts":[{"security_code":"7203"}]}"#;
+        assert!(matches!(
+            decode::<DividendList>(json),
+            Err(ReceiptStorageError::UnsupportedVersion(999))
+        ));
+    }
+
+    #[test]
+    fn test_decode_rejects_invalid_data() {
+        let wrong_type = r#"{"version":1,"receipts":[{"security_code":7203}]}"#;
+        for json in ["not json", r#"{"receipts":[]}"#, wrong_type] {
+            assert!(matches!(
+                decode::<DividendList>(json),
+                Err(ReceiptStorageError::InvalidData(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,666 @@
+use chrono::{DateTime, Local, Utc};
+use gloo::storage::{LocalStorage, Storage};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use super::{receipt_template::ReceiptProps, ReceiptsType};
+use crate::{
+    data::use_info::UserInfo,
+    services::shoken_web_api::{ApiClient, ApiError},
+    setting::RECEIPTS_SYNC_TIMEOUT_MS,
+};
+
+/// サーバーに保存する受取金
+///
+/// 一度も同期していない利用者には、サーバーは `revision` が 0 の空の一覧を 200 で返す。
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "T: ReceiptProps")]
+struct RemoteReceipts<T> {
+    revision: u64, // 保存するたびにサーバーが増やす番号
+    #[serde(default)]
+    receipts: Vec<T>,
+    #[serde(default)]
+    deleted: Vec<String>, // 削除した行の行キー (他の端末にも削除を伝える)
+}
+
+/// 同じ行キーでサーバーと内容が異なる場合にどちらを残すか
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Prefer {
+    Local,
+    Remote,
+}
+
+/// 保存の競合 (412) が続いたときに同期をやり直す回数
+const SYNC_ATTEMPTS: usize = 3;
+
+/// 同期の結果
+#[derive(Clone, PartialEq, Debug)]
+pub struct SyncResult<T> {
+    pub receipts: Vec<T>,
+    pub downloaded: usize, // サーバーにだけあった行
+    pub uploaded: usize,   // この端末にだけあった行
+    pub conflicts: usize,  // 行キーが同じで内容が異なる行
+    pub deleted: usize,    // どちらかの端末で削除されたため除いた行
+    pub prefer: Prefer,    // 競合した行で残した側
+    pub synced_at: DateTime<Utc>,
+    deleted_keys: Vec<String>, // サーバーに送る削除済みの行キー
+    changed: bool,             // サーバーに保存し直す必要がある
+}
+
+fn api_path<T: ReceiptProps>() -> &'static str {
+    match T::receipts_type() {
+        ReceiptsType::Dividend => "/receipts/dividend",
+        ReceiptsType::DomesticStock => "/receipts/domestic_stock",
+        ReceiptsType::MutualFund => "/receipts/mutual_fund",
+    }
+}
+
+// 同期の記録は利用者ごとに分ける (別のアカウントでログインしたときに、前の利用者の記録を削除とみなさないため)
+fn synced_at_key<T: ReceiptProps>(user: &str) -> String {
+    format!("receipts_synced_at:{}:{:?}", user, T::receipts_type())
+}
+
+fn synced_keys_key<T: ReceiptProps>(user: &str) -> String {
+    format!("receipts_synced_keys:{}:{:?}", user, T::receipts_type())
+}
+
+/// `user` が最後に同期した日時
+pub fn last_synced_at<T: ReceiptProps>(user: &str) -> Option<DateTime<Utc>> {
+    LocalStorage::get(synced_at_key::<T>(user)).ok()
+}
+
+/// `user` が最後に同期したときの `sync_keys` (この端末で削除した行を見分けるのに使う)
+pub fn last_synced_keys<T: ReceiptProps>(user: &str) -> HashSet<String> {
+    LocalStorage::get(synced_keys_key::<T>(user)).unwrap_or_default()
+}
+
+/// 同期で行を見分けるキー。行キーに、同じ行キーの行の中で何番目かを付ける。
+///
+/// 同日・同額の約定のように行キーが同じ行も別の行として残すため (`csv_import::merge_receipts` と同じ)。
+pub fn sync_keys<T: ReceiptProps>(receipts: &[T]) -> Vec<String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    receipts
+        .iter()
+        .map(|receipt| {
+            let key = receipt.row_key();
+            let count = counts.entry(key.clone()).or_default();
+            *count += 1;
+            format!("{}#{}", key, count)
+        })
+        .collect()
+}
+
+/// この端末とサーバーの行を `sync_keys` で突き合わせ、両方の行を含む一覧を作る。
+///
+/// キーが同じで内容が異なる行は競合として数え、`prefer` の側を残す。
+/// 取り込み元のファイル名は端末ごとに異なることがあるため、内容の比較には含めない。
+///
+/// `synced_keys` は前回の同期時のキーで、そこにあってこの端末にない行はこの端末で削除したものとして
+/// サーバーからも削除する。サーバーで削除済みの行は、前回の同期後にこの端末で取り込み直したものでなければ除く。
+fn merge<T: ReceiptProps>(
+    local: &[T],
+    remote: RemoteReceipts<T>,
+    synced_keys: &HashSet<String>,
+    prefer: Prefer,
+) -> SyncResult<T> {
+    let local_keys = sync_keys(local);
+    let local_key_set: HashSet<&String> = local_keys.iter().collect();
+    let remote_deleted: HashSet<String> = remote.deleted.into_iter().collect();
+
+    let remote_order = sync_keys(&remote.receipts);
+    let mut remote_by_key: HashMap<String, T> =
+        remote_order.iter().cloned().zip(remote.receipts).collect();
+
+    let (mut uploaded, mut conflicts, mut deleted) = (0, 0, 0);
+    let mut merged = Vec::new();
+    let mut merged_keys = HashSet::new();
+    for (receipt, key) in local.iter().zip(&local_keys) {
+        match remote_by_key.remove(key) {
+            None if remote_deleted.contains(key) && synced_keys.contains(key) => {
+                deleted += 1;
+                continue;
+            }
+            None => {
+                uploaded += 1;
+                merged.push(receipt.clone());
+            }
+            Some(remote) if same_content(&remote, receipt) => merged.push(receipt.clone()),
+            Some(remote) => {
+                conflicts += 1;
+                merged.push(match prefer {
+                    Prefer::Local => receipt.clone(),
+                    Prefer::Remote => remote,
+                });
+            }
+        }
+        merged_keys.insert(key.clone());
+    }
+    let (mut downloaded, mut deleted_here) = (0, 0);
+    for key in remote_order {
+        let Some(receipt) = remote_by_key.remove(&key) else {
+            continue;
+        };
+        if synced_keys.contains(&key) && !local_key_set.contains(&key) {
+            deleted_here += 1;
+        } else {
+            downloaded += 1;
+            merged.push(receipt);
+            merged_keys.insert(key);
+        }
+    }
+
+    let deleted_keys: Vec<String> = remote_deleted
+        .iter()
+        .chain(
+            synced_keys
+                .iter()
+                .filter(|key| !local_key_set.contains(key)),
+        )
+        .filter(|key| !merged_keys.contains(*key))
+        .cloned()
+        .sorted()
+        .dedup()
+        .collect();
+
+    merged.sort_by_key(|r| r.get_date().unwrap_or_default());
+    T::recalculate(&mut merged);
+    SyncResult {
+        receipts: merged,
+        downloaded,
+        uploaded,
+        conflicts,
+        deleted: deleted + deleted_here,
+        prefer,
+        synced_at: Utc::now(),
+        changed: uploaded > 0
+            || deleted_here > 0
+            || (conflicts > 0 && prefer == Prefer::Local)
+            || deleted_keys.len() != remote_deleted.len(),
+        deleted_keys,
+    }
+}
+
+/// 行をまたいで求める項目と取り込み元のファイル名を除いて比較する
+fn same_content<T: ReceiptProps>(a: &T, b: &T) -> bool {
+    let comparable = |receipt: &T| {
+        let mut receipt = receipt.without_derived();
+        receipt.set_source_file(None);
+        receipt
+    };
+    comparable(a) == comparable(b)
+}
+
+/// サーバーの受取金を取得して統合し、統合した一覧をサーバーに保存する。
+///
+/// 保存時は取得したときの `revision` を `If-Match` で送り、その間に他の端末が保存していた場合
+/// (412) は取得からやり直す。
+pub async fn sync<T: ReceiptProps>(
+    client: &ApiClient,
+    local: &[T],
+    synced_keys: &HashSet<String>,
+    prefer: Prefer,
+) -> Result<SyncResult<T>, ApiError> {
+    for _ in 0..SYNC_ATTEMPTS {
+        let remote: RemoteReceipts<T> = client.get(api_path::<T>()).send().await?;
+        let revision = remote.revision;
+
+        let merged = merge(local, remote, synced_keys, prefer);
+        if !merged.changed {
+            return Ok(merged);
+        }
+        let result = client
+            .put(api_path::<T>())
+            .timeout(Some(RECEIPTS_SYNC_TIMEOUT_MS))
+            .header("If-Match", &format!("\"{}\"", revision))
+            .json(&RemoteReceipts {
+                revision,
+                receipts: merged.receipts.clone(),
+                deleted: merged.deleted_keys.clone(),
+            })?
+            .send::<()>()
+            .await;
+        match result {
+            Ok(()) => return Ok(merged),
+            Err(ApiError::PreconditionFailed) => continue,
+            Err(err) => return Err(err),
+        }
+    }
+    Err(ApiError::PreconditionFailed)
+}
+
+/// サーバーに保存した受取金を削除する。この端末の行はそのまま残す。
+pub async fn delete_remote<T: ReceiptProps>(client: &ApiClient) -> Result<(), ApiError> {
+    client.delete(api_path::<T>()).send().await
+}
+
+#[derive(Properties, PartialEq)]
+pub struct SyncPanelProps<T: PartialEq> {
+    pub receipts: Vec<T>,
+    pub on_synced: Callback<Vec<T>>,
+}
+
+/// ログインしているときに表示する、サーバーとの同期ボタンと最終同期日時
+#[function_component]
+pub fn SyncPanel<T: ReceiptProps>(props: &SyncPanelProps<T>) -> Html {
+    let user_info = use_context::<UseReducerHandle<UserInfo>>();
+    let user = user_info
+        .as_ref()
+        .map(|user_info| user_info.email.clone())
+        .unwrap_or_default();
+    let synced_at = use_state(|| last_synced_at::<T>(&user));
+    let syncing = use_state(|| false);
+    let result = use_state(|| None::<Result<SyncResult<T>, ApiError>>);
+    let remote_deleted = use_state(|| None::<Result<(), ApiError>>);
+    {
+        let synced_at = synced_at.clone();
+        let result = result.clone();
+        let remote_deleted = remote_deleted.clone();
+        use_effect_with(user.clone(), move |user| {
+            synced_at.set(last_synced_at::<T>(user));
+            result.set(None);
+            remote_deleted.set(None);
+        });
+    }
+
+    let Some(token) = user_info
+        .filter(|user_info| user_info.is_authenticated(Utc::now()))
+        .and_then(|user_info| user_info.access_token.clone())
+    else {
+        return html! {};
+    };
+
+    let on_sync = |prefer: Prefer| {
+        let receipts = props.receipts.clone();
+        let on_synced = props.on_synced.clone();
+        let token = token.clone();
+        let user = user.clone();
+        let synced_at = synced_at.clone();
+        let syncing = syncing.clone();
+        let result = result.clone();
+        let remote_deleted = remote_deleted.clone();
+        Callback::from(move |_: MouseEvent| {
+            let client = ApiClient {
+                auth_token: Some(token.clone()),
+                ..Default::default()
+            };
+            remote_deleted.set(None);
+            let user = user.clone();
+            let receipts = receipts.clone();
+            let on_synced = on_synced.clone();
+            let synced_at = synced_at.clone();
+            let syncing = syncing.clone();
+            let result = result.clone();
+            syncing.set(true);
+            spawn_local(async move {
+                let synced = sync(&client, &receipts, &last_synced_keys::<T>(&user), prefer).await;
+                if let Ok(synced) = &synced {
+                    let keys: HashSet<String> = sync_keys(&synced.receipts).into_iter().collect();
+                    let _ = LocalStorage::set(synced_keys_key::<T>(&user), keys);
+                    let _ = LocalStorage::set(synced_at_key::<T>(&user), synced.synced_at);
+                    synced_at.set(Some(synced.synced_at));
+                    on_synced.emit(synced.receipts.clone());
+                }
+                result.set(Some(synced));
+                syncing.set(false);
+            });
+        })
+    };
+
+    let on_delete_remote = {
+        let token = token.clone();
+        let user = user.clone();
+        let synced_at = synced_at.clone();
+        let syncing = syncing.clone();
+        let result = result.clone();
+        let remote_deleted = remote_deleted.clone();
+        Callback::from(move |_: MouseEvent| {
+            let client = ApiClient {
+                auth_token: Some(token.clone()),
+                ..Default::default()
+            };
+            let user = user.clone();
+            let synced_at = synced_at.clone();
+            let syncing = syncing.clone();
+            let remote_deleted = remote_deleted.clone();
+            result.set(None);
+            syncing.set(true);
+            spawn_local(async move {
+                let deleted = delete_remote::<T>(&client).await;
+                if deleted.is_ok() {
+                    // 次の同期でこの端末の行を削除済みとみなさないよう、前回の同期の記録も消す
+                    LocalStorage::delete(synced_keys_key::<T>(&user));
+                    LocalStorage::delete(synced_at_key::<T>(&user));
+                    synced_at.set(None);
+                }
+                remote_deleted.set(Some(deleted));
+                syncing.set(false);
+            });
+        })
+    };
+
+    html! {
+        <div class="d-flex flex-wrap align-items-center gap-2 mt-2">
+            <button type="button" class="btn btn-outline-info btn-sm" disabled={*syncing} onclick={on_sync(Prefer::Local)}>
+                if *syncing {
+                    <span class="spinner-border spinner-border-sm me-1" role="status" aria-hidden="true"></span>
+                }
+                { "サーバーと同期" }
+            </button>
+            <small class="text-muted">
+                {
+                    match *synced_at {
+                        Some(synced_at) => format!("最終同期: {}", synced_at.with_timezone(&Local).format("%Y/%m/%d %H:%M")),
+                        None => "未同期".to_string(),
+                    }
+                }
+            </small>
+            {
+                match &*result {
+                    Some(Ok(synced)) if synced.conflicts > 0 && synced.prefer == Prefer::Remote => html! {
+                        <small class="text-warning">
+                            { format!("{} 行をサーバーの内容に置き換えました", synced.conflicts) }
+                        </small>
+                    },
+                    Some(Ok(synced)) if synced.conflicts > 0 => html! {
+                        <span class="text-warning small">
+                            { format!("{} 行がサーバーと異なるため、この端末の内容を保存しました", synced.conflicts) }
+                            <button type="button" class="btn btn-link btn-sm" disabled={*syncing} onclick={on_sync(Prefer::Remote)}>
+                                { "サーバーの内容を使う" }
+                            </button>
+                        </span>
+                    },
+                    Some(Ok(synced)) => html! {
+                        <small class="text-success">
+                            { format!("取得 {} 行 / 送信 {} 行 / 削除 {} 行", synced.downloaded, synced.uploaded, synced.deleted) }
+                        </small>
+                    },
+                    Some(Err(err)) => html! { <small class="text-danger">{ err.to_string() }</small> },
+                    None => html! {},
+                }
+            }
+            {
+                match &*remote_deleted {
+                    Some(Ok(())) => html! { <small class="text-success">{ "サーバーのデータを削除しました" }</small> },
+                    Some(Err(err)) => html! { <small class="text-danger">{ err.to_string() }</small> },
+                    None => html! {},
+                }
+            }
+            if synced_at.is_some() {
+                <button type="button" class="btn btn-link btn-sm text-danger ms-auto" disabled={*syncing} onclick={on_delete_remote}>
+                    { "サーバーのデータを削除" }
+                </button>
+            }
+        </div>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        pages::receipts::dividend_list::DividendList,
+        services::shoken_web_api::mock_server::{block_on, mock_server_sequence},
+    };
+    use chrono::NaiveDate;
+    use std::time::Duration;
+
+    fn dividend(day: u32, amount: i32, source_file: &str) -> DividendList {
+        DividendList {
+            settlement_date: NaiveDate::from_ymd_opt(2024, 3, day),
+            security_code: Some("7203".into()),
+            dividends_before_tax: Some(amount as f64),
+            source_file: Some(source_file.into()),
+            ..Default::default()
+        }
+    }
+
+    fn remote(
+        receipts: Vec<DividendList>,
+        deleted: &[&DividendList],
+    ) -> RemoteReceipts<DividendList> {
+        RemoteReceipts {
+            revision: 1,
+            receipts,
+            deleted: deleted.iter().map(|r| key(r)).collect(),
+        }
+    }
+
+    fn key(receipt: &DividendList) -> String {
+        format!("{}#1", receipt.row_key())
+    }
+
+    fn keys(receipts: &[&DividendList]) -> HashSet<String> {
+        receipts.iter().map(|r| key(r)).collect()
+    }
+
+    #[test]
+    fn test_merge() {
+        let local = vec![dividend(1, 1000, "a.csv"), dividend(2, 2000, "a.csv")];
+        let remote_receipts = vec![
+            dividend(2, 2000, "b.csv"),
+            dividend(3, 3000, "b.csv"),
+            dividend(3, 3000, "b.csv"),
+        ];
+
+        // 取り込み元のファイル名だけが異なる行は競合としない
+        let merged = merge(
+            &local,
+            remote(remote_receipts.clone(), &[]),
+            &HashSet::new(),
+            Prefer::Local,
+        );
+        // 同日・同額の行は別の行として両方残す
+        assert_eq!(
+            merged.receipts,
+            vec![
+                local[0].clone(),
+                local[1].clone(),
+                remote_receipts[1].clone(),
+                remote_receipts[2].clone()
+            ]
+        );
+        assert_eq!(
+            (merged.downloaded, merged.uploaded, merged.conflicts),
+            (2, 1, 0)
+        );
+        assert!(merged.changed);
+    }
+
+    #[test]
+    fn test_merge_identical_rows() {
+        let local = vec![dividend(3, 3000, "a.csv"), dividend(3, 3000, "a.csv")];
+        assert_eq!(
+            sync_keys(&local),
+            vec![
+                format!("{}#1", local[0].row_key()),
+                format!("{}#2", local[0].row_key())
+            ]
+        );
+
+        // 同じ行が両方に 2 行ずつあれば、変更はないので保存し直さない
+        let merged = merge(
+            &local,
+            remote(local.clone(), &[]),
+            &keys(&[&local[0]]),
+            Prefer::Local,
+        );
+        assert_eq!(merged.receipts, local);
+        assert_eq!((merged.downloaded, merged.uploaded), (0, 0));
+        assert!(!merged.changed);
+
+        // この端末で 1 行だけ削除した
+        let synced_keys: HashSet<String> = sync_keys(&local).into_iter().collect();
+        let merged = merge(
+            &local[..1],
+            remote(local.clone(), &[]),
+            &synced_keys,
+            Prefer::Local,
+        );
+        assert_eq!(merged.receipts, local[..1]);
+        assert_eq!(
+            merged.deleted_keys,
+            vec![format!("{}#2", local[0].row_key())]
+        );
+    }
+
+    #[test]
+    fn test_merge_conflict() {
+        let local = vec![dividend(2, 2000, "a.csv")];
+        let remote_receipts = vec![DividendList {
+            taxes: Some(406.0),
+            ..dividend(2, 2000, "a.csv")
+        }];
+
+        let merged = merge(
+            &local,
+            remote(remote_receipts.clone(), &[]),
+            &HashSet::new(),
+            Prefer::Local,
+        );
+        assert_eq!(merged.conflicts, 1);
+        assert_eq!(merged.receipts, local);
+
+        let merged = merge(
+            &local,
+            remote(remote_receipts.clone(), &[]),
+            &HashSet::new(),
+            Prefer::Remote,
+        );
+        assert_eq!(merged.receipts, remote_receipts);
+        assert!(!merged.changed);
+    }
+
+    #[test]
+    fn test_merge_deletions() {
+        let (a, b, c) = (
+            dividend(1, 1000, "a.csv"),
+            dividend(2, 2000, "a.csv"),
+            dividend(3, 3000, "a.csv"),
+        );
+        // 前回 a, b, c を同期した後、この端末で a を、他の端末で b を削除した
+        let merged = merge(
+            &[b.clone(), c.clone()],
+            remote(vec![a.clone(), c.clone()], &[&b]),
+            &keys(&[&a, &b, &c]),
+            Prefer::Local,
+        );
+
+        assert_eq!(merged.receipts, vec![c.clone()]);
+        assert_eq!(merged.deleted, 2);
+        assert_eq!(merged.deleted_keys, {
+            let mut deleted = vec![key(&a), key(&b)];
+            deleted.sort();
+            deleted
+        });
+        assert!(merged.changed);
+
+        // 削除された行でも、前回の同期の後に取り込み直したものは残す
+        let merged = merge(
+            std::slice::from_ref(&b),
+            remote(vec![], &[&b]),
+            &HashSet::new(),
+            Prefer::Local,
+        );
+        assert_eq!(merged.receipts, vec![b.clone()]);
+        assert_eq!(merged.uploaded, 1);
+        assert!(merged.deleted_keys.is_empty());
+    }
+
+    #[test]
+    fn test_sync_with_mock_server() {
+        let (client, received) = mock_server_sequence(
+            vec![
+                (
+                    "200 OK",
+                    r#"{"revision":3,"receipts":[{"settlement_date":"2024-03-03","security_code":"7203","dividends_before_tax":3000,"source_file":"b.csv"}]}"#,
+                ),
+                ("204 No Content", ""),
+            ],
+            Duration::ZERO,
+        );
+        let local = vec![dividend(1, 1000, "a.csv")];
+        let synced = block_on(sync(&client, &local, &HashSet::new(), Prefer::Local)).unwrap();
+
+        assert_eq!(
+            synced.receipts,
+            vec![local[0].clone(), dividend(3, 3000, "b.csv")]
+        );
+        assert_eq!(
+            (synced.downloaded, synced.uploaded, synced.conflicts),
+            (1, 1, 0)
+        );
+        assert!(received
+            .recv()
+            .unwrap()
+            .starts_with("GET /receipts/dividend HTTP/1.1"));
+        let put = received.recv().unwrap();
+        assert!(put.starts_with("PUT /receipts/dividend HTTP/1.1"));
+        assert!(put.to_lowercase().contains(r#"if-match: "3""#));
+        assert!(put.contains(r#""source_file":"a.csv""#));
+    }
+
+    #[test]
+    fn test_delete_remote() {
+        let (client, received) = mock_server_sequence(vec![("204 No Content", "")], Duration::ZERO);
+        block_on(delete_remote::<DividendList>(&client)).unwrap();
+
+        assert!(received
+            .recv()
+            .unwrap()
+            .starts_with("DELETE /receipts/dividend HTTP/1.1"));
+    }
+
+    #[test]
+    fn test_sync_first_time() {
+        let (client, received) = mock_server_sequence(
+            vec![
+                ("200 OK", r#"{"revision":0,"receipts":[]}"#),
+                ("204 No Content", ""),
+            ],
+            Duration::ZERO,
+        );
+        let local = vec![dividend(1, 1000, "a.csv")];
+        let synced = block_on(sync(&client, &local, &HashSet::new(), Prefer::Local)).unwrap();
+
+        assert_eq!(synced.uploaded, 1);
+        received.recv().unwrap();
+        assert!(received.recv().unwrap().starts_with("PUT "));
+    }
+
+    #[test]
+    fn test_sync_does_not_treat_not_found_as_empty() {
+        // 404 は URL の誤りなどでも返るため、未同期とはみなさず何も送らない
+        let (client, _) = mock_server_sequence(vec![("404 Not Found", "")], Duration::ZERO);
+        let local = vec![dividend(1, 1000, "a.csv")];
+        let synced = block_on(sync(&client, &local, &HashSet::new(), Prefer::Local));
+
+        assert_eq!(synced, Err(ApiError::NotFound));
+    }
+
+    #[test]
+    fn test_sync_retries_after_concurrent_update() {
+        // 取得から保存までの間に他の端末が保存したので、取得し直してから保存する
+        let (client, received) = mock_server_sequence(
+            vec![
+                ("200 OK", r#"{"revision":1,"receipts":[]}"#),
+                ("412 Precondition Failed", ""),
+                (
+                    "200 OK",
+                    r#"{"revision":2,"receipts":[{"settlement_date":"2024-03-03","security_code":"7203","dividends_before_tax":3000}]}"#,
+                ),
+                ("204 No Content", ""),
+            ],
+            Duration::ZERO,
+        );
+        let local = vec![dividend(1, 1000, "a.csv")];
+        let synced = block_on(sync(&client, &local, &HashSet::new(), Prefer::Local)).unwrap();
+
+        assert_eq!((synced.downloaded, synced.uploaded), (1, 1));
+        let requests: Vec<String> = received.iter().take(4).collect();
+        assert!(requests[1].to_lowercase().contains(r#"if-match: "1""#));
+        assert!(requests[3].to_lowercase().contains(r#"if-match: "2""#));
+    }
+}
@@ -2,39 +2,211 @@ use chrono::NaiveDate;
 use csv::StringRecord;
 use gloo::console;
 use itertools::Itertools;
-use std::ops::Not;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{ops::Not, rc::Rc};
+use strum::{EnumMessage, IntoEnumIterator};
 use wasm_bindgen_futures::spawn_local;
-use web_sys::{File, HtmlInputElement};
+use web_sys::{File, FileList, HtmlInputElement};
 use yew::{prelude::*, virtual_dom::VNode};
+use yew_router::prelude::*;
 
-use crate::{services::*, setting::*};
+use super::{
+    csv_import::{self, ImportOptions, ImportStatus},
+    receipt_storage,
+    receipt_sync::SyncPanel,
+    ReceiptsType,
+};
+use crate::{
+    app::Route,
+    services::{
+        csv_reader::{CSVColumns, CSVEncoding},
+        csv_writer::ExportFormat,
+        *,
+    },
+    setting::*,
+};
 
 #[derive(Properties, PartialEq, Debug, Clone)]
 pub struct ReceiptTemplateProps {
     pub name: String,
+    /// 他のタブで選択され、このタブで読み込み直す CSV ファイル (さらに別の帳票のファイルを含むことがある)
+    #[prop_or_default]
+    pub files: Vec<File>,
+    /// 選択された CSV が全て別の帳票だった場合に、判定結果とファイルを通知する
+    pub on_detect: Callback<(ReceiptsType, Vec<File>)>,
+}
+
+/// 書き出しで選べる文字コード
+const EXPORT_ENCODINGS: [CSVEncoding; 2] = [CSVEncoding::Utf8Bom, CSVEncoding::ShiftJis];
+
+/// 表の書き出し方法
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct ExportOptions {
+    format: ExportFormat,
+    encoding: CSVEncoding,
+    include_summary: bool, // 小計行を含める
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::default(),
+            encoding: CSVEncoding::Utf8Bom,
+            include_summary: false,
+        }
+    }
+}
+
+/// 取り込み済みの受取金データと直近の取り込み結果
+#[derive(PartialEq, Debug, Clone, Default)]
+struct ReceiptsState<T> {
+    receipts: Vec<T>,
+    statuses: Vec<ImportStatus>,
+    restored: bool,                // 保存済みデータの読み込みが完了したか
+    revision: u32,                 // receipts を変更するたびに増やす (保存のトリガー)
+    storage_error: Option<String>, // 保存済みデータを読み込めなかった理由 (この間は保存しない)
+}
+
+enum ReceiptsAction<T> {
+    Restore(Vec<T>),
+    /// 保存済みデータを読み込めなかった。上書きしないよう保存を止める
+    RestoreFailed(String),
+    /// 読み込めなかった保存済みデータを破棄し、保存を再開する
+    DiscardStored,
+    Import(Vec<(ImportStatus, Vec<T>)>),
+    /// 指定したファイルから取り込んだ行を削除する (None はファイル名のない以前の行)
+    RemoveFile(Option<String>),
+    Clear,
+    Synced(Vec<T>),
+}
+
+impl<T: ReceiptProps> Reducible for ReceiptsState<T> {
+    type Action = ReceiptsAction<T>;
+
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        let mut state = (*self).clone();
+        match action {
+            ReceiptsAction::RestoreFailed(err) => {
+                state.storage_error = Some(err);
+                return Rc::new(state);
+            }
+            ReceiptsAction::DiscardStored => {
+                state.storage_error = None;
+                state.restored = true;
+            }
+            ReceiptsAction::Restore(restored) => {
+                // 読み込み中に取り込まれた行は保存済みデータの後に追加する
+                let imported = std::mem::replace(&mut state.receipts, restored);
+                state.receipts.extend(imported);
+                state
+                    .receipts
+                    .sort_by_key(|r| r.get_date().unwrap_or_default());
+                state.restored = true;
+            }
+            ReceiptsAction::Import(results) => {
+                state.statuses.clear();
+                for (mut status, new_receipts) in results {
+                    csv_import::merge_receipts(&mut state.receipts, new_receipts, &mut status);
+                    state.statuses.push(status);
+                }
+            }
+            ReceiptsAction::RemoveFile(file_name) => {
+                state
+                    .receipts
+                    .retain(|r| r.get_source_file() != file_name.as_deref());
+                if let Some(file_name) = file_name {
+                    state.statuses.retain(|s| s.file_name != file_name);
+                }
+            }
+            ReceiptsAction::Clear => {
+                state.receipts.clear();
+                state.statuses.clear();
+            }
+            ReceiptsAction::Synced(receipts) => state.receipts = receipts,
+        }
+        T::recalculate(&mut state.receipts);
+        state.revision += 1;
+        Rc::new(state)
+    }
 }
 
 #[function_component]
 pub fn ReceiptTemplate<T: ReceiptProps>(props: &ReceiptTemplateProps) -> Html {
-    let receipts = use_state(Vec::<T>::new);
-    let csv_file = use_state(|| None::<File>);
-    let file_name = use_state(String::new);
+    let state = use_reducer(ReceiptsState::<T>::default);
+    let pending_files = use_state(|| props.files.clone());
+    let options = use_state(ImportOptions::default);
+    let export_options = use_state(ExportOptions::default);
     let query = use_state(|| None::<String>);
 
     {
-        let file_name = file_name.clone();
-        let receipts = receipts.clone();
+        let state = state.dispatcher();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                match receipt_storage::load::<T>().await {
+                    Ok(restored) => state.dispatch(ReceiptsAction::Restore(restored)),
+                    Err(err) => state.dispatch(ReceiptsAction::RestoreFailed(err.to_string())),
+                }
+            });
+        });
+    }
+
+    {
+        let receipts = state.receipts.clone();
+        use_effect_with((state.revision, state.restored), move |(_, restored)| {
+            if *restored {
+                spawn_local(async move {
+                    if let Err(err) = receipt_storage::save(&receipts).await {
+                        console::log!(err.to_string());
+                    }
+                });
+            }
+        });
+    }
 
-        use_effect_with((*csv_file).clone(), move |csv_file| {
-            handle_csv_file_change((*csv_file).clone(), file_name, receipts);
+    {
+        let state = state.dispatcher();
+        let options = *options;
+        let on_detect = props.on_detect.clone();
+        let setter = pending_files.setter();
+
+        use_effect_with((*pending_files).clone(), move |pending_files| {
+            if !pending_files.is_empty() {
+                handle_csv_files_change(pending_files.clone(), options, state, on_detect);
+                setter.set(Vec::new());
+            }
         });
     }
 
+    let on_remove = {
+        let state = state.dispatcher();
+        Callback::from(move |file_name: Option<String>| {
+            state.dispatch(ReceiptsAction::RemoveFile(file_name))
+        })
+    };
+    let on_clear = {
+        let state = state.dispatcher();
+        Callback::from(move |_: MouseEvent| state.dispatch(ReceiptsAction::Clear))
+    };
+    let on_discard = {
+        let state = state.dispatcher();
+        Callback::from(move |_: MouseEvent| state.dispatch(ReceiptsAction::DiscardStored))
+    };
+    let on_synced = {
+        let state = state.dispatcher();
+        Callback::from(move |receipts: Vec<T>| state.dispatch(ReceiptsAction::Synced(receipts)))
+    };
+
     html! {
         <>
-            { render_csvfile_input(csv_file.clone(), file_name.clone()) }
+            { render_csvfile_input(pending_files.clone(), options.clone()) }
+            { render_storage_error(&state.storage_error, on_discard) }
+            { render_stored_files(&state.receipts, on_remove, on_clear) }
+            if state.restored {
+                <SyncPanel<T> receipts={state.receipts.clone()} {on_synced} />
+            }
+            { render_import_statuses::<T>(&state.statuses) }
             <div class="mt-2">
-                <table class="table table-bordered">{ T::view_summary(&(*receipts)) }</table>
+                <table class="table table-bordered">{ T::view_summary(&state.receipts) }</table>
             </div>
             <div class="card shadow-sm">
                 <div class="card-header bg-info text-white">
@@ -42,16 +214,15 @@ pub fn ReceiptTemplate<T: ReceiptProps>(props: &ReceiptTemplateProps) -> Html {
                         <div class="col col-lg-1"><h5 class="mb-0">{ props.name.clone() }</h5></div>
                         if T::is_view_search() {
                             <div class="col col-md-auto"><h6 class="mb-0">{ "銘柄コード:" }</h6></div>
-                            <div class="col col-lg-2">{ render_search::<T>(&(*receipts), &query) }</div>
+                            <div class="col col-lg-2">{ render_search::<T>(&state.receipts, &query) }</div>
                         }
+                        <div class="col">{ render_export::<T>(&props.name, &state.receipts, &query, export_options.clone()) }</div>
                     </div>
                 </div>
                 <div class="table-responsive" style="max-height: 500px;">
                     <table class="table table-bordered">
                         { render_thead::<T>() }
-                        if csv_file.is_some() {
-                            { render_tbody::<T>(&(*receipts), &(*query)) }
-                        }
+                        { render_tbody::<T>(&state.receipts, &query) }
                     </table>
                 </div>
             </div>
@@ -59,16 +230,13 @@ pub fn ReceiptTemplate<T: ReceiptProps>(props: &ReceiptTemplateProps) -> Html {
     }
 }
 
-fn render_search<T: ReceiptProps>(
-    receipts: &Vec<T>,
-    query: &UseStateHandle<Option<String>>,
-) -> Html {
+fn render_search<T: ReceiptProps>(receipts: &[T], query: &UseStateHandle<Option<String>>) -> Html {
     html! {
             <select class="form-select form-select-sm" oninput={on_input_security_code_callback(query)}>
         <option selected=true />
         {
             receipts
-                .into_iter()
+                .iter()
                 .map(|receipt| { (receipt.get_security_code().to_string(), receipt) })
                 .sorted_by(|(a, _), (b, _)| a.cmp(b))
                 .chunk_by(|(key, _)| key.clone())
@@ -92,21 +260,166 @@ fn render_search<T: ReceiptProps>(
 }
 
 fn render_csvfile_input(
-    csv_file: UseStateHandle<Option<File>>,
-    file_name: UseStateHandle<String>,
+    pending_files: UseStateHandle<Vec<File>>,
+    options: UseStateHandle<ImportOptions>,
 ) -> Html {
-    let on_input = on_input_csvfile_callback(csv_file.clone());
+    let on_input = on_input_csvfile_callback(pending_files.clone());
+    let on_drop = on_drop_csvfile_callback(pending_files);
+
+    html! {
+    <div
+        class="border border-info rounded p-2"
+        style="border-style: dashed !important;"
+        ondragover={Callback::from(|e: DragEvent| e.prevent_default())}
+        ondrop={on_drop}
+    >
+        <div class="input-group">
+            <label class="input-group-btn" for="csv-file-input">
+                <span class="btn bg-info text-white">{ "CSVファイル選択" }</span>
+            </label>
+            <input id="csv-file-input" type="file" accept=".csv" multiple=true style="display:none" oninput={on_input} />
+            <input type="text" class="form-control form-control-sm" readonly=true placeholder="CSVファイルを選択、またはここにドラッグ&ドロップしてください。" />
+            <select class="form-select form-select-sm" style="max-width: 200px;" oninput={on_input_encoding_callback(options.clone())}>
+                <option value="" selected=true>{ "文字コード: 自動判定" }</option>
+                { for CSVEncoding::iter().enumerate().map(|(i, e)| html! {
+                    <option value={i.to_string()}>{ e.get_message() }</option>
+                })}
+            </select>
+        </div>
+        <div class="form-check mt-1">
+            <input id="skip-invalid-rows" class="form-check-input" type="checkbox" checked={options.skip_invalid_rows} oninput={on_input_skip_invalid_rows_callback(options)} />
+            <label class="form-check-label" for="skip-invalid-rows">{ "問題のある行をスキップして取り込む" }</label>
+        </div>
+    </div>
+    }
+}
+
+/// 保存済みデータを読み込めなかった理由と、破棄して保存を再開するボタンを表示する。
+fn render_storage_error(storage_error: &Option<String>, on_discard: Callback<MouseEvent>) -> Html {
+    let Some(err) = storage_error else {
+        return html! {};
+    };
     html! {
-    <div class="input-group">
-        <label class="input-group-btn" for="csv-file-input">
-            <span class="btn bg-info text-white">{ "CSVファイル選択" }</span>
-        </label>
-        <input id="csv-file-input" type="file" accept=".csv" style="display:none" oninput={on_input} />
-        <input type="text" class="form-control form-control-sm" readonly=true placeholder="CSVファイルを選択してください。" value={(*file_name).clone()} />
+    <div class="alert alert-danger d-flex align-items-center mt-2 py-2">
+        <small class="me-2">{ format!("{} (保存済みデータを上書きしないよう、変更は保存されません)", err) }</small>
+        <button class="btn btn-outline-danger btn-sm ms-auto text-nowrap" type="button" onclick={on_discard}>
+            { "保存済みデータを破棄" }
+        </button>
     </div>
     }
 }
 
+/// 保存済みのデータを取り込み元ファイルごとに表示する。
+fn render_stored_files<T: ReceiptProps>(
+    receipts: &[T],
+    on_remove: Callback<Option<String>>,
+    on_clear: Callback<MouseEvent>,
+) -> Html {
+    if receipts.is_empty() {
+        return html! {};
+    }
+
+    let files = receipts.iter().map(|r| r.get_source_file()).counts();
+
+    html! {
+    <div class="d-flex flex-wrap align-items-center mt-2">
+        <small class="me-2">{ "保存済み:" }</small>
+        { for files.into_iter().sorted().map(|(file_name, count)| {
+            let on_click = {
+                let file_name = file_name.map(str::to_string);
+                on_remove.reform(move |_: MouseEvent| file_name.clone())
+            };
+            html! {
+                <span class="badge bg-light text-dark border me-2">
+                    { format!("{} ({}行)", file_name.unwrap_or("ファイル名なし"), count) }
+                    <button type="button" class="btn-close ms-1" style="font-size: 0.6em;" aria-label="削除" onclick={on_click} />
+                </span>
+            }
+        })}
+        <button class="btn btn-outline-danger btn-sm ms-auto" type="button" onclick={on_clear}>
+            { "データを全て削除" }
+        </button>
+    </div>
+    }
+}
+
+fn render_import_statuses<T: ReceiptProps>(statuses: &[ImportStatus]) -> Html {
+    html! {
+        <ul class="list-group mt-2">
+            { for statuses.iter().enumerate().map(|(i, status)| render_import_status::<T>(i, status)) }
+        </ul>
+    }
+}
+
+fn render_import_status<T: ReceiptProps>(index: usize, status: &ImportStatus) -> Html {
+    let (class, message) = if let Some(error) = &status.error {
+        ("list-group-item-danger", error.clone())
+    } else if let Some(profile) = status.profile.filter(|_| status.is_other_type::<T>()) {
+        (
+            "list-group-item-secondary",
+            format!(
+                "{}のCSVのため取り込みませんでした",
+                profile.receipts_type.get_message().unwrap_or_default()
+            ),
+        )
+    } else {
+        (
+            if status.issues.is_empty() {
+                ""
+            } else {
+                "list-group-item-warning"
+            },
+            format!(
+                "取り込み {}行 / 重複 {}行 / スキップ {}行",
+                status.imported_rows, status.duplicate_rows, status.skipped_rows
+            ),
+        )
+    };
+    let collapse_id = format!("import-issues-{index}");
+
+    html! {
+    <li class={classes!("list-group-item", "py-1", class)}>
+        <span class="fw-bold me-2">{ &status.file_name }</span>
+        if let Some(profile) = status.profile {
+            <span class="badge bg-info me-1">{ profile.broker.get_message() }</span>
+        }
+        if let Some(detected) = status.encoding {
+            <span class="badge bg-secondary me-2">{ detected.get_message() }</span>
+        }
+        <small>{ message }</small>
+        if !status.issues.is_empty() {
+            <a class="alert-link ms-2" data-bs-toggle="collapse" href={format!("#{collapse_id}")} role="button" aria-expanded="false" aria-controls={collapse_id.clone()}>
+                <small>{ format!("警告 {}件", status.issues.len()) }</small>
+            </a>
+            <div class="collapse" id={collapse_id}>
+                <div class="table-responsive mt-2" style="max-height: 300px;">
+                    <table class="table table-sm table-bordered mb-0">
+                        <thead>
+                            <tr>
+                                <th scope="col">{ "行" }</th>
+                                <th scope="col">{ "列" }</th>
+                                <th scope="col">{ "値" }</th>
+                                <th scope="col">{ "理由" }</th>
+                            </tr>
+                        </thead>
+                        <tbody>
+                        { for status.issues.iter().map(|issue| html! {
+                            <tr>
+                                <td class="text-nowrap">{ issue.row }</td>
+                                <td class="text-nowrap">{ issue.column.clone().unwrap_or_default() }</td>
+                                <td>{ &issue.value }</td>
+                                <td>{ &issue.reason }</td>
+                            </tr>
+                        })}
+                        </tbody>
+                    </table>
+                </div>
+            </div>
+        }
+    </li>
+    }
+}
+
 fn render_thead<T: ReceiptProps>() -> Html {
     html! {
     <thead class="thead-light">
@@ -125,58 +438,242 @@ fn render_thead<T: ReceiptProps>() -> Html {
     }
 }
 
-fn render_tbody<T: ReceiptProps>(receipts: &Vec<T>, query: &Option<String>) -> Html {
+/// 小計行を含めずに受取金の表を描画する
+pub(crate) fn render_receipts_table<T: ReceiptProps>(receipts: &[T]) -> Html {
+    html! {
+        <table class="table table-hover table-sm">
+            { render_thead::<T>() }
+            <tbody>
+                { for receipts.iter().map(|receipt| receipt.view(None)) }
+            </tbody>
+        </table>
+    }
+}
+
+fn render_tbody<T: ReceiptProps>(receipts: &[T], query: &Option<String>) -> Html {
     html! {
         <tbody> {
-            receipts
-                .into_iter()
-                .filter_map(|receipt| {
-                    match &query {
-                        Some(q) => receipt.search(q).then(|| (q.to_string(), receipt)),
-                        None => receipt.get_date().map(|date| (date.to_string(), receipt)),
-                    }
-                })
-                .chunk_by(|(key, _)| key.clone())
-                .into_iter()
-                .flat_map(|(_, group)| {
-                    let receipts: Vec<&T> = group.map(|(_, receipt)| receipt).collect();
-                    let mut views: Vec<Html> = receipts.iter().map(|r| r.view(None)).collect();
-                    if let Some(summary) = T::new_summary(&receipts) {
-                        views.push(summary.view(Some(format!("table-success"))));
-                    }
-                    views
-                })
-                .collect::<Vec<VNode>>()
+            for visible_rows(receipts, query).iter().map(|(receipt, is_summary)| {
+                receipt.view(is_summary.then(|| "table-success".to_string()))
+            })
         }
         </tbody>
     }
 }
 
-fn handle_csv_file_change<T: ReceiptProps>(
-    csv_file: Option<File>,
-    file_name: UseStateHandle<String>,
-    receipts: UseStateHandle<Vec<T>>,
-) {
-    file_name.set("".to_string());
-
-    if let Some(csv_file) = csv_file {
-        spawn_local(async move {
-            file_name.set(csv_file.name());
-            if let Err(err) = csv_reader::read_file(&csv_file)
-                .await
-                .and_then(|content| process_csv_content(receipts, content))
+/// 表に表示する行を、日付 (または検索した銘柄) ごとの小計行を挟んで返す。
+///
+/// 小計行は `true` を付けて返す。
+fn visible_rows<T: ReceiptProps>(receipts: &[T], query: &Option<String>) -> Vec<(T, bool)> {
+    receipts
+        .iter()
+        .filter_map(|receipt| match &query {
+            Some(q) => receipt.search(q).then(|| (q.to_string(), receipt)),
+            None => receipt.get_date().map(|date| (date.to_string(), receipt)),
+        })
+        .chunk_by(|(key, _)| key.clone())
+        .into_iter()
+        .flat_map(|(_, group)| {
+            let receipts: Vec<&T> = group.map(|(_, receipt)| receipt).collect();
+            let mut rows: Vec<(T, bool)> = receipts.iter().map(|r| ((*r).clone(), false)).collect();
+            if let Some(summary) = T::new_summary(&receipts) {
+                rows.push((summary, true));
+            }
+            rows
+        })
+        .collect()
+}
+
+fn render_export<T: ReceiptProps>(
+    name: &str,
+    receipts: &[T],
+    query: &Option<String>,
+    export_options: UseStateHandle<ExportOptions>,
+) -> Html {
+    let on_click = {
+        let name = name.to_string();
+        let receipts = receipts.to_vec();
+        let query = query.clone();
+        let options = *export_options;
+        Callback::from(move |_: MouseEvent| {
+            if let Err(err) = export_rows(&name, &receipts, &query, options) {
+                console::log!(err);
+            }
+        })
+    };
+    let on_input_format = {
+        let export_options = export_options.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            if let Some(format) = input
+                .value()
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| ExportFormat::iter().nth(i))
             {
-                console::log!(err.to_string());
-            };
-        });
+                export_options.set(ExportOptions {
+                    format,
+                    ..*export_options
+                });
+            }
+        })
+    };
+    let on_input_encoding = {
+        let export_options = export_options.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            if let Some(&encoding) = input
+                .value()
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| EXPORT_ENCODINGS.get(i))
+            {
+                export_options.set(ExportOptions {
+                    encoding,
+                    ..*export_options
+                });
+            }
+        })
+    };
+    let on_input_include_summary = {
+        let export_options = export_options.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            export_options.set(ExportOptions {
+                include_summary: input.checked(),
+                ..*export_options
+            });
+        })
+    };
+
+    html! {
+    <div class="d-flex align-items-center justify-content-end gap-2">
+        <select class="form-select form-select-sm w-auto" oninput={on_input_format}>
+            { for ExportFormat::iter().enumerate().map(|(i, f)| html! {
+                <option value={i.to_string()} selected={f == export_options.format}>{ f.get_message() }</option>
+            })}
+        </select>
+        <select class="form-select form-select-sm w-auto" oninput={on_input_encoding}>
+            { for EXPORT_ENCODINGS.iter().enumerate().map(|(i, e)| html! {
+                <option value={i.to_string()} selected={*e == export_options.encoding}>{ e.get_message() }</option>
+            })}
+        </select>
+        <div class="form-check mb-0 text-nowrap">
+            <input id="export-include-summary" class="form-check-input" type="checkbox" checked={export_options.include_summary} oninput={on_input_include_summary} />
+            <label class="form-check-label" for="export-include-summary">{ "小計行を含める" }</label>
+        </div>
+        <button class="btn btn-light btn-sm text-nowrap" type="button" onclick={on_click} disabled={receipts.is_empty()}>
+            { "エクスポート" }
+        </button>
+    </div>
     }
 }
 
-fn on_input_csvfile_callback(csv_file: UseStateHandle<Option<File>>) -> Callback<InputEvent> {
+/// 表示中の行を `HEADERS` の見出しで書き出し、ダウンロードさせる。
+fn export_rows<T: ReceiptProps>(
+    name: &str,
+    receipts: &[T],
+    query: &Option<String>,
+    options: ExportOptions,
+) -> Result<(), String> {
+    let keys: Vec<&str> = T::new().get_all_fields().iter().map(|(k, _)| *k).collect();
+    let headers: Vec<&str> = keys
+        .iter()
+        .map(|key| *HEADERS.get(key).unwrap_or(key))
+        .collect();
+    let rows: Vec<Vec<String>> = visible_rows(receipts, query)
+        .into_iter()
+        .filter(|(_, is_summary)| options.include_summary || !is_summary)
+        .map(|(receipt, _)| {
+            receipt
+                .get_all_fields()
+                .into_iter()
+                .map(|(_, value)| value.unwrap_or_default())
+                .collect()
+        })
+        .collect();
+
+    let bytes = csv_writer::write_delimited(&headers, &rows, options.format, options.encoding)
+        .map_err(|e| e.to_string())?;
+    let file_name = format!("{}.{}", name, options.format.extension());
+    csv_writer::download(&bytes, &file_name, options.format).map_err(|e| format!("{:?}", e))
+}
+
+fn handle_csv_files_change<T: ReceiptProps>(
+    csv_files: Vec<File>,
+    options: ImportOptions,
+    state: UseReducerDispatcher<ReceiptsState<T>>,
+    on_detect: Callback<(ReceiptsType, Vec<File>)>,
+) {
+    spawn_local(async move {
+        let results = csv_import::import_files::<T>(&csv_files, options).await;
+
+        // 全てのファイルが別の帳票だった場合は、最初のファイルのタブに切り替えて全てのファイルを読み込み直す。
+        // さらに別の帳票のファイルは、切り替え先のタブで取り込まなかったことを表示する。
+        if let Some(detected) = detected_type(&results) {
+            on_detect.emit((detected, csv_files));
+            return;
+        }
+
+        state.dispatch(ReceiptsAction::Import(results));
+    });
+}
+
+/// 全てのファイルが `T` と別の帳票と判定された場合に、切り替え先の帳票 (最初のファイルの帳票) を返す。
+fn detected_type<T: ReceiptProps>(results: &[(ImportStatus, Vec<T>)]) -> Option<ReceiptsType> {
+    if results.is_empty() || !results.iter().all(|(s, _)| s.is_other_type::<T>()) {
+        return None;
+    }
+    results[0].0.profile.map(|p| p.receipts_type)
+}
+
+fn append_files(pending_files: &UseStateHandle<Vec<File>>, files: Option<FileList>) {
+    let Some(files) = files else {
+        return;
+    };
+    pending_files.set((0..files.length()).filter_map(|i| files.get(i)).collect());
+}
+
+fn on_input_csvfile_callback(pending_files: UseStateHandle<Vec<File>>) -> Callback<InputEvent> {
+    Callback::from(move |e: InputEvent| {
+        let input: HtmlInputElement = e.target_unchecked_into();
+        append_files(&pending_files, input.files());
+        // 同じファイルを再度選択しても input イベントが発生するようにする
+        input.set_value("");
+    })
+}
+
+fn on_drop_csvfile_callback(pending_files: UseStateHandle<Vec<File>>) -> Callback<DragEvent> {
+    Callback::from(move |e: DragEvent| {
+        e.prevent_default();
+        append_files(&pending_files, e.data_transfer().and_then(|dt| dt.files()));
+    })
+}
+
+fn on_input_encoding_callback(options: UseStateHandle<ImportOptions>) -> Callback<InputEvent> {
+    Callback::from(move |e: InputEvent| {
+        let input: HtmlInputElement = e.target_unchecked_into();
+        let value = input
+            .value()
+            .parse::<usize>()
+            .ok()
+            .and_then(|i| CSVEncoding::iter().nth(i));
+        options.set(ImportOptions {
+            encoding: value,
+            ..*options
+        });
+    })
+}
+
+fn on_input_skip_invalid_rows_callback(
+    options: UseStateHandle<ImportOptions>,
+) -> Callback<InputEvent> {
     Callback::from(move |e: InputEvent| {
         let input: HtmlInputElement = e.target_unchecked_into();
-        let value = input.files().and_then(|files| files.get(0));
-        csv_file.set(value.clone());
+        options.set(ImportOptions {
+            skip_invalid_rows: input.checked(),
+            ..*options
+        });
     })
 }
 
@@ -191,31 +688,42 @@ fn on_input_security_code_callback(
     })
 }
 
-fn process_csv_content<T: ReceiptProps>(
-    receipts: UseStateHandle<Vec<T>>,
-    content: Vec<u8>,
-) -> Result<(), csv_reader::CSVError> {
-    let records = csv_reader::read_csv(content)?;
-    let new_receipts: Vec<_> = records
-        .into_iter()
-        .map(|record| T::new_from_string_record(record))
-        .sorted_by(|a, b| {
-            a.get_date()
-                .unwrap_or_default()
-                .cmp(&b.get_date().unwrap_or_default())
-        })
-        .collect();
-    receipts.set(new_receipts);
-
-    Ok(())
-}
-
-pub trait ReceiptProps: Clone + Sized + PartialEq + Default + 'static {
+pub trait ReceiptProps:
+    Clone + Sized + PartialEq + Default + Serialize + DeserializeOwned + 'static
+{
     fn new() -> Self;
     fn new_summary(_receipts: &[&Self]) -> Option<Self> {
         None
     }
-    fn new_from_string_record(record: StringRecord) -> Self;
+    fn new_from_string_record(record: &StringRecord, columns: &CSVColumns) -> Self;
+
+    /// 取り込むと誤った値になるセルの `(フィールド名, 理由)` (未対応の通貨など)。
+    /// 該当する行は「問題のある行を取り込まない」の設定に関わらず取り込まない。
+    fn rejected_fields(
+        _record: &StringRecord,
+        _columns: &CSVColumns,
+    ) -> Vec<(&'static str, &'static str)> {
+        Vec::new()
+    }
+
+    /// 複数ファイル間の重複判定に使う行のキー (日付・銘柄・口座・金額)
+    fn row_key(&self) -> String;
+
+    /// 取り込み元のファイル名を設定する
+    fn set_source_file(&mut self, file_name: Option<&str>);
+
+    fn get_source_file(&self) -> Option<&str>;
+
+    /// CSV の帳票の種類 (証券会社のプロファイル判定に使う)
+    fn receipts_type() -> ReceiptsType;
+
+    /// 行の追加・削除の後に、行をまたいで求める項目 (年初来の源泉徴収税額など) を計算し直す
+    fn recalculate(_receipts: &mut [Self]) {}
+
+    /// 行をまたいで求める項目を除いた値 (同期の競合判定に使う)
+    fn without_derived(&self) -> Self {
+        self.clone()
+    }
 
     fn get_all_fields(&self) -> Vec<(&'static str, Option<String>)>;
     fn get_date(&self) -> Option<NaiveDate>;
@@ -238,20 +746,31 @@ pub trait ReceiptProps: Clone + Sized + PartialEq + Default + 'static {
 
     fn view_summary(receipts: &[Self]) -> Html;
 
+    /// 表に表示する値の書式 (`get_all_fields` の値を受け取る)
+    fn format_field(&self, key: &str, value: &str) -> String {
+        formater::format_value(key, value)
+    }
+
     fn view(&self, tr_class: Option<String>) -> Html {
         html! {
             <tr class={tr_class}>
                 { for self.get_all_fields().iter().map(|(key, value)| {
                     let value = value.as_deref().unwrap_or("");
-                    let value = formater::format_value(key, value);
+                    let value = self.format_field(key, value);
                     let style = "overflow-wrap: break-word; white-space: normal;";
                     let mut class = "text-nowrap".to_string();
                     if value.starts_with("¥ -") {
                         class = format!("{} text-danger", class);
                     }
+                    // 銘柄コードは銘柄の詳細ページへのリンクにする
+                    let content = if *key == "security_code" && !value.is_empty() {
+                        html! { <Link<Route> to={Route::Stock { code: value.clone() }}>{ value }</Link<Route>> }
+                    } else {
+                        html! { value }
+                    };
                     html! {
                         <td class={class} style={style}>
-                            {value}
+                            { content }
                         </td>
                     }
                 })}
@@ -275,3 +794,77 @@ pub trait ReceiptProps: Clone + Sized + PartialEq + Default + 'static {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pages::receipts::{broker_profile, dividend_list::DividendList};
+
+    fn receipt(source_file: Option<&str>, code: &str) -> DividendList {
+        let mut receipt = DividendList {
+            security_code: Some(code.into()),
+            ..Default::default()
+        };
+        receipt.set_source_file(source_file);
+        receipt
+    }
+
+    #[test]
+    fn test_detected_type_is_first_file_type() {
+        let status = |receipts_type| ImportStatus {
+            profile: broker_profile::PROFILES
+                .iter()
+                .find(|p| p.receipts_type == receipts_type),
+            ..Default::default()
+        };
+        let results = vec![
+            (
+                status(ReceiptsType::DomesticStock),
+                Vec::<DividendList>::new(),
+            ),
+            (status(ReceiptsType::MutualFund), Vec::new()),
+        ];
+        assert_eq!(detected_type(&results), Some(ReceiptsType::DomesticStock));
+
+        let results = vec![
+            (
+                status(ReceiptsType::DomesticStock),
+                Vec::<DividendList>::new(),
+            ),
+            (status(ReceiptsType::Dividend), Vec::new()),
+        ];
+        assert_eq!(detected_type(&results), None);
+        assert_eq!(detected_type::<DividendList>(&[]), None);
+    }
+
+    #[test]
+    fn test_restore_failure_blocks_saving() {
+        let state = Rc::new(ReceiptsState::<DividendList>::default());
+        let state = state.reduce(ReceiptsAction::RestoreFailed("error".into()));
+        assert_eq!(state.revision, 0);
+        assert!(!state.restored);
+
+        // 読み込みに失敗したまま変更しても保存しない
+        let state = state.reduce(ReceiptsAction::Clear);
+        assert!(!state.restored);
+        assert_eq!(state.storage_error.as_deref(), Some("error"));
+
+        let state = state.reduce(ReceiptsAction::DiscardStored);
+        assert!(state.restored);
+        assert_eq!(state.storage_error, None);
+    }
+
+    #[test]
+    fn test_remove_file_without_name() {
+        let state = Rc::new(ReceiptsState {
+            receipts: vec![receipt(None, "7203"), receipt(Some("a.csv"), "8306")],
+            ..Default::default()
+        });
+
+        let state = state.reduce(ReceiptsAction::RemoveFile(None));
+        assert_eq!(state.receipts, vec![receipt(Some("a.csv"), "8306")]);
+
+        let state = state.reduce(ReceiptsAction::RemoveFile(Some("a.csv".into())));
+        assert!(state.receipts.is_empty());
+    }
+}
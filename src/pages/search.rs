@@ -1,88 +1,257 @@
+use chrono::{DateTime, Utc};
 use gloo::console;
 use wasm_bindgen_futures::spawn_local;
 use web_sys::HtmlInputElement;
 use yew::prelude::*;
+use yew_router::prelude::*;
 
-use super::layout::Layout;
-use crate::{data::stock::StockData, services::shoken_web_api, setting::*};
+use super::{
+    layout::Layout,
+    stock::{render_stale_since, render_stock_info},
+    watchlist::WatchlistToggle,
+};
+use crate::{
+    app::Route,
+    data::{link_provider::LinkProviders, stock::StockData},
+    services::shoken_web_api::{self, ApiError, LatestRequest},
+};
+
+/// 候補として表示する最大件数
+const MAX_SUGGESTIONS: usize = 10;
+
+/// 入力が止まってから検索するまでの時間 [ms]
+const SEARCH_DEBOUNCE_MS: u32 = 300;
+
+/// 検索結果の取得状況
+#[derive(Clone, PartialEq, Debug, Default)]
+enum FetchState {
+    #[default]
+    Idle,
+    Loading,
+    Failed(ApiError),
+}
 
 #[function_component]
 pub fn Search() -> Html {
     let stock = use_state(StockData::default);
+    let stale_since = use_state(|| None::<DateTime<Utc>>); // キャッシュを表示しているときの取得日時
     let code_or_name = use_state(String::new);
+    let suggestions = use_state(Vec::<StockData>::new);
+    let active = use_state(|| None::<usize>);
+    let fetch_state = use_state(FetchState::default);
+    let search_request = use_state(LatestRequest::default);
+    let detail_request = use_state(LatestRequest::default);
+    let link_providers =
+        use_context::<UseReducerHandle<LinkProviders>>().expect("LinkProviders context");
+
+    let on_select = {
+        let stock = stock.clone();
+        let stale_since = stale_since.clone();
+        let code_or_name = code_or_name.clone();
+        let suggestions = suggestions.clone();
+        let active = active.clone();
+        let fetch_state = fetch_state.clone();
+        let search_request = (*search_request).clone();
+        let detail_request = (*detail_request).clone();
+        Callback::from(move |selected: StockData| {
+            search_request.begin();
+            code_or_name.set(format!("{} {}", selected.code, selected.name));
+            suggestions.set(Vec::new());
+            active.set(None);
+            stock.set(selected.clone());
+            fetch_state.set(FetchState::Loading);
+
+            // 候補の情報は検索結果のものなので、選んだ銘柄の詳細を取得し直す
+            let stock = stock.clone();
+            let stale_since = stale_since.clone();
+            let fetch_state = fetch_state.clone();
+            let detail_request = detail_request.clone();
+            spawn_local(async move {
+                let request = |signal: Option<_>| async move {
+                    shoken_web_api::fetch_stock_data(&selected.code, signal.as_ref()).await
+                };
+                match detail_request.debounce(0, request).await {
+                    Ok(fetched) => {
+                        stale_since.set(fetched.stale.then_some(fetched.cached_at));
+                        stock.set(fetched.value);
+                        fetch_state.set(FetchState::Idle);
+                    }
+                    Err(ApiError::Aborted) => {}
+                    Err(err) => fetch_state.set(FetchState::Failed(err)),
+                }
+            });
+        })
+    };
 
     let on_input = {
         let stock = stock.clone();
+        let stale_since = stale_since.clone();
         let code_or_name = code_or_name.clone();
+        let suggestions = suggestions.clone();
+        let active = active.clone();
+        let fetch_state = fetch_state.clone();
+        let search_request = (*search_request).clone();
+        let detail_request = (*detail_request).clone();
 
         Callback::from(move |e: InputEvent| {
             let input: HtmlInputElement = e.target_unchecked_into();
             let value = input.value();
             code_or_name.set(value.clone());
             stock.set(StockData::default());
+            stale_since.set(None);
+            active.set(None);
 
             console::log!(format!("Input value: {value}"));
 
-            let stock = stock.clone();
+            detail_request.begin();
+            if value.trim().is_empty() {
+                search_request.begin();
+                suggestions.set(Vec::new());
+                fetch_state.set(FetchState::Idle);
+                return;
+            }
+            fetch_state.set(FetchState::Loading);
+
+            let suggestions = suggestions.clone();
+            let fetch_state = fetch_state.clone();
+            let search_request = search_request.clone();
             spawn_local(async move {
-                match shoken_web_api::fetch_stock_data(&value).await {
-                    Ok(new_stock) => stock.set(new_stock),
-                    Err(err) => console::log!(&err.to_string()),
+                let request = |signal: Option<_>| async move {
+                    shoken_web_api::search_stocks(&value, signal.as_ref()).await
+                };
+                match search_request.debounce(SEARCH_DEBOUNCE_MS, request).await {
+                    Ok(mut candidates) => {
+                        candidates.truncate(MAX_SUGGESTIONS);
+                        suggestions.set(candidates);
+                        fetch_state.set(FetchState::Idle);
+                    }
+                    // 次の入力で取り消された
+                    Err(ApiError::Aborted) => {}
+                    Err(err) => fetch_state.set(FetchState::Failed(err)),
                 }
             });
         })
     };
 
+    let on_keydown = {
+        let suggestions = suggestions.clone();
+        let active = active.clone();
+        let on_select = on_select.clone();
+        Callback::from(move |e: KeyboardEvent| {
+            let len = suggestions.len();
+            if len == 0 {
+                return;
+            }
+            match e.key().as_str() {
+                "ArrowDown" => {
+                    e.prevent_default();
+                    active.set(Some(active.map_or(0, |i| (i + 1) % len)));
+                }
+                "ArrowUp" => {
+                    e.prevent_default();
+                    active.set(Some(active.map_or(len - 1, |i| (i + len - 1) % len)));
+                }
+                "Enter" => {
+                    e.prevent_default();
+                    if let Some(selected) = suggestions.get(active.unwrap_or(0)) {
+                        on_select.emit(selected.clone());
+                    }
+                }
+                "Escape" => {
+                    suggestions.set(Vec::new());
+                    active.set(None);
+                }
+                _ => {}
+            }
+        })
+    };
+
     html! {
         <Layout>
-            <div class="mb-3">
+            <div class="mb-3 position-relative">
                 <input
                     type="text"
                     class="form-control form-control-lg shadow-sm"
                     id="stockCode"
                     placeholder="銘柄名・銘柄コードを入力"
+                    autocomplete="off"
+                    role="combobox"
+                    aria-expanded={(!suggestions.is_empty()).to_string()}
+                    aria-controls="stockSuggestions"
                     value={(*code_or_name).clone()}
                     oninput={on_input}
+                    onkeydown={on_keydown}
                 />
+                { render_suggestions(&suggestions, *active, &on_select) }
             </div>
             <div class="card shadow-sm">
                 <div class="card-header bg-info text-white">
                     <h5 class="mb-0">{ "検索結果" }</h5>
                 </div>
                 <div class="card-body">
-                    <table class="table">
-                        <tbody>
-                            { render_table_row("銘柄名", &stock.name) }
-                            { render_table_row("銘柄コード", &stock.code) }
-                            { render_table_row("マーケットカテゴリ", &stock.market_category) }
-                            { render_table_row("33業種区分", &stock.industry_category_33.clone().unwrap_or_default()) }
-                            { render_table_row("17業種区分", &stock.industry_category_17.clone().unwrap_or_default()) }
-                            { render_table_row("規模区分", &stock.size_category.clone().unwrap_or_default()) }
-                        </tbody>
-                    </table>
-                    <div class="d-flex flex-wrap">
-                        { for STOCK_INFO_LINKS.iter().enumerate().map(|(i, (text, href))| {
-                            html! {
-                                <>
-                                    <a href={href.replace("{}", &stock.code)} target="_blank" class="fw-bold">
-                                        { text }
-                                    </a>
-                                    { if i < STOCK_INFO_LINKS.len() - 1 { html! { <span class="mx-2">{"|"} </span> } } else { html! {} } }
-                                </>
-                            }
-                        })}
-                    </div>
+                    { render_fetch_state(&fetch_state) }
+                    { render_stale_since(*stale_since) }
+                    { render_stock_info(&stock, &link_providers) }
+                    if !stock.code.is_empty() {
+                        <div class="d-flex flex-wrap align-items-center gap-3 mt-3">
+                            <Link<Route> to={Route::Stock { code: stock.code.clone() }}>{ "詳細と取り込み済みの受取金" }</Link<Route>>
+                            <WatchlistToggle code={stock.code.clone()} />
+                        </div>
+                    }
                 </div>
             </div>
         </Layout>
     }
 }
 
-fn render_table_row(label: &str, value: &str) -> Html {
+fn render_suggestions(
+    suggestions: &[StockData],
+    active: Option<usize>,
+    on_select: &Callback<StockData>,
+) -> Html {
+    if suggestions.is_empty() {
+        return html! {};
+    }
     html! {
-        <tr>
-            <th scope="row" width="125px">{ label }</th>
-            <td>{ value }</td>
-        </tr>
+        <div id="stockSuggestions" class="list-group position-absolute w-100 shadow" style="z-index: 1000;" role="listbox">
+            { for suggestions.iter().enumerate().map(|(i, candidate)| {
+                let class = if Some(i) == active {
+                    "list-group-item list-group-item-action active"
+                } else {
+                    "list-group-item list-group-item-action"
+                };
+                let selected = candidate.clone();
+                html! {
+                    <button type="button" class={class} role="option" aria-selected={(Some(i) == active).to_string()}
+                        onclick={on_select.reform(move |_| selected.clone())}>
+                        <span class="fw-bold me-2">{ &candidate.code }</span>
+                        { &candidate.name }
+                        <small class="ms-2 text-body-secondary">{ &candidate.market_category }</small>
+                    </button>
+                }
+            })}
+        </div>
+    }
+}
+
+fn render_fetch_state(fetch_state: &FetchState) -> Html {
+    match fetch_state {
+        FetchState::Idle => html! {},
+        FetchState::Loading => html! {
+            <div class="d-flex align-items-center text-info mb-3">
+                <div class="spinner-border spinner-border-sm me-2" role="status" aria-hidden="true"></div>
+                { "読み込み中..." }
+            </div>
+        },
+        FetchState::Failed(ApiError::Aborted) => html! {},
+        FetchState::Failed(err @ ApiError::StockNotFound(_)) => html! {
+            <div class="alert alert-warning mb-3" role="alert">{ err.to_string() }</div>
+        },
+        FetchState::Failed(err) => html! {
+            <div class="alert alert-danger mb-3" role="alert">
+                <i class="fas fa-exclamation-triangle me-2"/>
+                { err.to_string() }
+            </div>
+        },
     }
 }
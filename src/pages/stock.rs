@@ -0,0 +1,220 @@
+use chrono::{DateTime, Local, Utc};
+use gloo::console;
+use strum::EnumMessage;
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use super::{
+    layout::Layout,
+    receipts::{
+        dividend_list::DividendList,
+        domestic_stock::DomesticStock,
+        receipt_storage,
+        receipt_template::{self, ReceiptProps},
+    },
+};
+use crate::{
+    data::{link_provider::LinkProviders, stock::StockData, watchlist::Watchlist},
+    services::shoken_web_api::{self, ApiError, LatestRequest},
+};
+
+mod price_chart;
+
+use price_chart::{PriceChart, TradeMarker};
+
+#[derive(Properties, PartialEq)]
+pub struct StockProps {
+    pub code: String,
+}
+
+/// 銘柄の詳細と、その銘柄の取り込み済みの受取金
+///
+/// 投資信託は銘柄コードを持たないため表示しない。
+#[function_component]
+pub fn Stock(props: &StockProps) -> Html {
+    let stock = use_state(|| None::<StockData>);
+    let stale_since = use_state(|| None::<DateTime<Utc>>);
+    let error = use_state(|| None::<ApiError>);
+    let watched = use_context::<UseReducerHandle<Watchlist>>()
+        .is_some_and(|watchlist| watchlist.contains(&props.code));
+    let link_providers =
+        use_context::<UseReducerHandle<LinkProviders>>().expect("LinkProviders context");
+    let dividends = use_state(Vec::<DividendList>::new);
+    let domestic_stocks = use_state(Vec::<DomesticStock>::new);
+    // 銘柄コードが変わったときに、前の銘柄の応答で上書きしないようにする
+    let stock_request = use_state(LatestRequest::default);
+    let receipts_request = use_state(LatestRequest::default);
+
+    {
+        let stock = stock.clone();
+        let stale_since = stale_since.clone();
+        let error = error.clone();
+        let stock_request = stock_request.clone();
+        use_effect_with(props.code.clone(), move |code| {
+            let code = code.clone();
+            stock.set(None);
+            stale_since.set(None);
+            error.set(None);
+            spawn_local(async move {
+                let request = |signal: Option<_>| async move {
+                    shoken_web_api::fetch_stock_data(&code, signal.as_ref()).await
+                };
+                match stock_request.debounce(0, request).await {
+                    Ok(fetched) => {
+                        stale_since.set(fetched.stale.then_some(fetched.cached_at));
+                        stock.set(Some(fetched.value));
+                    }
+                    Err(ApiError::Aborted) => {}
+                    Err(err) => error.set(Some(err)),
+                }
+            });
+        });
+    }
+
+    {
+        let dividends = dividends.clone();
+        let domestic_stocks = domestic_stocks.clone();
+        let receipts_request = receipts_request.clone();
+        use_effect_with(props.code.clone(), move |code| {
+            let code = code.clone();
+            let seq = receipts_request.begin();
+            spawn_local(async move {
+                let loaded = (load_receipts(&code).await, load_receipts(&code).await);
+                if receipts_request.is_latest(seq) {
+                    dividends.set(loaded.0);
+                    domestic_stocks.set(loaded.1);
+                }
+            });
+        });
+    }
+
+    let trades: Vec<TradeMarker> = domestic_stocks
+        .iter()
+        .filter_map(|receipt| {
+            Some(TradeMarker {
+                date: receipt.trade_date?,
+                label: format!("売却 {}株", receipt.shares.unwrap_or_default()),
+            })
+        })
+        .collect();
+
+    let title = stock
+        .as_ref()
+        .map_or(props.code.clone(), |s| format!("{} {}", s.code, s.name));
+    html! {
+        <Layout>
+            <div class="card shadow-sm mb-4">
+                <div class="card-header bg-info text-white">
+                    <h5 class="mb-0">
+                        { title }
+                        if watched {
+                            <span class="badge text-bg-light ms-2">{ "ウォッチ中" }</span>
+                        }
+                    </h5>
+                </div>
+                <div class="card-body">
+                    {
+                        match (&*stock, &*error) {
+                            (Some(stock), _) => html! {
+                                <>
+                                    { render_stale_since(*stale_since) }
+                                    { render_stock_info(stock, &link_providers) }
+                                </>
+                            },
+                            (None, Some(err)) => html! {
+                                <div class="alert alert-warning mb-0" role="alert">{ err.to_string() }</div>
+                            },
+                            (None, None) => html! {
+                                <div class="spinner-border spinner-border-sm text-info" role="status"></div>
+                            },
+                        }
+                    }
+                </div>
+            </div>
+            <PriceChart code={props.code.clone()} {trades} />
+            { render_receipts(&dividends) }
+            { render_receipts(&domestic_stocks) }
+        </Layout>
+    }
+}
+
+/// 保存済みの受取金から銘柄コードが一致するものを読み込む
+async fn load_receipts<T: ReceiptProps>(code: &str) -> Vec<T> {
+    match receipt_storage::load::<T>().await {
+        Ok(receipts) => receipts
+            .into_iter()
+            .filter(|r| r.get_security_code() == code)
+            .collect(),
+        Err(err) => {
+            console::log!(err.to_string());
+            Vec::new()
+        }
+    }
+}
+
+fn render_receipts<T: ReceiptProps>(receipts: &[T]) -> Html {
+    if receipts.is_empty() {
+        return html! {};
+    }
+    html! {
+        <div class="card shadow-sm mb-4">
+            <div class="card-header">
+                <h5 class="mb-0">{ T::receipts_type().get_message() }</h5>
+            </div>
+            <div class="card-body table-responsive">
+                { receipt_template::render_receipts_table(receipts) }
+            </div>
+        </div>
+    }
+}
+
+pub fn render_stale_since(stale_since: Option<DateTime<Utc>>) -> Html {
+    let Some(cached_at) = stale_since else {
+        return html! {};
+    };
+    html! {
+        <div class="alert alert-secondary py-2 mb-3" role="status">
+            { format!("通信できないため、{} 時点のデータを表示しています", cached_at.with_timezone(&Local).format("%Y/%m/%d %H:%M")) }
+        </div>
+    }
+}
+
+/// 銘柄情報の表と、各種証券情報サイトへのリンク
+pub fn render_stock_info(stock: &StockData, link_providers: &LinkProviders) -> Html {
+    let links: Vec<(&str, String)> = link_providers.links_for(stock).collect();
+    html! {
+        <>
+            <table class="table">
+                <tbody>
+                    { render_table_row("銘柄名", &stock.name) }
+                    { render_table_row("銘柄コード", &stock.code) }
+                    { render_table_row("マーケットカテゴリ", &stock.market_category) }
+                    { render_table_row("33業種区分", &stock.industry_category_33.clone().unwrap_or_default()) }
+                    { render_table_row("17業種区分", &stock.industry_category_17.clone().unwrap_or_default()) }
+                    { render_table_row("規模区分", &stock.size_category.clone().unwrap_or_default()) }
+                </tbody>
+            </table>
+            <div class="d-flex flex-wrap">
+                { for links.iter().enumerate().map(|(i, (text, href))| {
+                    html! {
+                        <>
+                            <a href={href.clone()} target="_blank" class="fw-bold">
+                                { text }
+                            </a>
+                            { if i < links.len() - 1 { html! { <span class="mx-2">{"|"} </span> } } else { html! {} } }
+                        </>
+                    }
+                })}
+            </div>
+        </>
+    }
+}
+
+fn render_table_row(label: &str, value: &str) -> Html {
+    html! {
+        <tr>
+            <th scope="row" width="125px">{ label }</th>
+            <td>{ value }</td>
+        </tr>
+    }
+}
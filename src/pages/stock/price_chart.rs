@@ -0,0 +1,388 @@
+use chrono::{Local, Months, NaiveDate};
+use strum::{EnumIter, EnumMessage, IntoEnumIterator};
+use wasm_bindgen_futures::spawn_local;
+use yew::prelude::*;
+
+use crate::{
+    data::stock::{DailyPrice, DividendHistory},
+    services::{
+        formater::StrFormater,
+        shoken_web_api::{self, ApiError, LatestRequest},
+    },
+};
+
+// SVG の大きさと余白
+const WIDTH: f64 = 800.0;
+const HEIGHT: f64 = 320.0;
+const MARGIN_LEFT: f64 = 70.0;
+const MARGIN_RIGHT: f64 = 10.0;
+const MARGIN_TOP: f64 = 10.0;
+const MARGIN_BOTTOM: f64 = 30.0;
+const Y_TICKS: usize = 5;
+
+const UP_COLOR: &str = "#dc3545"; // 陽線
+const DOWN_COLOR: &str = "#0d6efd"; // 陰線
+const LINE_COLOR: &str = "#0dcaf0";
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, EnumMessage, EnumIter)]
+pub enum ChartRange {
+    #[strum(message = "1M")]
+    OneMonth,
+
+    #[strum(message = "6M")]
+    SixMonths,
+
+    #[strum(message = "1Y")]
+    OneYear,
+
+    #[strum(message = "5Y")]
+    FiveYears,
+}
+
+impl ChartRange {
+    fn months(&self) -> u32 {
+        match self {
+            ChartRange::OneMonth => 1,
+            ChartRange::SixMonths => 6,
+            ChartRange::OneYear => 12,
+            ChartRange::FiveYears => 60,
+        }
+    }
+
+    /// `today` までの期間の開始日
+    pub fn start(&self, today: NaiveDate) -> NaiveDate {
+        today
+            .checked_sub_months(Months::new(self.months()))
+            .unwrap_or(NaiveDate::MIN)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, EnumMessage, EnumIter)]
+pub enum ChartKind {
+    #[strum(message = "ライン")]
+    Line,
+
+    #[strum(message = "ローソク足")]
+    Candlestick,
+}
+
+/// チャートに表示する自分の取引
+#[derive(Clone, PartialEq, Debug)]
+pub struct TradeMarker {
+    pub date: NaiveDate,
+    pub label: String,
+}
+
+#[derive(Properties, PartialEq)]
+pub struct PriceChartProps {
+    pub code: String,
+    #[prop_or_default]
+    pub trades: Vec<TradeMarker>,
+}
+
+/// 株価チャート
+///
+/// 最長の期間 (5Y) の日足をまとめて取得し、選んだ期間で絞り込んで描画する。
+#[function_component]
+pub fn PriceChart(props: &PriceChartProps) -> Html {
+    let prices = use_state(Vec::<DailyPrice>::new);
+    let dividends = use_state(Vec::<DividendHistory>::new);
+    let error = use_state(|| None::<String>);
+    let range = use_state(|| ChartRange::OneYear);
+    let kind = use_state(|| ChartKind::Line);
+    // 銘柄コードが変わったときは前の銘柄のリクエストを中止し、その応答を捨てる
+    let latest_request = use_state(LatestRequest::default);
+
+    {
+        let prices = prices.clone();
+        let dividends = dividends.clone();
+        let error = error.clone();
+        let latest_request = latest_request.clone();
+        use_effect_with(props.code.clone(), move |code| {
+            let code = code.clone();
+            let today = Local::now().date_naive();
+            prices.set(Vec::new());
+            dividends.set(Vec::new());
+            error.set(None);
+            spawn_local(async move {
+                let from = ChartRange::FiveYears.start(today);
+                let request = |signal: Option<_>| async move {
+                    let prices =
+                        shoken_web_api::fetch_daily_prices(&code, from, today, signal.as_ref())
+                            .await;
+                    let dividends =
+                        shoken_web_api::fetch_dividend_history(&code, signal.as_ref()).await;
+                    Ok::<_, ApiError>((prices, dividends))
+                };
+                let Ok((loaded_prices, loaded_dividends)) =
+                    latest_request.debounce(0, request).await
+                else {
+                    return;
+                };
+                match loaded_prices {
+                    Ok(loaded) => prices.set(loaded),
+                    Err(err) => error.set(Some(err.to_string())),
+                }
+                // 配当の履歴は取得できなくてもチャートは表示する
+                if let Ok(loaded) = loaded_dividends {
+                    dividends.set(loaded);
+                }
+            });
+        });
+    }
+
+    let start = range.start(Local::now().date_naive());
+    let visible: Vec<DailyPrice> = prices
+        .iter()
+        .filter(|price| price.date >= start)
+        .cloned()
+        .collect();
+
+    html! {
+        <div class="card shadow-sm mb-4">
+            <div class="card-header d-flex flex-wrap align-items-center gap-2">
+                <h5 class="mb-0 me-auto">{ "株価チャート" }</h5>
+                { render_button_group(&range) }
+                { render_button_group(&kind) }
+            </div>
+            <div class="card-body">
+                {
+                    match (&*error, visible.is_empty()) {
+                        (Some(err), _) => html! { <div class="alert alert-warning mb-0" role="alert">{ err }</div> },
+                        (None, true) => html! { <p class="text-muted mb-0">{ "株価データがありません" }</p> },
+                        (None, false) => render_chart(&visible, *kind, &dividends, &props.trades),
+                    }
+                }
+            </div>
+        </div>
+    }
+}
+
+fn render_button_group<T>(selected: &UseStateHandle<T>) -> Html
+where
+    T: Copy + PartialEq + EnumMessage + IntoEnumIterator + 'static,
+{
+    html! {
+        <div class="btn-group btn-group-sm" role="group">
+            { for T::iter().map(|item| {
+                let class = if item == **selected { "btn btn-info text-white" } else { "btn btn-outline-info" };
+                let selected = selected.clone();
+                html! {
+                    <button type="button" class={class} onclick={Callback::from(move |_| selected.set(item))}>
+                        { item.get_message() }
+                    </button>
+                }
+            })}
+        </div>
+    }
+}
+
+/// 価格と日付 (売買日の順番) を SVG の座標に変換する
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct ChartScale {
+    min: f64,
+    max: f64,
+    count: usize,
+}
+
+impl ChartScale {
+    fn new(prices: &[DailyPrice]) -> Option<Self> {
+        let min = prices.iter().map(|p| p.low).reduce(f64::min)?;
+        let max = prices.iter().map(|p| p.high).reduce(f64::max)?;
+        // 上下に 5% の余白を取る
+        let padding = ((max - min) * 0.05).max(1.0);
+        Some(Self {
+            min: (min - padding).max(0.0),
+            max: max + padding,
+            count: prices.len(),
+        })
+    }
+
+    fn step(&self) -> f64 {
+        (WIDTH - MARGIN_LEFT - MARGIN_RIGHT) / self.count.max(1) as f64
+    }
+
+    /// `index` 番目の日の中央の x 座標
+    fn x(&self, index: usize) -> f64 {
+        MARGIN_LEFT + self.step() * (index as f64 + 0.5)
+    }
+
+    fn y(&self, price: f64) -> f64 {
+        let height = HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+        MARGIN_TOP + height * (self.max - price) / (self.max - self.min)
+    }
+}
+
+/// `date` 以降で最初の営業日の番号。表示する期間の外なら `None`。
+fn index_of(prices: &[DailyPrice], date: NaiveDate) -> Option<usize> {
+    if prices.first().is_none_or(|first| date < first.date) {
+        return None;
+    }
+    let index = prices.partition_point(|price| price.date < date);
+    (index < prices.len()).then_some(index)
+}
+
+fn render_chart(
+    prices: &[DailyPrice],
+    kind: ChartKind,
+    dividends: &[DividendHistory],
+    trades: &[TradeMarker],
+) -> Html {
+    let Some(scale) = ChartScale::new(prices) else {
+        return html! {};
+    };
+    let view_box = format!("0 0 {} {}", WIDTH, HEIGHT);
+
+    html! {
+        <svg viewBox={view_box} class="w-100" role="img" aria-label="株価チャート">
+            { render_axes(prices, &scale) }
+            {
+                match kind {
+                    ChartKind::Line => render_line(prices, &scale),
+                    ChartKind::Candlestick => render_candlesticks(prices, &scale),
+                }
+            }
+            { for dividends.iter().filter_map(|dividend| {
+                let index = index_of(prices, dividend.ex_date)?;
+                let x = scale.x(index);
+                let y = HEIGHT - MARGIN_BOTTOM - 6.0;
+                Some(html! {
+                    <g>
+                        <title>{ format!("{} 配当 {} 円", dividend.ex_date.format("%Y/%m/%d"), dividend.amount) }</title>
+                        <circle cx={x.to_string()} cy={y.to_string()} r="4" fill="#198754" />
+                    </g>
+                })
+            })}
+            { for trades.iter().filter_map(|trade| {
+                let index = index_of(prices, trade.date)?;
+                let x = scale.x(index);
+                let y = scale.y(prices[index].high) - 4.0;
+                let points = format!("{},{} {},{} {},{}", x, y, x - 6.0, y - 10.0, x + 6.0, y - 10.0);
+                Some(html! {
+                    <g>
+                        <title>{ format!("{} {}", trade.date.format("%Y/%m/%d"), trade.label) }</title>
+                        <polygon points={points} fill="#fd7e14" />
+                    </g>
+                })
+            })}
+        </svg>
+    }
+}
+
+fn render_axes(prices: &[DailyPrice], scale: &ChartScale) -> Html {
+    let bottom = HEIGHT - MARGIN_BOTTOM;
+    let last = prices.len() - 1;
+    let x_labels = [0, last / 2, last];
+
+    html! {
+        <g font-size="11" fill="#6c757d">
+            { for (0..=Y_TICKS).map(|i| {
+                let price = scale.min + (scale.max - scale.min) * i as f64 / Y_TICKS as f64;
+                let y = scale.y(price);
+                html! {
+                    <>
+                        <line x1={MARGIN_LEFT.to_string()} x2={(WIDTH - MARGIN_RIGHT).to_string()}
+                            y1={y.to_string()} y2={y.to_string()} stroke="#dee2e6" />
+                        <text x={(MARGIN_LEFT - 6.0).to_string()} y={(y + 4.0).to_string()} text-anchor="end">
+                            { format!("{:.0}", price).as_str().format_number() }
+                        </text>
+                    </>
+                }
+            })}
+            { for x_labels.iter().map(|&i| html! {
+                <text x={scale.x(i).to_string()} y={(bottom + 18.0).to_string()} text-anchor="middle">
+                    { prices[i].date.format("%Y/%m/%d").to_string() }
+                </text>
+            })}
+        </g>
+    }
+}
+
+fn render_line(prices: &[DailyPrice], scale: &ChartScale) -> Html {
+    let path = prices
+        .iter()
+        .enumerate()
+        .map(|(i, price)| {
+            let command = if i == 0 { "M" } else { "L" };
+            format!("{}{:.1},{:.1}", command, scale.x(i), scale.y(price.close))
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    html! { <path d={path} fill="none" stroke={LINE_COLOR} stroke-width="2" /> }
+}
+
+fn render_candlesticks(prices: &[DailyPrice], scale: &ChartScale) -> Html {
+    let body_width = (scale.step() * 0.6).max(1.0);
+    html! {
+        <g>
+            { for prices.iter().enumerate().map(|(i, price)| {
+                let x = scale.x(i);
+                let color = if price.close >= price.open { UP_COLOR } else { DOWN_COLOR };
+                let top = scale.y(price.open.max(price.close));
+                let height = (scale.y(price.open.min(price.close)) - top).max(1.0);
+                html! {
+                    <g>
+                        <line x1={x.to_string()} x2={x.to_string()}
+                            y1={scale.y(price.high).to_string()} y2={scale.y(price.low).to_string()} stroke={color} />
+                        <rect x={(x - body_width / 2.0).to_string()} y={top.to_string()}
+                            width={body_width.to_string()} height={height.to_string()} fill={color} />
+                    </g>
+                }
+            })}
+        </g>
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn price(day: u32, low: f64, high: f64) -> DailyPrice {
+        DailyPrice {
+            date: NaiveDate::from_ymd_opt(2024, 1, day).unwrap(),
+            open: low,
+            high,
+            low,
+            close: high,
+            volume: 0,
+        }
+    }
+
+    #[test]
+    fn test_chart_range_start() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+        assert_eq!(
+            ChartRange::OneMonth.start(today),
+            NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()
+        );
+        assert_eq!(
+            ChartRange::FiveYears.start(today),
+            NaiveDate::from_ymd_opt(2019, 3, 31).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_chart_scale() {
+        let prices = vec![price(4, 1000.0, 1100.0), price(5, 1050.0, 1200.0)];
+        let scale = ChartScale::new(&prices).unwrap();
+
+        assert_eq!((scale.min, scale.max), (990.0, 1210.0));
+        assert_eq!(scale.y(scale.max), MARGIN_TOP);
+        assert_eq!(scale.y(scale.min), HEIGHT - MARGIN_BOTTOM);
+        assert!(scale.x(0) < scale.x(1));
+        assert!(ChartScale::new(&[]).is_none());
+    }
+
+    #[test]
+    fn test_index_of() {
+        let prices = vec![price(4, 1.0, 2.0), price(5, 1.0, 2.0), price(9, 1.0, 2.0)];
+        let date = |day| NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+
+        assert_eq!(index_of(&prices, date(1)), None);
+        assert_eq!(index_of(&prices, date(4)), Some(0));
+        assert_eq!(index_of(&prices, date(5)), Some(1));
+        // 休日の取引は翌営業日に表示する
+        assert_eq!(index_of(&prices, date(6)), Some(2));
+        assert_eq!(index_of(&prices, date(10)), None);
+    }
+}
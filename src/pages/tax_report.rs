@@ -0,0 +1,182 @@
+use gloo::console;
+use strum::{EnumMessage, IntoEnumIterator};
+use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+
+mod annual_statement;
+
+use annual_statement::{AccountType, AnnualStatement, TaxEstimate};
+
+use super::{
+    layout::Layout,
+    receipts::{
+        dividend_list::DividendList, domestic_stock::DomesticStock, exchange_rates, receipt_storage,
+    },
+};
+use crate::services::formater::StrFormater;
+
+#[function_component]
+pub fn TaxReport() -> Html {
+    let stocks = use_state(Vec::<DomesticStock>::new);
+    let dividends = use_state(Vec::<DividendList>::new);
+    let other_taxable_income = use_state(|| 0_i64);
+
+    {
+        let stocks = stocks.clone();
+        let dividends = dividends.clone();
+        use_effect_with((), move |_| {
+            spawn_local(async move {
+                match receipt_storage::load::<DomesticStock>().await {
+                    Ok(loaded) => stocks.set(loaded),
+                    Err(err) => console::log!(err.to_string()),
+                }
+                match receipt_storage::load::<DividendList>().await {
+                    Ok(loaded) => dividends.set(loaded),
+                    Err(err) => console::log!(err.to_string()),
+                }
+            });
+        });
+    }
+
+    let on_input_income = {
+        let other_taxable_income = other_taxable_income.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            other_taxable_income.set(input.value().replace(',', "").parse().unwrap_or(0));
+        })
+    };
+
+    let rates = exchange_rates::load_rates();
+    let conversion = exchange_rates::convert_to_jpy(&dividends, &rates);
+    let dividends = conversion.converted;
+    let statements = annual_statement::build_statements(&stocks, &dividends, *other_taxable_income);
+
+    html! {
+        <Layout>
+            <div class="card shadow-sm">
+                <div class="card-header bg-info text-white">
+                    <h5 class="mb-0">{ "確定申告" }</h5>
+                </div>
+                <div class="card-body">
+                    { exchange_rates::render_missing_rates(&conversion.missing) }
+                    <div class="row g-2 align-items-center mb-3">
+                        <label class="col-auto col-form-label" for="other-taxable-income">
+                            { "配当を除く課税所得" }
+                        </label>
+                        <div class="col-auto">
+                            <input
+                                id="other-taxable-income"
+                                type="number"
+                                class="form-control"
+                                min="0"
+                                step="1000"
+                                value={other_taxable_income.to_string()}
+                                oninput={on_input_income}
+                            />
+                        </div>
+                        <div class="col-auto form-text">
+                            { "総合課税を選んだ場合の税率の判定に使います。" }
+                        </div>
+                    </div>
+                    {
+                        if statements.is_empty() {
+                            html! { <p class="text-muted mb-0">{ "受取金ページで国内株式・配当金の CSV を取り込んでください。" }</p> }
+                        } else {
+                            render_statements(&statements)
+                        }
+                    }
+                    <p class="form-text mt-3 mb-0">
+                        { "特定口座は源泉徴収ありとして試算しています。繰越損失は配当を申告分離課税とした場合の金額です。" }
+                    </p>
+                </div>
+            </div>
+        </Layout>
+    }
+}
+
+fn render_statements(statements: &[AnnualStatement]) -> Html {
+    let row = |label: &str, value: &dyn Fn(&AnnualStatement) -> i64| {
+        html! {
+            <tr>
+                <th scope="row" class="text-nowrap">{ label }</th>
+                { for statements.iter().map(|s| render_yen(value(s))) }
+            </tr>
+        }
+    };
+    let gain_label =
+        |account: AccountType| format!("譲渡損益 ({})", account.get_message().unwrap_or_default());
+
+    html! {
+    <div class="table-responsive">
+        <table class="table table-sm table-hover">
+            <thead>
+                <tr>
+                    <th scope="col"></th>
+                    { for statements.iter().map(|s| html! { <th scope="col" class="text-end">{ format!("{}年", s.year) }</th> }) }
+                </tr>
+            </thead>
+            <tbody>
+                { for AccountType::iter().map(|account| row(&gain_label(account), &move |s| match account {
+                    AccountType::Specific => s.specific_gain,
+                    AccountType::General => s.general_gain,
+                    AccountType::Nisa => s.nisa_gain,
+                })) }
+                { row("配当所得", &|s| s.dividends) }
+                { row("配当 (NISA)", &|s| s.nisa_dividends) }
+                { row("源泉徴収税額", &|s| s.withheld_tax) }
+                { row("繰越控除額", &|s| s.carried_loss_used) }
+                { row("翌年以降への繰越損失", &|s| s.carried_loss_remaining) }
+            </tbody>
+            { render_estimate_rows(statements, "申告分離課税", |s| s.separate) }
+            { render_estimate_rows(statements, "総合課税", |s| s.aggregate) }
+        </table>
+    </div>
+    }
+}
+
+fn render_estimate_rows(
+    statements: &[AnnualStatement],
+    title: &str,
+    estimate: fn(&AnnualStatement) -> TaxEstimate,
+) -> Html {
+    html! {
+    <tbody class="table-group-divider">
+        <tr class="table-light">
+            <th scope="row" colspan={(statements.len() + 1).to_string()}>{ format!("配当を{}とした場合", title) }</th>
+        </tr>
+        <tr>
+            <th scope="row">{ "課税所得" }</th>
+            { for statements.iter().map(|s| render_yen(estimate(s).taxable_income)) }
+        </tr>
+        <tr>
+            <th scope="row">{ "税額" }</th>
+            { for statements.iter().map(|s| render_yen(estimate(s).tax)) }
+        </tr>
+        <tr>
+            <th scope="row">{ "納付 / 還付" }</th>
+            { for statements.iter().map(|s| render_balance(estimate(s).balance)) }
+        </tr>
+    </tbody>
+    }
+}
+
+fn render_yen(value: i64) -> Html {
+    let class = if value < 0 {
+        "text-end text-nowrap text-danger"
+    } else {
+        "text-end text-nowrap"
+    };
+    html! { <td class={class}>{ value.to_string().as_str().format_yen() }</td> }
+}
+
+fn render_balance(balance: i64) -> Html {
+    let (label, class) = match balance {
+        b if b < 0 => ("還付", "text-end text-nowrap text-success fw-bold"),
+        b if b > 0 => ("納付", "text-end text-nowrap text-danger fw-bold"),
+        _ => ("", "text-end text-nowrap"),
+    };
+    html! {
+        <td class={class}>{ format!("{} {}", label, balance.abs().to_string().as_str().format_yen()) }</td>
+    }
+}
@@ -0,0 +1,351 @@
+use chrono::Datelike;
+use std::collections::BTreeMap;
+use strum::{EnumIter, EnumMessage};
+
+use crate::{
+    pages::receipts::{dividend_list::DividendList, domestic_stock::DomesticStock},
+    services::tax_rule::TaxRule,
+};
+
+/// 譲渡損失を繰り越せる年数
+const CARRY_FORWARD_YEARS: i32 = 3;
+
+/// 所得税の速算表 (課税所得の上限, 税率, 控除額)
+const INCOME_TAX_BRACKETS: [(i64, f64, i64); 7] = [
+    (1_949_000, 0.05, 0),
+    (3_299_000, 0.10, 97_500),
+    (6_949_000, 0.20, 427_500),
+    (8_999_000, 0.23, 636_000),
+    (17_999_000, 0.33, 1_536_000),
+    (39_999_000, 0.40, 2_796_000),
+    (i64::MAX, 0.45, 4_796_000),
+];
+
+const RECONSTRUCTION_TAX_RATE: f64 = 0.021; // 復興特別所得税 (所得税額に対する割合)
+const RESIDENT_TAX_RATE: f64 = 0.10; // 住民税 (総合課税)
+const DIVIDEND_CREDIT_THRESHOLD: i64 = 10_000_000; // 配当控除率が下がる課税所得
+const DIVIDEND_CREDIT_RATES: (f64, f64) = (0.10, 0.05); // 配当控除 (所得税)
+const RESIDENT_DIVIDEND_CREDIT_RATES: (f64, f64) = (0.028, 0.014); // 配当控除 (住民税)
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, EnumMessage, EnumIter)]
+pub enum AccountType {
+    #[strum(message = "特定")]
+    Specific,
+
+    #[strum(message = "一般")]
+    General,
+
+    #[strum(message = "NISA")]
+    Nisa,
+}
+
+impl AccountType {
+    /// CSV の口座区分 (`特定口座`, `NISA口座(成長投資枠)` など) から判定する。
+    pub fn from_account(account: &str) -> Self {
+        if account.contains("NISA") || account.contains("ＮＩＳＡ") {
+            Self::Nisa
+        } else if account.contains("特定") {
+            Self::Specific
+        } else {
+            Self::General
+        }
+    }
+}
+
+/// 配当所得の課税方式ごとの試算結果
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct TaxEstimate {
+    pub taxable_income: i64, // 課税所得
+    pub tax: i64,            // 税額
+    pub balance: i64,        // 納付額 (負の場合は還付額)
+}
+
+/// 1 年分の確定申告の試算
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct AnnualStatement {
+    pub year: i32,
+    pub specific_gain: i64,          // 譲渡損益 (特定口座)
+    pub general_gain: i64,           // 譲渡損益 (一般口座)
+    pub nisa_gain: i64,              // 譲渡損益 (NISA, 非課税)
+    pub dividends: i64,              // 配当所得 (課税口座)
+    pub nisa_dividends: i64,         // 配当 (NISA, 非課税)
+    pub withheld_tax: i64,           // 源泉徴収税額
+    pub carried_loss_used: i64,      // 繰越控除額
+    pub carried_loss_remaining: i64, // 翌年以降へ繰り越す損失
+    pub separate: TaxEstimate,       // 申告分離課税
+    pub aggregate: TaxEstimate,      // 総合課税
+}
+
+/// 年ごとの集計
+#[derive(Default)]
+struct YearTotals {
+    gains: [i64; 3], // AccountType の順
+    dividends: i64,
+    nisa_dividends: i64,
+    dividend_taxes: i64,
+}
+
+/// 国内株式の譲渡損益と配当金から、年ごとの確定申告の試算を作る。
+///
+/// `other_taxable_income` は配当を除く総合課税の課税所得で、総合課税を選んだ場合の税率の判定に使う。
+/// 譲渡損失の繰越控除は、配当を申告分離課税とした場合の損益通算後の損失で計算する。
+/// `dividends` は円に換算済みの行を渡す (外国税額控除は試算に含めない)。
+/// 譲渡損益は受渡日の年に計上する (受渡日がない行は約定日で判定する)。
+pub fn build_statements(
+    stocks: &[DomesticStock],
+    dividends: &[DividendList],
+    other_taxable_income: i64,
+) -> Vec<AnnualStatement> {
+    let mut years: BTreeMap<i32, YearTotals> = BTreeMap::new();
+    for stock in stocks {
+        let (Some(date), Some(profit)) = (stock.tax_date(), stock.realized_profit_and_loss) else {
+            continue;
+        };
+        let account = AccountType::from_account(stock.account.as_deref().unwrap_or_default());
+        years.entry(date.year()).or_default().gains[account as usize] += profit as i64;
+    }
+    for dividend in dividends {
+        let Some(date) = dividend.settlement_date else {
+            continue;
+        };
+        let totals = years.entry(date.year()).or_default();
+        let amount = dividend.dividends_before_tax.unwrap_or(0.0) as i64;
+        match AccountType::from_account(dividend.account.as_deref().unwrap_or_default()) {
+            AccountType::Nisa => totals.nisa_dividends += amount,
+            _ => {
+                totals.dividends += amount;
+                totals.dividend_taxes += dividend.taxes.unwrap_or(0.0) as i64;
+            }
+        }
+    }
+
+    // (発生年, 残額)
+    let mut carried_losses: Vec<(i32, i64)> = Vec::new();
+    years
+        .into_iter()
+        .map(|(year, totals)| {
+            carried_losses.retain(|(loss_year, _)| year - loss_year <= CARRY_FORWARD_YEARS);
+            new_statement(year, &totals, other_taxable_income, &mut carried_losses)
+        })
+        .collect()
+}
+
+fn new_statement(
+    year: i32,
+    totals: &YearTotals,
+    other_taxable_income: i64,
+    carried_losses: &mut Vec<(i32, i64)>,
+) -> AnnualStatement {
+    let [specific_gain, general_gain, nisa_gain] = totals.gains;
+    let rule = TaxRule::for_year(year);
+    let taxable_gain = specific_gain + general_gain;
+    // 特定口座 (源泉徴収あり) で差し引かれた税額
+    let withheld_gain_tax = rule.withhold(specific_gain).total();
+    let withheld_tax = withheld_gain_tax + totals.dividend_taxes;
+
+    // 申告分離課税: 譲渡損失と配当を損益通算し、繰越損失を古い順に控除する
+    let netted = taxable_gain + totals.dividends;
+    let available: i64 = carried_losses.iter().map(|(_, loss)| loss).sum();
+    let separate_used = netted.max(0).min(available);
+    let separate_taxable = netted.max(0) - separate_used;
+    let separate_tax = rule.withhold(separate_taxable).total();
+
+    // 総合課税: 配当は損益通算できず、繰越損失は譲渡所得からのみ控除する
+    let aggregate_used = taxable_gain.max(0).min(available);
+    let aggregate_gain_tax = rule.withhold(taxable_gain.max(0) - aggregate_used).total();
+    let aggregate_tax =
+        aggregate_gain_tax + aggregate_dividend_tax(&rule, totals.dividends, other_taxable_income);
+
+    consume_losses(carried_losses, separate_used);
+    if netted < 0 {
+        carried_losses.push((year, -netted));
+    }
+
+    AnnualStatement {
+        year,
+        specific_gain,
+        general_gain,
+        nisa_gain,
+        dividends: totals.dividends,
+        nisa_dividends: totals.nisa_dividends,
+        withheld_tax,
+        carried_loss_used: separate_used,
+        carried_loss_remaining: carried_losses.iter().map(|(_, loss)| loss).sum(),
+        separate: TaxEstimate {
+            taxable_income: separate_taxable,
+            tax: separate_tax,
+            balance: separate_tax - withheld_tax,
+        },
+        aggregate: TaxEstimate {
+            taxable_income: taxable_gain.max(0) - aggregate_used + totals.dividends,
+            tax: aggregate_tax,
+            balance: aggregate_tax - withheld_tax,
+        },
+    }
+}
+
+/// 古い年の損失から順に控除する。
+fn consume_losses(carried_losses: &mut Vec<(i32, i64)>, mut amount: i64) {
+    for (_, loss) in carried_losses.iter_mut() {
+        let used = amount.min(*loss);
+        *loss -= used;
+        amount -= used;
+    }
+    carried_losses.retain(|(_, loss)| *loss > 0);
+}
+
+/// 配当を総合課税とした場合に増える所得税・住民税から配当控除を差し引いた額
+fn aggregate_dividend_tax(rule: &TaxRule, dividends: i64, other_taxable_income: i64) -> i64 {
+    if dividends <= 0 {
+        return 0;
+    }
+    let total_income = other_taxable_income.max(0) + dividends;
+    let income_tax = income_tax(total_income) - income_tax(other_taxable_income.max(0));
+
+    // 課税所得が 1,000 万円を超える部分の配当は控除率が下がる
+    let over_threshold = (total_income - DIVIDEND_CREDIT_THRESHOLD).clamp(0, dividends);
+    let under_threshold = dividends - over_threshold;
+    let credit = |(low, high): (f64, f64)| {
+        (under_threshold as f64 * low + over_threshold as f64 * high) as i64
+    };
+
+    let income_tax = (income_tax - credit(DIVIDEND_CREDIT_RATES)).max(0);
+    let reconstruction_tax = if rule.has_reconstruction_tax() {
+        (income_tax as f64 * RECONSTRUCTION_TAX_RATE) as i64
+    } else {
+        0
+    };
+    let resident_tax = ((dividends as f64 * RESIDENT_TAX_RATE) as i64
+        - credit(RESIDENT_DIVIDEND_CREDIT_RATES))
+    .max(0);
+
+    income_tax + reconstruction_tax + resident_tax
+}
+
+/// 速算表による所得税額
+fn income_tax(taxable_income: i64) -> i64 {
+    INCOME_TAX_BRACKETS
+        .iter()
+        .find(|(limit, _, _)| taxable_income <= *limit)
+        .map_or(0, |(_, rate, deduction)| {
+            ((taxable_income as f64 * rate) as i64 - deduction).max(0)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn stock(date: (i32, u32, u32), account: &str, profit: i32) -> DomesticStock {
+        DomesticStock {
+            trade_date: NaiveDate::from_ymd_opt(date.0, date.1, date.2),
+            account: Some(account.to_string()),
+            realized_profit_and_loss: Some(profit),
+            ..Default::default()
+        }
+    }
+
+    fn dividend(date: (i32, u32, u32), account: &str, amount: i32, taxes: i32) -> DividendList {
+        DividendList {
+            settlement_date: NaiveDate::from_ymd_opt(date.0, date.1, date.2),
+            account: Some(account.to_string()),
+            dividends_before_tax: Some(amount as f64),
+            taxes: Some(taxes as f64),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_account_type() {
+        assert_eq!(AccountType::from_account("特定口座"), AccountType::Specific);
+        assert_eq!(AccountType::from_account("一般"), AccountType::General);
+        assert_eq!(
+            AccountType::from_account("NISA口座(成長投資枠)"),
+            AccountType::Nisa
+        );
+    }
+
+    #[test]
+    fn test_build_statements_by_account() {
+        let stocks = vec![
+            stock((2024, 2, 5), "特定", 45_000),
+            stock((2024, 4, 10), "NISA", -10_000),
+            stock((2024, 6, 1), "一般", 5_000),
+        ];
+        let dividends = vec![
+            dividend((2024, 3, 28), "特定口座", 3_000, 609),
+            dividend((2024, 6, 27), "NISA口座(成長投資枠)", 10_000, 0),
+        ];
+        let statements = build_statements(&stocks, &dividends, 0);
+
+        assert_eq!(statements.len(), 1);
+        let s = &statements[0];
+        assert_eq!(
+            (s.specific_gain, s.general_gain, s.nisa_gain),
+            (45_000, 5_000, -10_000)
+        );
+        assert_eq!((s.dividends, s.nisa_dividends), (3_000, 10_000));
+        assert_eq!(s.withheld_tax, 9_141 + 609);
+        assert_eq!(s.separate.taxable_income, 53_000);
+        assert_eq!(s.separate.tax, 10_766);
+        assert_eq!(s.separate.balance, 10_766 - 9_750);
+    }
+
+    #[test]
+    fn test_gain_counts_in_settlement_year() {
+        // 年末に約定し、年明けに受け渡した売却は翌年の所得になる
+        let stocks = vec![
+            DomesticStock {
+                settlement_date: NaiveDate::from_ymd_opt(2025, 1, 6),
+                ..stock((2024, 12, 27), "特定", 30_000)
+            },
+            stock((2024, 12, 2), "特定", 10_000),
+        ];
+        let statements = build_statements(&stocks, &[], 0);
+
+        assert_eq!(statements.len(), 2);
+        assert_eq!(
+            (statements[0].year, statements[0].specific_gain),
+            (2024, 10_000)
+        );
+        assert_eq!(
+            (statements[1].year, statements[1].specific_gain),
+            (2025, 30_000)
+        );
+    }
+
+    #[test]
+    fn test_loss_offsets_dividends_and_carries_forward() {
+        let stocks = vec![
+            stock((2021, 5, 1), "特定", -100_000),
+            stock((2022, 5, 1), "特定", 30_000),
+            stock((2025, 5, 1), "特定", 50_000),
+        ];
+        let dividends = vec![dividend((2021, 6, 1), "特定", 20_000, 4_063)];
+        let statements = build_statements(&stocks, &dividends, 0);
+
+        // 2021 年: 配当と損益通算し、源泉徴収税額が還付される
+        assert_eq!(statements[0].separate.balance, -4_063);
+        assert_eq!(statements[0].carried_loss_remaining, 80_000);
+        // 総合課税では配当と損益通算できない
+        assert!(statements[0].aggregate.tax > 0);
+
+        // 2022 年: 繰越損失を控除する
+        assert_eq!(statements[1].carried_loss_used, 30_000);
+        assert_eq!(statements[1].separate.tax, 0);
+        assert_eq!(statements[1].carried_loss_remaining, 50_000);
+
+        // 2025 年: 3 年を過ぎた損失は控除できない
+        assert_eq!(statements[2].carried_loss_used, 0);
+        assert_eq!(statements[2].separate.taxable_income, 50_000);
+    }
+
+    #[test]
+    fn test_aggregate_dividend_tax() {
+        // 課税所得 195 万円以下: 所得税 5% は配当控除 10% で 0、住民税 10% - 2.8%
+        let rule = TaxRule::for_year(2024);
+        assert_eq!(aggregate_dividend_tax(&rule, 100_000, 1_000_000), 7_200);
+        assert_eq!(aggregate_dividend_tax(&rule, 0, 1_000_000), 0);
+    }
+}
@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+
+use gloo::console;
+use wasm_bindgen_futures::spawn_local;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+use yew_router::prelude::*;
+
+use super::layout::Layout;
+use crate::{
+    app::Route,
+    data::{
+        stock::StockData,
+        watchlist::{Watchlist, WatchlistAction, WatchlistError},
+    },
+    services::{csv_reader, csv_writer, shoken_web_api},
+};
+
+/// ウォッチリストの一覧
+#[function_component]
+pub fn WatchlistPage() -> Html {
+    let watchlist = use_context::<UseReducerHandle<Watchlist>>().expect("Watchlist context");
+    let selected = use_state(|| 0usize);
+    let new_list_name = use_state(String::new);
+    let stocks = use_state(HashMap::<String, StockData>::new);
+    let import_error = use_state(|| None::<String>);
+
+    let list = (*selected).min(watchlist.lists.len() - 1);
+    let codes = watchlist.lists[list].codes.clone();
+
+    {
+        let stocks = stocks.clone();
+        use_effect_with(codes.clone(), move |codes| {
+            let codes = codes.clone();
+            let mut loaded = (*stocks).clone();
+            spawn_local(async move {
+                let missing: Vec<String> = codes
+                    .into_iter()
+                    .filter(|code| !loaded.contains_key(code))
+                    .collect();
+                for code in missing {
+                    match shoken_web_api::fetch_stock_data(&code, None).await {
+                        Ok(fetched) => {
+                            loaded.insert(code, fetched.value);
+                        }
+                        Err(err) => console::log!(format!("{}: {}", code, err)),
+                    }
+                }
+                stocks.set(loaded);
+            });
+        });
+    }
+
+    let on_input_list_name = {
+        let new_list_name = new_list_name.clone();
+        Callback::from(move |e: InputEvent| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            new_list_name.set(input.value());
+        })
+    };
+
+    let on_add_list = {
+        let watchlist = watchlist.clone();
+        let new_list_name = new_list_name.clone();
+        let selected = selected.clone();
+        Callback::from(move |e: SubmitEvent| {
+            e.prevent_default();
+            if new_list_name.trim().is_empty() {
+                return;
+            }
+            selected.set(
+                watchlist
+                    .lists
+                    .iter()
+                    .position(|g| g.name == new_list_name.trim())
+                    .unwrap_or(watchlist.lists.len()),
+            );
+            watchlist.dispatch(WatchlistAction::AddList((*new_list_name).clone()));
+            new_list_name.set(String::new());
+        })
+    };
+
+    let on_remove_list = {
+        let watchlist = watchlist.clone();
+        let selected = selected.clone();
+        Callback::from(move |_| {
+            watchlist.dispatch(WatchlistAction::RemoveList(list));
+            selected.set(list.saturating_sub(1));
+        })
+    };
+
+    let on_export = {
+        let watchlist = watchlist.clone();
+        Callback::from(move |_| {
+            let json = watchlist.to_json();
+            if let Err(err) =
+                csv_writer::download_as(json.as_bytes(), "watchlist.json", "application/json")
+            {
+                console::log!(err);
+            }
+        })
+    };
+
+    let on_import = {
+        let watchlist = watchlist.clone();
+        let import_error = import_error.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            let Some(file) = input.files().and_then(|files| files.get(0)) else {
+                return;
+            };
+            input.set_value("");
+            let watchlist = watchlist.clone();
+            let import_error = import_error.clone();
+            spawn_local(async move {
+                let result = csv_reader::read_file(&file)
+                    .await
+                    .map_err(|e| e.to_string())
+                    .and_then(|bytes| {
+                        let json = String::from_utf8_lossy(&bytes);
+                        Watchlist::from_json(&json).map_err(|e: WatchlistError| e.to_string())
+                    });
+                match result {
+                    Ok(imported) => {
+                        watchlist.dispatch(WatchlistAction::Import(imported));
+                        import_error.set(None);
+                    }
+                    Err(err) => import_error.set(Some(err)),
+                }
+            });
+        })
+    };
+
+    html! {
+        <Layout>
+            <div class="card shadow-sm mb-4">
+                <div class="card-header bg-info text-white d-flex align-items-center">
+                    <h5 class="mb-0 me-auto">{ "ウォッチリスト" }</h5>
+                    <button type="button" class="btn btn-sm btn-light me-2" onclick={on_export}>
+                        { "JSON で書き出し" }
+                    </button>
+                    <label class="btn btn-sm btn-light mb-0">
+                        { "JSON を読み込み" }
+                        <input type="file" accept=".json,application/json" class="d-none" onchange={on_import} />
+                    </label>
+                </div>
+                <div class="card-body">
+                    if let Some(err) = &*import_error {
+                        <div class="alert alert-danger" role="alert">{ err }</div>
+                    }
+                    <div class="d-flex flex-wrap align-items-center gap-2 mb-3">
+                        <ul class="nav nav-pills me-auto">
+                            { for watchlist.lists.iter().enumerate().map(|(i, group)| {
+                                let class = if i == list { "nav-link active" } else { "nav-link" };
+                                let selected = selected.clone();
+                                html! {
+                                    <li class="nav-item">
+                                        <button type="button" class={class} onclick={Callback::from(move |_| selected.set(i))}>
+                                            { &group.name }
+                                            <span class="badge text-bg-light ms-2">{ group.codes.len() }</span>
+                                        </button>
+                                    </li>
+                                }
+                            })}
+                        </ul>
+                        <form class="d-flex gap-2" onsubmit={on_add_list}>
+                            <input type="text" class="form-control form-control-sm" placeholder="新しいリスト名"
+                                value={(*new_list_name).clone()} oninput={on_input_list_name} />
+                            <button type="submit" class="btn btn-sm btn-outline-info text-nowrap">{ "リストを追加" }</button>
+                        </form>
+                        <button type="button" class="btn btn-sm btn-outline-danger text-nowrap" onclick={on_remove_list}>
+                            { "このリストを削除" }
+                        </button>
+                    </div>
+                    { render_codes(&watchlist, list, &codes, &stocks) }
+                </div>
+            </div>
+        </Layout>
+    }
+}
+
+fn render_codes(
+    watchlist: &UseReducerHandle<Watchlist>,
+    list: usize,
+    codes: &[String],
+    stocks: &HashMap<String, StockData>,
+) -> Html {
+    if codes.is_empty() {
+        return html! {
+            <p class="text-muted mb-0">{ "銘柄検索の結果からウォッチリストに追加できます" }</p>
+        };
+    }
+    html! {
+        <div class="table-responsive">
+            <table class="table table-hover align-middle">
+                <thead>
+                    <tr>
+                        <th>{ "銘柄コード" }</th>
+                        <th>{ "銘柄名" }</th>
+                        <th>{ "マーケットカテゴリ" }</th>
+                        <th>{ "33業種区分" }</th>
+                        <th>{ "17業種区分" }</th>
+                        <th>{ "規模区分" }</th>
+                        <th></th>
+                    </tr>
+                </thead>
+                <tbody>
+                    { for codes.iter().enumerate().map(|(i, code)| {
+                        let stock = stocks.get(code).cloned().unwrap_or_default();
+                        let dispatch = |action: fn(usize, usize, String) -> WatchlistAction| {
+                            let watchlist = watchlist.clone();
+                            let code = code.clone();
+                            Callback::from(move |_| watchlist.dispatch(action(list, i, code.clone())))
+                        };
+                        html! {
+                            <tr>
+                                <td>
+                                    <Link<Route> to={Route::Stock { code: code.clone() }}>{ code }</Link<Route>>
+                                </td>
+                                <td>{ stock.name }</td>
+                                <td>{ stock.market_category }</td>
+                                <td>{ stock.industry_category_33.unwrap_or_default() }</td>
+                                <td>{ stock.industry_category_17.unwrap_or_default() }</td>
+                                <td>{ stock.size_category.unwrap_or_default() }</td>
+                                <td class="text-nowrap text-end">
+                                    <button type="button" class="btn btn-sm btn-outline-secondary me-1" title="上へ"
+                                        disabled={i == 0}
+                                        onclick={dispatch(|list, i, _| WatchlistAction::Move { list, from: i, to: i.saturating_sub(1) })}>
+                                        { "↑" }
+                                    </button>
+                                    <button type="button" class="btn btn-sm btn-outline-secondary me-1" title="下へ"
+                                        disabled={i + 1 == codes.len()}
+                                        onclick={dispatch(|list, i, _| WatchlistAction::Move { list, from: i, to: i + 1 })}>
+                                        { "↓" }
+                                    </button>
+                                    <button type="button" class="btn btn-sm btn-outline-danger" title="削除"
+                                        onclick={dispatch(|list, _, code| WatchlistAction::Remove { list, code })}>
+                                        { "×" }
+                                    </button>
+                                </td>
+                            </tr>
+                        }
+                    })}
+                </tbody>
+            </table>
+        </div>
+    }
+}
+
+#[derive(Properties, PartialEq)]
+pub struct WatchlistToggleProps {
+    pub code: String,
+}
+
+/// 選んだリストへの追加・削除を切り替えるボタン
+#[function_component]
+pub fn WatchlistToggle(props: &WatchlistToggleProps) -> Html {
+    let watchlist = use_context::<UseReducerHandle<Watchlist>>().expect("Watchlist context");
+    let selected = use_state(|| 0usize);
+
+    let list = (*selected).min(watchlist.lists.len() - 1);
+    let watched = watchlist.lists[list].codes.contains(&props.code);
+
+    let on_change_list = {
+        let selected = selected.clone();
+        Callback::from(move |e: Event| {
+            let input: HtmlInputElement = e.target_unchecked_into();
+            selected.set(input.value().parse().unwrap_or_default());
+        })
+    };
+
+    let on_click = {
+        let watchlist = watchlist.clone();
+        let code = props.code.clone();
+        Callback::from(move |_| {
+            let code = code.clone();
+            watchlist.dispatch(if watched {
+                WatchlistAction::Remove { list, code }
+            } else {
+                WatchlistAction::Add { list, code }
+            });
+        })
+    };
+
+    html! {
+        <div class="input-group input-group-sm w-auto">
+            <select class="form-select" onchange={on_change_list}>
+                { for watchlist.lists.iter().enumerate().map(|(i, group)| html! {
+                    <option value={i.to_string()} selected={i == list}>{ &group.name }</option>
+                })}
+            </select>
+            <button type="button" class={if watched { "btn btn-outline-danger" } else { "btn btn-outline-info" }} onclick={on_click}>
+                { if watched { "ウォッチリストから削除" } else { "ウォッチリストに追加" } }
+            </button>
+        </div>
+    }
+}
@@ -1,5 +1,9 @@
 pub mod csv_reader;
+pub mod csv_writer;
 pub mod formater;
+pub mod indexed_db;
 pub mod oauth_google;
 pub mod parser;
+pub mod response_cache;
 pub mod shoken_web_api;
+pub mod tax_rule;
@@ -1,19 +1,212 @@
 use csv::StringRecord;
-use encoding_rs::SHIFT_JIS;
+use encoding_rs::{SHIFT_JIS, UTF_8};
+use std::collections::HashMap;
+use strum::{EnumIter, EnumMessage};
 use thiserror::Error;
 use wasm_bindgen_futures::JsFuture;
 use web_sys::{js_sys, File};
 
 #[derive(Debug, Error)]
+#[allow(clippy::enum_variant_names)]
 pub enum CSVError {
-    #[error("デコードに失敗")]
-    DecodeError,
+    #[error("デコードに失敗 ({0})")]
+    DecodeError(&'static str),
+
+    #[error("エンコードに失敗 ({0} で表せない文字が含まれています)")]
+    EncodeError(&'static str),
 
     #[error("ファイル読み込み失敗: {0}")]
     FileReadError(String),
 
     #[error("CSV読み込み失敗: {0}")]
-    CSVReadError(#[from] csv::Error),
+    CSVReadError(String),
+
+    #[error("CSVの列が一致しません (不足: {}, 不明: {})", .missing.join(", "), .unknown.join(", "))]
+    ColumnMismatchError {
+        missing: Vec<String>,
+        unknown: Vec<String>,
+    },
+
+    #[error("対応していないCSV形式です")]
+    UnknownFormatError,
+
+    #[error("{}行目を読み込めません: {}", .0.row, .0.reason)]
+    InvalidRowError(ImportIssue),
+}
+
+/// 取り込み時に検出した行単位の問題
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportIssue {
+    /// CSV の行番号 (ヘッダー行が 1 行目)
+    pub row: u64,
+    /// ヘッダー名 (行全体の問題の場合は None)
+    pub column: Option<String>,
+    /// 元の値
+    pub value: String,
+    /// 理由
+    pub reason: String,
+}
+
+impl From<csv::Error> for CSVError {
+    fn from(err: csv::Error) -> Self {
+        CSVError::CSVReadError(describe_csv_error(&err))
+    }
+}
+
+/// csv クレートのエラーを日本語の説明にする
+fn describe_csv_error(err: &csv::Error) -> String {
+    match err.kind() {
+        csv::ErrorKind::UnequalLengths {
+            expected_len, len, ..
+        } => format!("列の数が {} 個です (ヘッダーは {} 個)", len, expected_len),
+        csv::ErrorKind::Utf8 { .. } => "文字コードを変換できない値があります".to_string(),
+        csv::ErrorKind::Io(_) => "データを読み込めません".to_string(),
+        _ => "CSVの形式が正しくありません".to_string(),
+    }
+}
+
+/// CSV ファイルの文字コード
+#[derive(Clone, PartialEq, Eq, Debug, EnumMessage, Copy, EnumIter)]
+pub enum CSVEncoding {
+    #[strum(message = "Shift_JIS (CP932)")]
+    ShiftJis,
+
+    #[strum(message = "UTF-8")]
+    Utf8,
+
+    #[strum(message = "UTF-8 (BOM付き)")]
+    Utf8Bom,
+}
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+impl CSVEncoding {
+    /// BOM の有無と UTF-8 としての妥当性から文字コードを推定する。
+    /// どちらでもなければ Shift_JIS (Windows-31J) とみなす。
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(UTF8_BOM) {
+            CSVEncoding::Utf8Bom
+        } else if std::str::from_utf8(bytes).is_ok() {
+            CSVEncoding::Utf8
+        } else {
+            CSVEncoding::ShiftJis
+        }
+    }
+
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, CSVError> {
+        match self {
+            CSVEncoding::ShiftJis => {
+                let (cow, _, had_errors) = SHIFT_JIS.encode(text);
+                if had_errors {
+                    return Err(CSVError::EncodeError(
+                        self.get_message().unwrap_or_default(),
+                    ));
+                }
+                Ok(cow.into_owned())
+            }
+            CSVEncoding::Utf8 => Ok(text.as_bytes().to_vec()),
+            CSVEncoding::Utf8Bom => Ok([UTF8_BOM, text.as_bytes()].concat()),
+        }
+    }
+
+    pub fn decode(&self, bytes: &[u8]) -> Result<String, CSVError> {
+        // encoding_rs の SHIFT_JIS は WHATWG 準拠で、CP932 の拡張文字も扱える
+        let (cow, had_errors) = match self {
+            CSVEncoding::ShiftJis => SHIFT_JIS.decode_without_bom_handling(bytes),
+            CSVEncoding::Utf8 => UTF_8.decode_without_bom_handling(bytes),
+            CSVEncoding::Utf8Bom => {
+                UTF_8.decode_without_bom_handling(bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes))
+            }
+        };
+        if had_errors {
+            return Err(CSVError::DecodeError(
+                self.get_message().unwrap_or_default(),
+            ));
+        }
+        Ok(cow.into_owned())
+    }
+}
+
+/// 読み込んだ CSV の内容
+#[derive(Debug, Clone, PartialEq)]
+pub struct CSVContent {
+    pub encoding: CSVEncoding,
+    pub headers: StringRecord,
+    pub records: Vec<StringRecord>,
+    /// 列数の不一致などで読み込めなかった行
+    pub invalid_rows: Vec<ImportIssue>,
+}
+
+/// フィールド名から CSV の列番号を引くための対応表。
+///
+/// ヘッダー行と `(フィールド名, ヘッダー名)` の一覧から作成する。
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CSVColumns {
+    indices: HashMap<&'static str, usize>,
+}
+
+impl CSVColumns {
+    /// ヘッダー行から各フィールドの列番号を解決する。
+    ///
+    /// 必要な列が見つからない場合は、不足している列とファイル側にしかない列を
+    /// `CSVError::ColumnMismatchError` で返す。
+    pub fn resolve(
+        headers: &StringRecord,
+        columns: &[(&'static str, &'static str)],
+    ) -> Result<Self, CSVError> {
+        let headers: Vec<String> = headers.iter().map(normalize_header).collect();
+
+        let mut indices = HashMap::new();
+        let mut missing = Vec::new();
+        for (key, header) in columns {
+            let header = normalize_header(header);
+            match headers.iter().position(|h| *h == header) {
+                Some(index) => {
+                    indices.insert(*key, index);
+                }
+                None => missing.push(header),
+            }
+        }
+
+        if missing.is_empty() {
+            return Ok(Self { indices });
+        }
+
+        let unknown = headers
+            .into_iter()
+            .enumerate()
+            .filter(|(i, h)| !h.is_empty() && !indices.values().any(|index| index == i))
+            .map(|(_, h)| h)
+            .collect();
+        Err(CSVError::ColumnMismatchError { missing, unknown })
+    }
+
+    pub fn get<'a>(&self, record: &'a StringRecord, key: &str) -> Option<&'a str> {
+        self.indices.get(key).and_then(|&index| record.get(index))
+    }
+
+    /// `(フィールド名, 列番号)` を列番号順に返す。
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, usize)> + '_ {
+        let mut columns: Vec<_> = self.indices.iter().map(|(k, i)| (*k, *i)).collect();
+        columns.sort_by_key(|(_, index)| *index);
+        columns.into_iter()
+    }
+}
+
+/// ヘッダー名を比較用に正規化する (BOM・前後の空白の除去、全角括弧の半角化)。
+fn normalize_header(header: &str) -> String {
+    header
+        .trim_start_matches('\u{feff}')
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '［' => '[',
+            '］' => ']',
+            '（' => '(',
+            '）' => ')',
+            c => c,
+        })
+        .collect()
 }
 
 pub async fn read_file(file: &File) -> Result<Vec<u8>, CSVError> {
@@ -23,16 +216,128 @@ pub async fn read_file(file: &File) -> Result<Vec<u8>, CSVError> {
     Ok(js_sys::Uint8Array::new(&array_buffer).to_vec())
 }
 
-pub fn read_csv(bytes: Vec<u8>) -> Result<Vec<StringRecord>, CSVError> {
-    let (cow, _, had_errors) = SHIFT_JIS.decode(&bytes);
-    if had_errors {
-        return Err(CSVError::DecodeError);
-    }
-    let utf8_string = cow.into_owned();
+/// CSV を読み込み、ヘッダー行とデータ行を返す。
+///
+/// `encoding` が `None` の場合は文字コードを自動判定する。
+/// 読み込めない行があってもファイル全体は失敗とせず、`invalid_rows` に記録する。
+pub fn read_csv(bytes: Vec<u8>, encoding: Option<CSVEncoding>) -> Result<CSVContent, CSVError> {
+    let encoding = encoding.unwrap_or_else(|| CSVEncoding::detect(&bytes));
+    let utf8_string = encoding.decode(&bytes)?;
     let mut rdr = csv::ReaderBuilder::new()
         .has_headers(true)
         .from_reader(utf8_string.as_bytes());
-    rdr.records()
-        .collect::<Result<Vec<_>, csv::Error>>()
-        .map_err(CSVError::CSVReadError)
+    let headers = rdr.headers()?.clone();
+
+    let mut records = Vec::new();
+    let mut invalid_rows = Vec::new();
+    for result in rdr.records() {
+        match result {
+            Ok(record) => records.push(record),
+            Err(err) => invalid_rows.push(ImportIssue {
+                row: err.position().map_or(0, |p| p.line()),
+                column: None,
+                value: String::new(),
+                reason: describe_csv_error(&err),
+            }),
+        }
+    }
+
+    Ok(CSVContent {
+        encoding,
+        headers,
+        records,
+        invalid_rows,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COLUMNS: &[(&str, &str)] = &[("security_code", "銘柄コード"), ("shares", "数量［株］")];
+
+    const CSV: &str = "銘柄コード,数量[株]\n7203,100\n";
+
+    #[test]
+    fn test_read_csv_returns_headers() {
+        let (bytes, _, _) = SHIFT_JIS.encode(CSV);
+        let content = read_csv(bytes.into_owned(), None).unwrap();
+
+        assert_eq!(content.encoding, CSVEncoding::ShiftJis);
+        assert_eq!(
+            content.headers,
+            StringRecord::from(vec!["銘柄コード", "数量[株]"])
+        );
+        assert_eq!(
+            content.records,
+            vec![StringRecord::from(vec!["7203", "100"])]
+        );
+    }
+
+    #[test]
+    fn test_read_csv_detects_utf8() {
+        let content = read_csv(CSV.as_bytes().to_vec(), None).unwrap();
+        assert_eq!(content.encoding, CSVEncoding::Utf8);
+        assert_eq!(content.headers.get(0), Some("銘柄コード"));
+
+        let bytes = [UTF8_BOM, CSV.as_bytes()].concat();
+        let content = read_csv(bytes, None).unwrap();
+        assert_eq!(content.encoding, CSVEncoding::Utf8Bom);
+        assert_eq!(content.headers.get(0), Some("銘柄コード"));
+    }
+
+    #[test]
+    fn test_read_csv_cp932_extension() {
+        // ①・髙 は Shift_JIS にはなく CP932 で拡張された文字
+        let (bytes, _, had_errors) = SHIFT_JIS.encode("銘柄,備考\n髙島屋,①\n");
+        assert!(!had_errors);
+        let content = read_csv(bytes.into_owned(), None).unwrap();
+        assert_eq!(content.records[0], StringRecord::from(vec!["髙島屋", "①"]));
+    }
+
+    #[test]
+    fn test_read_csv_with_wrong_encoding() {
+        let (bytes, _, _) = SHIFT_JIS.encode(CSV);
+        match read_csv(bytes.into_owned(), Some(CSVEncoding::Utf8)) {
+            Err(CSVError::DecodeError(name)) => assert_eq!(name, "UTF-8"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_read_csv_collects_invalid_rows() {
+        let csv = "銘柄コード,数量[株]\n7203,100\n9984\n8058,200\n";
+        let content = read_csv(csv.as_bytes().to_vec(), None).unwrap();
+
+        assert_eq!(content.records.len(), 2);
+        assert_eq!(content.invalid_rows.len(), 1);
+        assert_eq!(content.invalid_rows[0].row, 3);
+        assert_eq!(
+            content.invalid_rows[0].reason,
+            "列の数が 1 個です (ヘッダーは 2 個)"
+        );
+    }
+
+    #[test]
+    fn test_resolve_by_header_name() {
+        let headers = StringRecord::from(vec!["備考", "数量[株]", " 銘柄コード "]);
+        let record = StringRecord::from(vec!["", "100", "7203"]);
+        let columns = CSVColumns::resolve(&headers, COLUMNS).unwrap();
+
+        assert_eq!(columns.get(&record, "security_code"), Some("7203"));
+        assert_eq!(columns.get(&record, "shares"), Some("100"));
+        assert_eq!(columns.get(&record, "unknown"), None);
+    }
+
+    #[test]
+    fn test_resolve_reports_missing_and_unknown() {
+        let headers = StringRecord::from(vec!["銘柄コード", "株数"]);
+        match CSVColumns::resolve(&headers, COLUMNS) {
+            Err(CSVError::ColumnMismatchError { missing, unknown }) => {
+                assert_eq!(missing, vec!["数量[株]"]);
+                assert_eq!(unknown, vec!["株数"]);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
 }
@@ -0,0 +1,146 @@
+use csv::{Terminator, WriterBuilder};
+use strum::{EnumIter, EnumMessage};
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{js_sys, Blob, BlobPropertyBag, HtmlAnchorElement, Url};
+
+use super::csv_reader::{CSVEncoding, CSVError};
+
+/// 書き出し形式
+#[derive(Clone, PartialEq, Eq, Debug, EnumMessage, Copy, EnumIter, Default)]
+pub enum ExportFormat {
+    #[default]
+    #[strum(message = "CSV")]
+    Csv,
+
+    #[strum(message = "TSV")]
+    Tsv,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Tsv => "tsv",
+        }
+    }
+
+    fn delimiter(&self) -> u8 {
+        match self {
+            ExportFormat::Csv => b',',
+            ExportFormat::Tsv => b'\t',
+        }
+    }
+
+    fn mime_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv",
+            ExportFormat::Tsv => "text/tab-separated-values",
+        }
+    }
+}
+
+/// ヘッダー行とデータ行を書き出し、指定した文字コードのバイト列を返す。
+///
+/// Excel で開けるよう改行は CRLF にする。
+pub fn write_delimited(
+    headers: &[&str],
+    rows: &[Vec<String>],
+    format: ExportFormat,
+    encoding: CSVEncoding,
+) -> Result<Vec<u8>, CSVError> {
+    let mut wtr = WriterBuilder::new()
+        .delimiter(format.delimiter())
+        .terminator(Terminator::CRLF)
+        .from_writer(Vec::new());
+    wtr.write_record(headers)?;
+    for row in rows {
+        wtr.write_record(row)?;
+    }
+    let bytes = wtr
+        .into_inner()
+        .map_err(|e| CSVError::FileReadError(e.to_string()))?;
+
+    encoding.encode(&String::from_utf8_lossy(&bytes))
+}
+
+/// バイト列を Blob URL 経由でファイルとしてダウンロードさせる。
+pub fn download(bytes: &[u8], file_name: &str, format: ExportFormat) -> Result<(), JsValue> {
+    download_as(bytes, file_name, format.mime_type())
+}
+
+/// MIME タイプを指定してダウンロードさせる。
+pub fn download_as(bytes: &[u8], file_name: &str, mime_type: &str) -> Result<(), JsValue> {
+    let document = web_sys::window()
+        .and_then(|w| w.document())
+        .ok_or_else(|| JsValue::from_str("no document"))?;
+
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let options = BlobPropertyBag::new();
+    options.set_type(mime_type);
+    let blob = Blob::new_with_u8_array_sequence_and_options(&parts, &options)?;
+    let url = Url::create_object_url_with_blob(&blob)?;
+
+    let anchor: HtmlAnchorElement = document.create_element("a")?.dyn_into()?;
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+
+    Url::revoke_object_url(&url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::SHIFT_JIS;
+
+    fn rows() -> Vec<Vec<String>> {
+        vec![
+            vec!["2024-03-28".into(), "トヨタ自動車".into(), "3,000".into()],
+            vec!["".into(), "".into(), "2391".into()],
+        ]
+    }
+
+    #[test]
+    fn test_write_csv_utf8_bom() {
+        let bytes = write_delimited(
+            &["受渡日", "銘柄名", "受取金額"],
+            &rows(),
+            ExportFormat::Csv,
+            CSVEncoding::Utf8Bom,
+        )
+        .unwrap();
+
+        assert!(bytes.starts_with(b"\xEF\xBB\xBF"));
+        assert_eq!(
+            String::from_utf8(bytes[3..].to_vec()).unwrap(),
+            "受渡日,銘柄名,受取金額\r\n2024-03-28,トヨタ自動車,\"3,000\"\r\n,,2391\r\n"
+        );
+    }
+
+    #[test]
+    fn test_write_tsv_shift_jis() {
+        let bytes = write_delimited(
+            &["受渡日", "銘柄名", "受取金額"],
+            &rows(),
+            ExportFormat::Tsv,
+            CSVEncoding::ShiftJis,
+        )
+        .unwrap();
+
+        let (text, _, had_errors) = SHIFT_JIS.decode(&bytes);
+        assert!(!had_errors);
+        assert_eq!(
+            text,
+            "受渡日\t銘柄名\t受取金額\r\n2024-03-28\tトヨタ自動車\t3,000\r\n\t\t2391\r\n"
+        );
+    }
+
+    #[test]
+    fn test_write_shift_jis_unmappable() {
+        let rows = vec![vec!["😀".to_string()]];
+        assert!(matches!(
+            write_delimited(&["銘柄名"], &rows, ExportFormat::Csv, CSVEncoding::ShiftJis),
+            Err(CSVError::EncodeError(_))
+        ));
+    }
+}
@@ -0,0 +1,100 @@
+use thiserror::Error;
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{
+    js_sys::Promise, Event, IdbDatabase, IdbOpenDbRequest, IdbRequest, IdbTransactionMode,
+};
+
+const DB_NAME: &str = "shoken-webapp-wasm";
+const DB_VERSION: u32 = 1;
+
+/// 取り込んだ受取金データ (キー: データセット名、値: JSON 文字列)
+pub const RECEIPTS_STORE: &str = "receipts";
+
+const STORES: [&str; 1] = [RECEIPTS_STORE];
+
+#[derive(Debug, Error)]
+pub enum IndexedDbError {
+    #[error("IndexedDB を利用できません")]
+    Unavailable,
+
+    #[error("IndexedDB の操作に失敗: {0}")]
+    RequestFailed(String),
+}
+
+impl From<JsValue> for IndexedDbError {
+    fn from(value: JsValue) -> Self {
+        IndexedDbError::RequestFailed(format!("{:?}", value))
+    }
+}
+
+/// `IdbRequest` の完了を待ち、結果を返す。
+async fn wait_request(request: &IdbRequest) -> Result<JsValue, IndexedDbError> {
+    let promise = Promise::new(&mut |resolve, reject| {
+        request.set_onsuccess(Some(&resolve));
+        request.set_onerror(Some(&reject));
+    });
+    JsFuture::from(promise).await?;
+    Ok(request.result()?)
+}
+
+async fn open() -> Result<IdbDatabase, IndexedDbError> {
+    let factory = web_sys::window()
+        .and_then(|w| w.indexed_db().ok().flatten())
+        .ok_or(IndexedDbError::Unavailable)?;
+    let request: IdbOpenDbRequest = factory.open_with_u32(DB_NAME, DB_VERSION)?;
+
+    let on_upgrade_needed = Closure::<dyn FnMut(Event)>::new(|event: Event| {
+        let Some(db) = event
+            .target()
+            .and_then(|t| t.dyn_into::<IdbOpenDbRequest>().ok())
+            .and_then(|r| r.result().ok())
+            .map(JsCast::unchecked_into::<IdbDatabase>)
+        else {
+            return;
+        };
+        for store in STORES {
+            if !db.object_store_names().contains(store) {
+                _ = db.create_object_store(store);
+            }
+        }
+    });
+    request.set_onupgradeneeded(Some(on_upgrade_needed.as_ref().unchecked_ref()));
+
+    let db = wait_request(&request).await?;
+    Ok(db.unchecked_into())
+}
+
+/// `store` から `key` の値を文字列として取得する。
+pub async fn get(store: &str, key: &str) -> Result<Option<String>, IndexedDbError> {
+    let db = open().await?;
+    let object_store = db
+        .transaction_with_str_and_mode(store, IdbTransactionMode::Readonly)?
+        .object_store(store)?;
+    let value = wait_request(&object_store.get(&JsValue::from_str(key))?).await?;
+    db.close();
+    Ok(value.as_string())
+}
+
+/// `store` の `key` に文字列を保存する。
+pub async fn put(store: &str, key: &str, value: &str) -> Result<(), IndexedDbError> {
+    let db = open().await?;
+    let object_store = db
+        .transaction_with_str_and_mode(store, IdbTransactionMode::Readwrite)?
+        .object_store(store)?;
+    wait_request(&object_store.put_with_key(&JsValue::from_str(value), &JsValue::from_str(key))?)
+        .await?;
+    db.close();
+    Ok(())
+}
+
+/// `store` から `key` を削除する。
+pub async fn delete(store: &str, key: &str) -> Result<(), IndexedDbError> {
+    let db = open().await?;
+    let object_store = db
+        .transaction_with_str_and_mode(store, IdbTransactionMode::Readwrite)?
+        .object_store(store)?;
+    wait_request(&object_store.delete(&JsValue::from_str(key))?).await?;
+    db.close();
+    Ok(())
+}
@@ -1,44 +1,261 @@
-/*
+use gloo::storage::{SessionStorage, Storage};
 use oauth2::{
-    basic::BasicClient, AuthUrl, ClientId, ClientSecret, CsrfToken, RedirectUrl, Scope, TokenUrl,
+    basic::BasicClient, AuthUrl, ClientId, CsrfToken, PkceCodeChallenge, RedirectUrl, Scope,
 };
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use url::Url;
 
+use super::shoken_web_api::{ApiClient, ApiError};
 use crate::env;
 
 static OAUTH2_AUTH_URL: &str = "https://accounts.google.com/o/oauth2/v2/auth";
-static OAUTH2_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
 
-#[derive(Error, Debug)]
+/// ログイン開始時の state と code_verifier を保存する sessionStorage のキー
+const PENDING_LOGIN_KEY: &str = "oauth_pending_login";
+
+#[derive(Error, Debug, Clone, PartialEq)]
 pub enum OAuthError {
     #[error("Invalid URL: {0}")]
-    InvalidUrl(#[from] oauth2::url::ParseError),
-    #[error("OAuth client creation failed")]
-    _ClientCreationFailed,
+    InvalidUrl(String),
+    #[error("Google ログインが設定されていません")]
+    NotConfigured,
+    #[error("ログインを開始した記録がありません")]
+    NoPendingLogin,
+    #[error("ログインの state が一致しません")]
+    StateMismatch,
+    #[error("ログインが拒否されました: {0}")]
+    Denied(String),
+    #[error("ログインに失敗しました: {0}")]
+    ApiError(#[from] ApiError),
+}
+
+impl OAuthError {
+    /// リフレッシュトークンが無効になっている (invalid_grant や 401) か。
+    ///
+    /// 通信エラーやサーバーの一時的なエラーでは再試行できるため false を返す。
+    pub fn is_auth_failure(&self) -> bool {
+        match self {
+            OAuthError::ApiError(ApiError::HttpError { status: 401, .. }) => true,
+            OAuthError::ApiError(ApiError::HttpError {
+                status: 400,
+                message,
+            }) => message.contains("invalid_grant"),
+            _ => false,
+        }
+    }
+}
+
+/// ログインを開始したときに作った値
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct PendingLogin {
+    pub state: String,
+    pub code_verifier: String,
 }
 
-fn create_oauth_client() -> Result<BasicClient, OAuthError> {
-    let client_id = ClientId::new(env::SHOKEN_WEBAPI_GOOGLE_OAUTH_CLIENT_ID.to_string());
-    let client_secret_id =
-        ClientSecret::new(env::SHOKEN_WEBAPI_GOOGLE_OAUTH_CLIENT_SECRET_ID.to_string());
+/// Google からリダイレクトされたときのクエリ
+#[derive(Clone, PartialEq, Debug)]
+pub struct AuthCallback {
+    pub code: String,
+    pub state: String,
+}
 
-    let redirect_url = RedirectUrl::new(env::SHOKEN_WEBAPI_REDIRECT_URL.to_string())?;
-    let auth_url = AuthUrl::new(OAUTH2_AUTH_URL.to_string())?;
-    let token_url = TokenUrl::new(OAUTH2_TOKEN_URL.to_string())?;
+/// バックエンドが認可コードと引き換えに返すトークン
+#[derive(Clone, PartialEq, Debug, Deserialize)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub expires_in: i64, // 有効期間 [秒]
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub email: String,
+}
 
-    Ok(
-        BasicClient::new(client_id, Some(client_secret_id), auth_url, Some(token_url))
-            .set_redirect_uri(redirect_url),
-    )
+#[derive(Clone, PartialEq, Debug, Default, Deserialize)]
+pub struct AuthConfig {
+    #[serde(default)]
+    pub login_required: bool, // 受取金などの画面でログインが必要
 }
 
-pub async fn google_oauth() -> Result<(Url, CsrfToken), OAuthError> {
-    let client = create_oauth_client()?;
-    Ok(client
+pub fn is_configured() -> bool {
+    !env::SHOKEN_WEBAPI_GOOGLE_OAUTH_CLIENT_ID.is_empty()
+        && !env::SHOKEN_WEBAPI_REDIRECT_URL.is_empty()
+}
+
+/// Google の認可画面の URL を作り、state と code_verifier を sessionStorage に保存する。
+///
+/// クライアントシークレットは使わず、PKCE で認可コードを保護する。
+pub fn google_oauth() -> Result<Url, OAuthError> {
+    if !is_configured() {
+        return Err(OAuthError::NotConfigured);
+    }
+    let auth_url = AuthUrl::new(OAUTH2_AUTH_URL.to_string())
+        .map_err(|e| OAuthError::InvalidUrl(e.to_string()))?;
+    let redirect_url = RedirectUrl::new(env::SHOKEN_WEBAPI_REDIRECT_URL.to_string())
+        .map_err(|e| OAuthError::InvalidUrl(e.to_string()))?;
+    let client = BasicClient::new(ClientId::new(
+        env::SHOKEN_WEBAPI_GOOGLE_OAUTH_CLIENT_ID.to_string(),
+    ))
+    .set_auth_uri(auth_url)
+    .set_redirect_uri(redirect_url);
+
+    let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+    let (url, state) = client
         .authorize_url(CsrfToken::new_random)
+        .add_scope(Scope::new("openid".to_string()))
         .add_scope(Scope::new("email".to_string()))
         .add_scope(Scope::new("profile".to_string()))
-        .url())
+        .set_pkce_challenge(pkce_challenge)
+        .url();
+
+    let pending = PendingLogin {
+        state: state.secret().clone(),
+        code_verifier: pkce_verifier.secret().clone(),
+    };
+    SessionStorage::set(PENDING_LOGIN_KEY, &pending)
+        .map_err(|e| OAuthError::InvalidUrl(e.to_string()))?;
+    Ok(url)
+}
+
+/// リダイレクト先のクエリ文字列から認可コードを取り出す。ログインの戻りでなければ `None`。
+pub fn parse_callback(search: &str) -> Option<Result<AuthCallback, OAuthError>> {
+    let pairs: Vec<(String, String)> =
+        url::form_urlencoded::parse(search.trim_start_matches('?').as_bytes())
+            .into_owned()
+            .collect();
+    let get = |key: &str| {
+        pairs
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.clone())
+    };
+
+    if let Some(error) = get("error") {
+        return Some(Err(OAuthError::Denied(error)));
+    }
+    let code = get("code")?;
+    Some(Ok(AuthCallback {
+        code,
+        state: get("state").unwrap_or_default(),
+    }))
+}
+
+/// 保存しておいた state と照合し、一致すれば code_verifier を返す。
+pub fn verify_callback(
+    pending: Option<PendingLogin>,
+    callback: &AuthCallback,
+) -> Result<String, OAuthError> {
+    let pending = pending.ok_or(OAuthError::NoPendingLogin)?;
+    if pending.state != callback.state {
+        return Err(OAuthError::StateMismatch);
+    }
+    Ok(pending.code_verifier)
+}
+
+/// sessionStorage からログイン開始時の値を取り出す。同じ値を二度使わないよう削除する。
+pub fn take_pending_login() -> Option<PendingLogin> {
+    let pending = SessionStorage::get(PENDING_LOGIN_KEY).ok();
+    SessionStorage::delete(PENDING_LOGIN_KEY);
+    pending
+}
+
+/// 認可コードをバックエンドでトークンに交換する。
+pub async fn exchange_code(
+    callback: &AuthCallback,
+    code_verifier: &str,
+) -> Result<TokenResponse, OAuthError> {
+    let body = serde_json::json!({
+        "code": callback.code,
+        "code_verifier": code_verifier,
+        "redirect_uri": env::SHOKEN_WEBAPI_REDIRECT_URL,
+    });
+    Ok(ApiClient::default()
+        .post("/auth/google/token")
+        .json(&body)?
+        .send()
+        .await?)
+}
+
+/// リフレッシュトークンで新しいアクセストークンを取得する。
+pub async fn refresh_token(refresh_token: &str) -> Result<TokenResponse, OAuthError> {
+    let body = serde_json::json!({ "refresh_token": refresh_token });
+    Ok(ApiClient::default()
+        .post("/auth/refresh")
+        .json(&body)?
+        .send()
+        .await?)
+}
+
+/// バックエンドがログインを必須にしているか。取得できない場合は必須にしない。
+pub async fn fetch_auth_config() -> AuthConfig {
+    ApiClient::default()
+        .get("/auth/config")
+        .send()
+        .await
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_auth_failure() {
+        let http_error = |status, message: &str| {
+            OAuthError::ApiError(ApiError::HttpError {
+                status,
+                message: message.to_string(),
+            })
+        };
+        assert!(http_error(401, "").is_auth_failure());
+        assert!(http_error(400, "invalid_grant").is_auth_failure());
+        assert!(!http_error(400, "invalid_request").is_auth_failure());
+        assert!(!http_error(503, "").is_auth_failure());
+        assert!(!OAuthError::ApiError(ApiError::Timeout(10_000)).is_auth_failure());
+        assert!(!OAuthError::ApiError(ApiError::NetworkError("offline".into())).is_auth_failure());
+    }
+
+    #[test]
+    fn test_parse_callback() {
+        assert_eq!(parse_callback(""), None);
+        assert_eq!(parse_callback("?tab=1"), None);
+        assert_eq!(
+            parse_callback("?state=abc&code=4%2F0Ab"),
+            Some(Ok(AuthCallback {
+                code: "4/0Ab".to_string(),
+                state: "abc".to_string(),
+            }))
+        );
+        assert_eq!(
+            parse_callback("?error=access_denied&state=abc"),
+            Some(Err(OAuthError::Denied("access_denied".to_string())))
+        );
+    }
+
+    #[test]
+    fn test_verify_callback() {
+        let pending = PendingLogin {
+            state: "abc".to_string(),
+            code_verifier: "verifier".to_string(),
+        };
+        let callback = |state: &str| AuthCallback {
+            code: "code".to_string(),
+            state: state.to_string(),
+        };
+
+        assert_eq!(
+            verify_callback(Some(pending.clone()), &callback("abc")),
+            Ok("verifier".to_string())
+        );
+        assert_eq!(
+            verify_callback(Some(pending), &callback("xyz")),
+            Err(OAuthError::StateMismatch)
+        );
+        assert_eq!(
+            verify_callback(None, &callback("abc")),
+            Err(OAuthError::NoPendingLogin)
+        );
+    }
 }
- */
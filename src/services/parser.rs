@@ -1,22 +1,108 @@
 use chrono::NaiveDate;
+use serde::{Deserialize, Deserializer};
 use std::str::FromStr;
 
+/// 証券会社ごとに異なる日付表記 (`2024/01/05`, `2024-01-05`, `20240105`)
+const DATE_FORMATS: [&str; 3] = ["%Y/%m/%d", "%Y-%m-%d", "%Y%m%d"];
+
 pub trait OptionalStrParser {
     fn try_parse_date(&self) -> Option<NaiveDate>;
     fn try_parse_num<T: FromStr>(&self) -> Option<T>;
+    fn try_parse_decimal(&self) -> Option<f64>;
     fn try_parse_string(&self) -> Option<String>;
 }
 
 impl OptionalStrParser for Option<&str> {
     fn try_parse_date(&self) -> Option<NaiveDate> {
-        self.and_then(|s| NaiveDate::parse_from_str(s, "%Y/%m/%d").ok())
+        self.and_then(|s| {
+            DATE_FORMATS
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(s.trim(), format).ok())
+        })
     }
 
     fn try_parse_num<T: FromStr>(&self) -> Option<T> {
         self.and_then(|s| s.replace(",", "").parse().ok())
     }
 
+    /// `1,000円` や `0.25 USD` のように単位の付いた金額から数値を取り出す
+    ///
+    /// 数字・桁区切りのカンマ・小数点と、末尾の単位だけを受け付け、それ以外は `None` にする。
+    fn try_parse_decimal(&self) -> Option<f64> {
+        self.and_then(parse_decimal)
+    }
+
     fn try_parse_string(&self) -> Option<String> {
         self.map(ToString::to_string)
     }
 }
+
+fn parse_decimal(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let end = value
+        .char_indices()
+        .find(|(_, c)| !(c.is_ascii_digit() || matches!(c, ',' | '.' | '-' | '+')))
+        .map_or(value.len(), |(i, _)| i);
+    let (number, unit) = value.split_at(end);
+    // 単位に数字や記号が混ざっているものは数値として扱わない
+    if unit
+        .trim_start()
+        .chars()
+        .any(|c| c.is_ascii_digit() || matches!(c, ',' | '.' | '-' | '+'))
+    {
+        return None;
+    }
+
+    let unsigned = number.strip_prefix(['-', '+']).unwrap_or(number);
+    let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    let groups: Vec<&str> = integer.split(',').collect();
+    let grouped = groups.len() == 1
+        || (!groups[0].is_empty()
+            && groups[0].len() <= 3
+            && groups[1..].iter().all(|g| g.len() == 3));
+    let digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    if integer.is_empty() || !grouped || !groups.iter().all(|g| digits(g)) || !digits(fraction) {
+        return None;
+    }
+    number.replace(',', "").parse().ok()
+}
+
+/// 数値と、以前の形式で保存された文字列のどちらからでも読み込む
+pub fn deserialize_decimal<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<f64>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Decimal {
+        Number(f64),
+        Text(String),
+    }
+
+    Ok(match Option::<Decimal>::deserialize(deserializer)? {
+        Some(Decimal::Number(value)) => Some(value),
+        Some(Decimal::Text(text)) => Some(text.as_str()).try_parse_decimal(),
+        None => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_try_parse_decimal() {
+        let parse = |s| Some(s).try_parse_decimal();
+        assert_eq!(parse("1,000円"), Some(1000.0));
+        assert_eq!(parse("0.25 USD"), Some(0.25));
+        assert_eq!(parse("-1,234.5"), Some(-1234.5));
+        assert_eq!(parse(" 12 "), Some(12.0));
+
+        assert_eq!(parse("1-2"), None);
+        assert_eq!(parse("1,00"), None);
+        assert_eq!(parse("1.2.3"), None);
+        assert_eq!(parse("約100円"), None);
+        assert_eq!(parse("100円(2回)"), None);
+        assert_eq!(parse("USD"), None);
+        assert_eq!(parse(""), None);
+    }
+}
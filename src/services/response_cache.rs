@@ -0,0 +1,232 @@
+use chrono::{DateTime, Duration, Utc};
+use gloo::storage::{LocalStorage, Storage};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{cell::RefCell, collections::HashMap};
+
+use crate::data::stock::normalize;
+
+/// キャッシュした応答と取得日時
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CachedEntry<T> {
+    pub value: T,
+    pub cached_at: DateTime<Utc>,
+}
+
+/// API の応答をメモリと localStorage に保存するキャッシュ
+///
+/// 有効期限内のものは通信せずに使い、期限切れのものは通信できないときの代わりに使う。
+/// 保存のたびに `retention` を過ぎたものと、`max_entries` を超えた古いものを削除する。
+/// キーは銘柄の検索と同じ `normalize` で全角・半角や大文字・小文字をそろえる。
+pub struct ResponseCache<T> {
+    prefix: &'static str, // localStorage のキーの接頭辞
+    ttl: Duration,        // 有効期限
+    retention: Duration,  // 期限切れのものを残しておく期間
+    max_entries: usize,   // 残しておく最大件数
+    persistent: bool,     // localStorage にも保存する
+    entries: RefCell<HashMap<String, CachedEntry<T>>>,
+}
+
+impl<T: Clone + Serialize + DeserializeOwned> ResponseCache<T> {
+    pub fn new(
+        prefix: &'static str,
+        ttl: Duration,
+        retention: Duration,
+        max_entries: usize,
+        persistent: bool,
+    ) -> Self {
+        Self {
+            prefix,
+            ttl,
+            retention,
+            max_entries,
+            persistent,
+            entries: RefCell::new(HashMap::new()),
+        }
+    }
+
+    /// 有効期限内のものだけを返す
+    pub fn get_fresh(&self, key: &str, now: DateTime<Utc>) -> Option<CachedEntry<T>> {
+        self.get(key)
+            .filter(|entry| now - entry.cached_at < self.ttl)
+    }
+
+    /// 期限切れのものも含めて返す
+    pub fn get(&self, key: &str) -> Option<CachedEntry<T>> {
+        let key = normalize(key);
+        if let Some(entry) = self.entries.borrow().get(&key) {
+            return Some(entry.clone());
+        }
+        let entry: CachedEntry<T> = self
+            .persistent
+            .then(|| LocalStorage::get(self.storage_key(&key)).ok())
+            .flatten()?;
+        self.entries.borrow_mut().insert(key, entry.clone());
+        Some(entry)
+    }
+
+    pub fn put(&self, key: &str, value: T, now: DateTime<Utc>) -> CachedEntry<T> {
+        let key = normalize(key);
+        let entry = CachedEntry {
+            value,
+            cached_at: now,
+        };
+        self.prune(&key, now);
+        if self.persistent {
+            // 容量不足などで保存できなくてもメモリには残す
+            let _ = LocalStorage::set(self.storage_key(&key), &entry);
+        }
+        self.entries.borrow_mut().insert(key, entry.clone());
+        entry
+    }
+
+    /// `key` を追加する前に、残しておかないものをメモリと localStorage から削除する
+    fn prune(&self, key: &str, now: DateTime<Utc>) {
+        let mut entries = self.entries.borrow_mut();
+        let cached = entries
+            .iter()
+            .map(|(k, entry)| (k.clone(), Some(entry.cached_at)))
+            .collect();
+        for evicted in self.evicted_keys(cached, key, now) {
+            entries.remove(&evicted);
+        }
+
+        if self.persistent {
+            let storage = LocalStorage::raw();
+            let prefix = self.storage_key("");
+            let stored = (0..storage.length().unwrap_or_default())
+                .filter_map(|i| storage.key(i).ok().flatten())
+                .filter_map(|k| Some(k.strip_prefix(&prefix)?.to_string()))
+                .map(|k| {
+                    // 読み込めないものは取得日時なしとして削除する
+                    let cached_at = LocalStorage::get::<CachedEntry<T>>(self.storage_key(&k))
+                        .ok()
+                        .map(|entry| entry.cached_at);
+                    (k, cached_at)
+                })
+                .collect();
+            for evicted in self.evicted_keys(stored, key, now) {
+                LocalStorage::delete(self.storage_key(&evicted));
+            }
+        }
+    }
+
+    /// 削除するキー: 取得日時がないもの、`retention` を過ぎたもの、
+    /// `key` を追加すると `max_entries` を超える場合の古いもの
+    fn evicted_keys(
+        &self,
+        mut cached: Vec<(String, Option<DateTime<Utc>>)>,
+        key: &str,
+        now: DateTime<Utc>,
+    ) -> Vec<String> {
+        cached.retain(|(k, _)| k != key);
+        cached.sort_by(|(_, a), (_, b)| b.cmp(a));
+        let keep = self.max_entries.saturating_sub(1);
+        cached
+            .into_iter()
+            .enumerate()
+            .filter(|(i, (_, cached_at))| {
+                *i >= keep || cached_at.is_none_or(|at| now - at >= self.retention)
+            })
+            .map(|(_, (k, _))| k)
+            .collect()
+    }
+
+    fn storage_key(&self, key: &str) -> String {
+        format!("{}:{}", self.prefix, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(max_entries: usize) -> ResponseCache<String> {
+        ResponseCache::new(
+            "test",
+            Duration::hours(1),
+            Duration::days(1),
+            max_entries,
+            false,
+        )
+    }
+
+    #[test]
+    fn test_get_fresh_and_stale() {
+        let cache = cache(10);
+        let now = Utc::now();
+        cache.put("7203", "トヨタ自動車".to_string(), now);
+
+        assert!(cache
+            .get_fresh("7203", now + Duration::minutes(59))
+            .is_some());
+        assert!(cache.get_fresh("7203", now + Duration::hours(1)).is_none());
+        assert_eq!(
+            cache.get("7203"),
+            Some(CachedEntry {
+                value: "トヨタ自動車".to_string(),
+                cached_at: now,
+            })
+        );
+        assert!(cache.get("6758").is_none());
+    }
+
+    #[test]
+    fn test_normalized_key() {
+        let cache = cache(10);
+        cache.put(" 130a ", "銘柄".to_string(), Utc::now());
+
+        assert!(cache.get("130A").is_some());
+        assert!(cache.get("１３０Ａ").is_some());
+        assert_eq!(normalize(" 7203\t"), "7203");
+    }
+
+    #[test]
+    fn test_prune_expired_entries() {
+        let cache = cache(10);
+        let now = Utc::now();
+        cache.put("7203", "トヨタ自動車".to_string(), now);
+        cache.put(
+            "6758",
+            "ソニーグループ".to_string(),
+            now + Duration::hours(2),
+        );
+        // 期限切れでも保存期間内なら残す
+        assert!(cache.get("7203").is_some());
+
+        cache.put(
+            "9984",
+            "ソフトバンクグループ".to_string(),
+            now + Duration::days(1),
+        );
+        assert!(cache.get("7203").is_none());
+        assert!(cache.get("6758").is_some());
+    }
+
+    #[test]
+    fn test_evict_oldest_entries() {
+        let cache = cache(2);
+        let now = Utc::now();
+        cache.put("7203", "トヨタ自動車".to_string(), now);
+        cache.put(
+            "6758",
+            "ソニーグループ".to_string(),
+            now + Duration::minutes(1),
+        );
+        // 同じキーの更新では削除しない
+        cache.put(
+            "6758",
+            "ソニーグループ".to_string(),
+            now + Duration::minutes(2),
+        );
+        assert!(cache.get("7203").is_some());
+
+        cache.put(
+            "9984",
+            "ソフトバンクグループ".to_string(),
+            now + Duration::minutes(3),
+        );
+        assert!(cache.get("7203").is_none());
+        assert!(cache.get("6758").is_some());
+        assert!(cache.get("9984").is_some());
+    }
+}
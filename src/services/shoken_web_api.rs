@@ -1,65 +1,304 @@
-use gloo::utils::format::JsValueSerdeExt;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use gloo::timers::future::TimeoutFuture;
+use std::{
+    cell::{Cell, RefCell},
+    future::Future,
+    rc::Rc,
+};
 use thiserror::Error;
-use wasm_bindgen::{JsCast, JsValue};
-use wasm_bindgen_futures::JsFuture;
-use web_sys::{Request, RequestInit, RequestMode, Response};
+use web_sys::{AbortController, AbortSignal};
 
-use crate::{data::stock::StockData, setting::*};
+use super::response_cache::ResponseCache;
+use crate::{
+    data::stock::{DailyPrice, DividendHistory, StockData},
+    setting::*,
+};
 
-#[derive(Debug, Error)]
+mod client;
+#[cfg(test)]
+pub(crate) mod mock_server;
+
+pub use client::ApiClient;
+
+#[derive(Debug, Error, Clone, PartialEq)]
 pub enum ApiError {
-    #[error("Request failed")]
-    RequestError,
-    #[error("No window object")]
-    NoWindowObject,
-    #[error("Fetch error")]
-    FetchError,
-    #[error("Response error")]
-    ResponseError,
-    #[error("JSON error")]
-    JsonError,
-    #[error("Deserialization error")]
-    DeserializationError,
+    #[error("リクエストを作成できません: {0}")]
+    RequestError(String),
+    #[error("サーバーに接続できません (ネットワークまたは CORS の問題): {0}")]
+    NetworkError(String),
+    #[error("対象が見つかりません (HTTP 404)")]
+    NotFound,
+    #[error("銘柄 {0} が見つかりません")]
+    StockNotFound(String),
+    #[error("サーバーのデータが他の端末で更新されています")]
+    PreconditionFailed,
+    #[error("サーバーエラー (HTTP {status}): {message}")]
+    HttpError { status: u16, message: String },
+    #[error("応答を JSON として読み込めません: {0}")]
+    JsonError(String),
+    #[error("応答の形式が正しくありません: {0}")]
+    DeserializationError(String),
+    #[error("リクエストが中止されました")]
+    Aborted,
+    #[error("応答がありません ({0} ms でタイムアウト)")]
+    Timeout(u32),
 }
 
 impl ApiError {
-    #[allow(dead_code)]
-    fn from_js_error<E: Into<ApiError>>(error: E) -> ApiError {
-        error.into()
+    /// HTTP ステータスとサーバーが返したエラー本文から作る
+    fn from_status(status: u16, body: &str) -> ApiError {
+        match status {
+            404 => ApiError::NotFound,
+            412 => ApiError::PreconditionFailed,
+            _ => ApiError::HttpError {
+                status,
+                message: server_message(body),
+            },
+        }
     }
 }
 
-async fn fetch_json(url: &str) -> Result<JsValue, ApiError> {
-    let window = web_sys::window().ok_or(ApiError::NoWindowObject)?;
-    let request = create_request(url)?;
+/// エラー本文が `{"error": "..."}` や `{"message": "..."}` の場合はその文字列、それ以外は本文をそのまま返す。
+fn server_message(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|json| {
+            ["error", "message", "detail"]
+                .iter()
+                .find_map(|key| json.get(key)?.as_str().map(ToString::to_string))
+        })
+        .unwrap_or_else(|| body.trim().to_string())
+}
+
+/// 最新のリクエストだけを扱うためのハンドル
+///
+/// 新しいリクエストを始めると、送信待ちや応答待ちの古いリクエストは取り消される。
+#[derive(Clone, Default)]
+pub struct LatestRequest {
+    seq: Rc<Cell<u64>>,
+    controller: Rc<RefCell<Option<AbortController>>>,
+}
+
+impl LatestRequest {
+    /// `delay_ms` だけ待ってからリクエストを送る。
+    ///
+    /// 待っている間や応答を待っている間に次のリクエストが始まった場合は、応答を捨てて
+    /// `ApiError::Aborted` を返す。
+    pub async fn debounce<T, F, Fut>(&self, delay_ms: u32, request: F) -> Result<T, ApiError>
+    where
+        F: FnOnce(Option<AbortSignal>) -> Fut,
+        Fut: Future<Output = Result<T, ApiError>>,
+    {
+        let seq = self.begin();
+        TimeoutFuture::new(delay_ms).await;
+        if !self.is_latest(seq) {
+            return Err(ApiError::Aborted);
+        }
+
+        let controller = AbortController::new().ok();
+        let signal = controller.as_ref().map(AbortController::signal);
+        *self.controller.borrow_mut() = controller;
+        let result = request(signal).await;
+        self.latest(seq, result)
+    }
+
+    /// 応答待ちのリクエストを中止し、新しいリクエストの番号を返す。
+    pub fn begin(&self) -> u64 {
+        if let Some(controller) = self.controller.borrow_mut().take() {
+            controller.abort();
+        }
+        self.seq.set(self.seq.get() + 1);
+        self.seq.get()
+    }
 
-    let response = JsFuture::from(window.fetch_with_request(&request))
-        .await
-        .map_err(|_| ApiError::FetchError)?;
-    let response: Response = response.dyn_into().map_err(|_| ApiError::ResponseError)?;
+    pub fn is_latest(&self, seq: u64) -> bool {
+        self.seq.get() == seq
+    }
 
-    JsFuture::from(response.json().map_err(|_| ApiError::JsonError)?)
-        .await
-        .map_err(|_| ApiError::JsonError)
+    /// `seq` が最新のリクエストであれば `result` を、取り消されていれば `ApiError::Aborted` を返す
+    fn latest<T>(&self, seq: u64, result: Result<T, ApiError>) -> Result<T, ApiError> {
+        if self.is_latest(seq) {
+            result
+        } else {
+            Err(ApiError::Aborted)
+        }
+    }
 }
 
-fn create_request(url: &str) -> Result<Request, ApiError> {
-    let opts = RequestInit::new();
-    opts.set_method("GET");
-    opts.set_mode(RequestMode::Cors);
+thread_local! {
+    static STOCK_CACHE: ResponseCache<StockData> =
+        ResponseCache::new(
+            "stock",
+            Duration::seconds(STOCK_CACHE_TTL_SECS),
+            Duration::days(STOCK_CACHE_RETENTION_DAYS),
+            STOCK_CACHE_MAX_ENTRIES,
+            true,
+        );
+}
 
-    Request::new_with_str_and_init(url, &opts).map_err(|_| ApiError::RequestError)
+/// キャッシュを含めて取得したデータ
+#[derive(Clone, PartialEq, Debug)]
+pub struct Fetched<T> {
+    pub value: T,
+    pub cached_at: DateTime<Utc>, // 取得日時
+    pub stale: bool,              // 通信できず、期限切れのキャッシュを使った
 }
 
 /// 株式データを API から取得する関数。
 ///
 /// - `code`: 銘柄コード (例: "7203")
-/// - 成功時: `StockData` を返す。
+/// - `signal`: 中止に使う `AbortSignal`
+/// - 成功時: `StockData` を返す。有効期限内のキャッシュがあれば通信しない。
+/// - 失敗時: 通信できない場合は期限切れのキャッシュを返し、それもなければ `ApiError` を返す。
+///   銘柄がない場合は `ApiError::StockNotFound` を返す。
+pub async fn fetch_stock_data(
+    code: &str,
+    signal: Option<&AbortSignal>,
+) -> Result<Fetched<StockData>, ApiError> {
+    let now = Utc::now();
+    if let Some(entry) = STOCK_CACHE.with(|cache| cache.get_fresh(code, now)) {
+        return Ok(Fetched {
+            value: entry.value,
+            cached_at: entry.cached_at,
+            stale: false,
+        });
+    }
+
+    let result = ApiClient::default()
+        .get(&format!("/stock/{}", code))
+        .signal(signal)
+        .send()
+        .await;
+    match result {
+        Ok(stock) => {
+            let entry = STOCK_CACHE.with(|cache| cache.put(code, stock, now));
+            Ok(Fetched {
+                value: entry.value,
+                cached_at: entry.cached_at,
+                stale: false,
+            })
+        }
+        Err(err @ (ApiError::NetworkError(_) | ApiError::Timeout(_))) => STOCK_CACHE
+            .with(|cache| cache.get(code))
+            .map(|entry| Fetched {
+                value: entry.value,
+                cached_at: entry.cached_at,
+                stale: true,
+            })
+            .ok_or(err),
+        Err(ApiError::NotFound) => Err(ApiError::StockNotFound(code.to_string())),
+        Err(err) => Err(err),
+    }
+}
+
+/// 日足を API から取得する関数。
+///
+/// - `code`: 銘柄コード (例: "7203")
+/// - `from`, `to`: 取得する期間 (両端を含む)
+/// - 成功時: 日付の古い順の `DailyPrice` を返す。
+/// - 失敗時: `ApiError` を返す。
+pub async fn fetch_daily_prices(
+    code: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+    signal: Option<&AbortSignal>,
+) -> Result<Vec<DailyPrice>, ApiError> {
+    let mut prices: Vec<DailyPrice> = ApiClient::default()
+        .get(&format!("/stock/{}/prices", code))
+        .query("from", &from.to_string())
+        .query("to", &to.to_string())
+        .signal(signal)
+        .send()
+        .await?;
+    prices.sort_by_key(|price| price.date);
+    Ok(prices)
+}
+
+/// 配当の履歴を API から取得する関数。
+///
+/// - `code`: 銘柄コード (例: "7203")
+/// - 成功時: 権利落ち日の古い順の `DividendHistory` を返す。
 /// - 失敗時: `ApiError` を返す。
-pub async fn fetch_stock_data(code: &str) -> Result<StockData, ApiError> {
-    let url = format!("{}/stock/{}", SHOKEN_WEB_API_URL, code);
-    let json = fetch_json(&url).await?;
+pub async fn fetch_dividend_history(
+    code: &str,
+    signal: Option<&AbortSignal>,
+) -> Result<Vec<DividendHistory>, ApiError> {
+    let mut dividends: Vec<DividendHistory> = ApiClient::default()
+        .get(&format!("/stock/{}/dividends", code))
+        .signal(signal)
+        .send()
+        .await?;
+    dividends.sort_by_key(|dividend| dividend.ex_date);
+    Ok(dividends)
+}
 
-    json.into_serde::<StockData>()
-        .map_err(|_| ApiError::DeserializationError)
+/// 銘柄名・読み・銘柄コードで銘柄を検索する関数。
+///
+/// - `query`: 入力された文字列 (例: "トヨタ", "72")
+/// - `signal`: 中止に使う `AbortSignal`
+/// - 成功時: 一致した `StockData` を一致の度合いの順に返す。
+/// - 失敗時: `ApiError` を返す。
+pub async fn search_stocks(
+    query: &str,
+    signal: Option<&AbortSignal>,
+) -> Result<Vec<StockData>, ApiError> {
+    let stocks = ApiClient::default()
+        .get("/stocks")
+        .query("q", query.trim())
+        .signal(signal)
+        .send()
+        .await?;
+    Ok(rank_stocks(stocks, query))
+}
+
+/// 候補を一致の度合いの順に並べ、一致しないものを除く。
+fn rank_stocks(stocks: Vec<StockData>, query: &str) -> Vec<StockData> {
+    let mut ranked: Vec<(u8, StockData)> = stocks
+        .into_iter()
+        .filter_map(|stock| Some((stock.match_rank(query)?, stock)))
+        .collect();
+    ranked.sort_by(|(a_rank, a), (b_rank, b)| a_rank.cmp(b_rank).then_with(|| a.code.cmp(&b.code)));
+    ranked.into_iter().map(|(_, stock)| stock).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latest_request_discards_stale_sequence() {
+        let request = LatestRequest::default();
+        let first = request.begin();
+        assert!(request.is_latest(first));
+
+        let second = request.clone().begin();
+        assert!(!request.is_latest(first));
+        assert!(request.is_latest(second));
+
+        // 取り消されたリクエストの応答は中止として扱う
+        assert_eq!(request.latest(first, Ok(1)), Err(ApiError::Aborted));
+        assert_eq!(request.latest(second, Ok(1)), Ok(1));
+    }
+
+    #[test]
+    fn test_from_status() {
+        assert_eq!(ApiError::from_status(404, ""), ApiError::NotFound);
+        assert_eq!(ApiError::from_status(412, ""), ApiError::PreconditionFailed);
+        // 404 は API 共通のため、銘柄に限らないメッセージにする
+        assert_eq!(
+            ApiError::from_status(404, "").to_string(),
+            "対象が見つかりません (HTTP 404)"
+        );
+        assert_eq!(
+            ApiError::from_status(500, r#"{"error": "database unavailable"}"#),
+            ApiError::HttpError {
+                status: 500,
+                message: "database unavailable".to_string()
+            }
+        );
+        assert_eq!(
+            ApiError::from_status(502, "Bad Gateway\n").to_string(),
+            "サーバーエラー (HTTP 502): Bad Gateway"
+        );
+    }
 }
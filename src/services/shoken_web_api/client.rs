@@ -0,0 +1,410 @@
+use serde::{de::DeserializeOwned, Serialize};
+use web_sys::AbortSignal;
+
+use super::ApiError;
+use crate::setting::*;
+
+/// タイムアウトの既定値 [ms]
+const DEFAULT_TIMEOUT_MS: u32 = 10_000;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Method {
+    Get,
+    Post,
+    Put,
+    Delete,
+}
+
+impl Method {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Delete => "DELETE",
+        }
+    }
+}
+
+/// shoken-webapp-api の REST クライアント
+#[derive(Clone, PartialEq, Debug)]
+pub struct ApiClient {
+    pub base_url: String,
+    pub timeout_ms: Option<u32>,    // None の場合はタイムアウトしない
+    pub auth_token: Option<String>, // Authorization: Bearer に付けるトークン
+}
+
+impl Default for ApiClient {
+    fn default() -> Self {
+        Self {
+            base_url: SHOKEN_WEB_API_URL.to_string(),
+            timeout_ms: Some(DEFAULT_TIMEOUT_MS),
+            auth_token: None,
+        }
+    }
+}
+
+impl ApiClient {
+    pub fn get(&self, path: &str) -> ApiRequest {
+        self.request(Method::Get, path)
+    }
+
+    pub fn post(&self, path: &str) -> ApiRequest {
+        self.request(Method::Post, path)
+    }
+
+    pub fn put(&self, path: &str) -> ApiRequest {
+        self.request(Method::Put, path)
+    }
+
+    pub fn delete(&self, path: &str) -> ApiRequest {
+        self.request(Method::Delete, path)
+    }
+
+    pub fn request(&self, method: Method, path: &str) -> ApiRequest {
+        let mut headers = vec![("Accept".to_string(), "application/json".to_string())];
+        if let Some(token) = &self.auth_token {
+            headers.push(("Authorization".to_string(), format!("Bearer {}", token)));
+        }
+        ApiRequest {
+            method,
+            url: format!(
+                "{}/{}",
+                self.base_url.trim_end_matches('/'),
+                path.trim_start_matches('/')
+            ),
+            query: Vec::new(),
+            headers,
+            body: None,
+            timeout_ms: self.timeout_ms,
+            signal: None,
+        }
+    }
+}
+
+/// 送信前のリクエスト
+#[derive(Clone, Debug)]
+pub struct ApiRequest {
+    method: Method,
+    url: String,
+    query: Vec<(String, String)>,
+    headers: Vec<(String, String)>,
+    body: Option<String>,
+    timeout_ms: Option<u32>,
+    signal: Option<AbortSignal>,
+}
+
+impl ApiRequest {
+    pub fn query(mut self, key: &str, value: &str) -> Self {
+        self.query.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn header(mut self, key: &str, value: &str) -> Self {
+        self.headers.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// 本文を JSON にして送る
+    pub fn json<B: Serialize>(mut self, body: &B) -> Result<Self, ApiError> {
+        let body =
+            serde_json::to_string(body).map_err(|err| ApiError::RequestError(err.to_string()))?;
+        self.body = Some(body);
+        Ok(self.header("Content-Type", "application/json"))
+    }
+
+    /// このリクエストだけタイムアウトを変える (None の場合はタイムアウトしない)
+    pub fn timeout(mut self, timeout_ms: Option<u32>) -> Self {
+        self.timeout_ms = timeout_ms;
+        self
+    }
+
+    /// `signal` が中止されたらリクエストも中止する
+    pub fn signal(mut self, signal: Option<&AbortSignal>) -> Self {
+        self.signal = signal.cloned();
+        self
+    }
+
+    /// クエリ文字列を含めた URL
+    pub fn url(&self) -> String {
+        if self.query.is_empty() {
+            return self.url.clone();
+        }
+        let query = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(&self.query)
+            .finish();
+        format!("{}?{}", self.url, query)
+    }
+
+    /// リクエストを送り、応答の JSON を `T` に変換する。
+    ///
+    /// 本文が空の応答 (204 No Content など) は `null` として変換する。
+    pub async fn send<T: DeserializeOwned>(self) -> Result<T, ApiError> {
+        let (status, body) = backend::send(&self).await?;
+        if !(200..300).contains(&status) {
+            return Err(ApiError::from_status(status, &body));
+        }
+
+        let body = if body.trim().is_empty() {
+            "null"
+        } else {
+            &body
+        };
+        serde_json::from_str(body).map_err(|err| {
+            if err.is_syntax() || err.is_eof() {
+                ApiError::JsonError(err.to_string())
+            } else {
+                ApiError::DeserializationError(err.to_string())
+            }
+        })
+    }
+}
+
+/// ブラウザでは fetch API で送る
+#[cfg(target_arch = "wasm32")]
+mod backend {
+    use gloo::{events::EventListener, timers::callback::Timeout};
+    use std::{cell::Cell, rc::Rc};
+    use wasm_bindgen::{JsCast, JsValue};
+    use wasm_bindgen_futures::JsFuture;
+    use web_sys::{js_sys, AbortController, Request, RequestInit, RequestMode, Response};
+
+    use super::{ApiError, ApiRequest};
+
+    /// JS の例外からメッセージを取り出す
+    fn js_error_text(error: &JsValue) -> String {
+        error
+            .dyn_ref::<js_sys::Error>()
+            .map(|e| String::from(e.message()))
+            .or_else(|| error.as_string())
+            .unwrap_or_else(|| format!("{:?}", error))
+    }
+
+    pub async fn send(request: &ApiRequest) -> Result<(u16, String), ApiError> {
+        let window = web_sys::window().ok_or_else(|| {
+            ApiError::RequestError("ブラウザの window オブジェクトがありません".to_string())
+        })?;
+        let controller =
+            AbortController::new().map_err(|err| ApiError::RequestError(js_error_text(&err)))?;
+
+        // 呼び出し元の signal が中止されたら、このリクエストも中止する
+        let _forward_abort = request.signal.as_ref().map(|signal| {
+            let controller = controller.clone();
+            EventListener::once(signal, "abort", move |_| controller.abort())
+        });
+        let timed_out = Rc::new(Cell::new(false));
+        let _timeout = request.timeout_ms.map(|timeout_ms| {
+            let controller = controller.clone();
+            let timed_out = timed_out.clone();
+            Timeout::new(timeout_ms, move || {
+                timed_out.set(true);
+                controller.abort();
+            })
+        });
+        let fetch_error = |err: JsValue| {
+            if timed_out.get() {
+                ApiError::Timeout(request.timeout_ms.unwrap_or_default())
+            } else if controller.signal().aborted() {
+                ApiError::Aborted
+            } else {
+                ApiError::NetworkError(js_error_text(&err))
+            }
+        };
+
+        let opts = RequestInit::new();
+        opts.set_method(request.method.as_str());
+        opts.set_mode(RequestMode::Cors);
+        opts.set_signal(Some(&controller.signal()));
+        if let Some(body) = &request.body {
+            opts.set_body(&JsValue::from_str(body));
+        }
+        let web_request = Request::new_with_str_and_init(&request.url(), &opts)
+            .map_err(|err| ApiError::RequestError(js_error_text(&err)))?;
+        for (key, value) in &request.headers {
+            web_request
+                .headers()
+                .set(key, value)
+                .map_err(|err| ApiError::RequestError(js_error_text(&err)))?;
+        }
+
+        let response: Response = JsFuture::from(window.fetch_with_request(&web_request))
+            .await
+            .map_err(fetch_error)?
+            .dyn_into()
+            .map_err(|err| ApiError::NetworkError(js_error_text(&err)))?;
+        let body = JsFuture::from(response.text().map_err(fetch_error)?)
+            .await
+            .map_err(fetch_error)?
+            .as_string()
+            .unwrap_or_default();
+
+        Ok((response.status(), body))
+    }
+}
+
+/// ネイティブ (テスト) では reqwest で送る
+#[cfg(not(target_arch = "wasm32"))]
+mod backend {
+    use std::time::Duration;
+
+    use super::{ApiError, ApiRequest};
+
+    pub async fn send(request: &ApiRequest) -> Result<(u16, String), ApiError> {
+        let method = reqwest::Method::from_bytes(request.method.as_str().as_bytes())
+            .map_err(|err| ApiError::RequestError(err.to_string()))?;
+        let mut builder = reqwest::Client::new().request(method, request.url());
+        for (key, value) in &request.headers {
+            builder = builder.header(key, value);
+        }
+        if let Some(body) = &request.body {
+            builder = builder.body(body.clone());
+        }
+        if let Some(timeout_ms) = request.timeout_ms {
+            builder = builder.timeout(Duration::from_millis(timeout_ms.into()));
+        }
+
+        let fetch_error = |err: reqwest::Error| {
+            if err.is_timeout() {
+                ApiError::Timeout(request.timeout_ms.unwrap_or_default())
+            } else {
+                ApiError::NetworkError(err.to_string())
+            }
+        };
+        let response = builder.send().await.map_err(fetch_error)?;
+        let status = response.status().as_u16();
+        let body = response.text().await.map_err(fetch_error)?;
+
+        Ok((status, body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::shoken_web_api::mock_server::{block_on, mock_server};
+    use serde::Deserialize;
+    use std::time::Duration;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Item {
+        code: String,
+    }
+
+    #[test]
+    fn test_url_with_query() {
+        let client = ApiClient {
+            base_url: "https://example.com/".to_string(),
+            ..Default::default()
+        };
+        let request = client
+            .get("/stocks")
+            .query("q", "トヨタ 自動車")
+            .query("limit", "10");
+        assert_eq!(
+            request.url(),
+            "https://example.com/stocks?q=%E3%83%88%E3%83%A8%E3%82%BF+%E8%87%AA%E5%8B%95%E8%BB%8A&limit=10"
+        );
+    }
+
+    #[test]
+    fn test_get_with_auth_header() {
+        let (client, received) = mock_server("200 OK", r#"[{"code":"7203"}]"#, Duration::ZERO);
+        let client = ApiClient {
+            auth_token: Some("token".to_string()),
+            ..client
+        };
+        let items: Vec<Item> = block_on(client.get("/stocks").query("q", "72").send()).unwrap();
+
+        assert_eq!(
+            items,
+            vec![Item {
+                code: "7203".into()
+            }]
+        );
+        let request = received.recv().unwrap().to_lowercase();
+        assert!(request.starts_with("get /stocks?q=72 http/1.1"));
+        assert!(request.contains("authorization: bearer token"));
+    }
+
+    #[test]
+    fn test_post_json_body() {
+        let (client, received) = mock_server("204 No Content", "", Duration::ZERO);
+        let request = client.post("/watchlist").json(&Item {
+            code: "7203".into(),
+        });
+        let result: Result<(), _> = block_on(request.unwrap().send());
+
+        assert_eq!(result, Ok(()));
+        let request = received.recv().unwrap();
+        assert!(request.starts_with("POST /watchlist HTTP/1.1"));
+        assert!(request
+            .to_lowercase()
+            .contains("content-type: application/json"));
+        assert!(request.ends_with(r#"{"code":"7203"}"#));
+    }
+
+    #[test]
+    fn test_delete() {
+        let (client, received) = mock_server("204 No Content", "", Duration::ZERO);
+        let result: Result<(), _> = block_on(client.delete("/watchlist/7203").send());
+
+        assert_eq!(result, Ok(()));
+        assert!(received
+            .recv()
+            .unwrap()
+            .starts_with("DELETE /watchlist/7203 HTTP/1.1"));
+    }
+
+    #[test]
+    fn test_error_status() {
+        let (client, _) = mock_server("404 Not Found", r#"{"error":"not found"}"#, Duration::ZERO);
+        let result: Result<Item, _> = block_on(client.get("/stock/0000").send());
+        assert_eq!(result, Err(ApiError::NotFound));
+
+        let (client, _) = mock_server(
+            "500 Internal Server Error",
+            r#"{"message":"boom"}"#,
+            Duration::ZERO,
+        );
+        let result: Result<Item, _> = block_on(client.get("/stock/7203").send());
+        assert_eq!(
+            result,
+            Err(ApiError::HttpError {
+                status: 500,
+                message: "boom".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_timeout() {
+        let (client, _) = mock_server("200 OK", "{}", Duration::from_millis(500));
+        let client = ApiClient {
+            timeout_ms: Some(50),
+            ..client
+        };
+        let result: Result<Item, _> = block_on(client.put("/stock/7203").send());
+        assert_eq!(result, Err(ApiError::Timeout(50)));
+    }
+
+    #[test]
+    fn test_request_timeout() {
+        // リクエストごとのタイムアウトはクライアントの既定値より優先する
+        let (client, _) = mock_server("200 OK", "{}", Duration::from_millis(500));
+        let result: Result<Item, _> = block_on(client.get("/stock/7203").timeout(Some(50)).send());
+        assert_eq!(result, Err(ApiError::Timeout(50)));
+
+        let (client, _) = mock_server("200 OK", r#"{"code":"7203"}"#, Duration::from_millis(100));
+        let client = ApiClient {
+            timeout_ms: Some(50),
+            ..client
+        };
+        let result: Result<Item, _> = block_on(client.get("/stock/7203").timeout(None).send());
+        assert_eq!(
+            result,
+            Ok(Item {
+                code: "7203".into()
+            })
+        );
+    }
+}
@@ -0,0 +1,81 @@
+//! テスト用のモックサーバー
+
+use std::{
+    future::Future,
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpListener,
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use super::ApiClient;
+
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+        .block_on(future)
+}
+
+/// 1 回だけ `status` と `body` を返すモックサーバーを立て、受け取ったリクエストを返す。
+pub fn mock_server(
+    status: &str,
+    body: &str,
+    delay: Duration,
+) -> (ApiClient, mpsc::Receiver<String>) {
+    mock_server_sequence(vec![(status, body)], delay)
+}
+
+/// `responses` を受け取った順に 1 つずつ返すモックサーバー
+pub fn mock_server_sequence(
+    responses: Vec<(&str, &str)>,
+    delay: Duration,
+) -> (ApiClient, mpsc::Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let responses: Vec<String> = responses
+        .into_iter()
+        .map(|(status, body)| {
+            format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )
+        })
+        .collect();
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for response in responses {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut request = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if let Some(length) = line.to_lowercase().strip_prefix("content-length:") {
+                    content_length = length.trim().parse().unwrap();
+                }
+                request.push_str(&line);
+                if line == "\r\n" {
+                    break;
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            request.push_str(&String::from_utf8(body).unwrap());
+            let _ = sender.send(request);
+
+            thread::sleep(delay);
+            let _ = reader.get_mut().write_all(response.as_bytes());
+        }
+    });
+    let client = ApiClient {
+        base_url,
+        ..Default::default()
+    };
+    (client, receiver)
+}
@@ -0,0 +1,179 @@
+use chrono::{Datelike, NaiveDate};
+
+/// 税率の単位 (1/100,000)。浮動小数点の誤差で 1 円ずれないよう整数で計算する。
+const RATE_UNIT: i64 = 100_000;
+
+/// 上場株式等の譲渡所得・配当所得に対する源泉徴収の税率
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TaxRule {
+    pub since: NaiveDate,        // 適用開始日
+    pub income_tax: i64,         // 所得税
+    pub reconstruction_tax: i64, // 復興特別所得税
+    pub resident_tax: i64,       // 住民税
+}
+
+/// 適用開始日の新しい順
+const TAX_RULES: [TaxRule; 4] = [
+    // 復興特別所得税の終了 (2037 年分まで)
+    TaxRule {
+        since: date(2038, 1, 1),
+        income_tax: 15_000,
+        reconstruction_tax: 0,
+        resident_tax: 5_000,
+    },
+    // 軽減税率の廃止
+    TaxRule {
+        since: date(2014, 1, 1),
+        income_tax: 15_000,
+        reconstruction_tax: 315,
+        resident_tax: 5_000,
+    },
+    // 復興特別所得税の開始 (軽減税率)
+    TaxRule {
+        since: date(2013, 1, 1),
+        income_tax: 7_000,
+        reconstruction_tax: 147,
+        resident_tax: 3_000,
+    },
+    // 軽減税率
+    TaxRule {
+        since: NaiveDate::MIN,
+        income_tax: 7_000,
+        reconstruction_tax: 0,
+        resident_tax: 3_000,
+    },
+];
+
+const fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+    match NaiveDate::from_ymd_opt(year, month, day) {
+        Some(date) => date,
+        None => panic!("invalid date"),
+    }
+}
+
+/// 税目ごとの源泉徴収税額
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Withholding {
+    pub income_tax: i64,         // 所得税
+    pub reconstruction_tax: i64, // 復興特別所得税
+    pub resident_tax: i64,       // 住民税
+}
+
+impl Withholding {
+    pub fn total(&self) -> i64 {
+        self.income_tax + self.reconstruction_tax + self.resident_tax
+    }
+}
+
+impl TaxRule {
+    /// `date` に適用される税率
+    pub fn for_date(date: NaiveDate) -> Self {
+        *TAX_RULES
+            .iter()
+            .find(|rule| rule.since <= date)
+            .unwrap_or(&TAX_RULES[TAX_RULES.len() - 1])
+    }
+
+    /// `year` 年分に適用される税率
+    pub fn for_year(year: i32) -> Self {
+        Self::for_date(NaiveDate::from_ymd_opt(year, 12, 31).unwrap_or(NaiveDate::MAX))
+    }
+
+    /// 源泉徴収税額を計算する。証券会社と同じく税目ごとに 1 円未満を切り捨てる。
+    pub fn withhold(&self, amount: i64) -> Withholding {
+        if amount <= 0 {
+            return Withholding::default();
+        }
+        Withholding {
+            income_tax: amount * self.income_tax / RATE_UNIT,
+            reconstruction_tax: amount * self.reconstruction_tax / RATE_UNIT,
+            resident_tax: amount * self.resident_tax / RATE_UNIT,
+        }
+    }
+
+    /// 復興特別所得税が課される年か
+    pub fn has_reconstruction_tax(&self) -> bool {
+        self.reconstruction_tax > 0
+    }
+}
+
+/// 特定口座 (源泉徴収あり) の年初来の損益通算
+///
+/// 証券会社と同じく、譲渡のたびに年初来の損益に対する源泉徴収税額との差額を徴収し、
+/// 損失で年初来の損益が減った場合は徴収済みの税額を還付する。年が変わると通算をやり直す。
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct YearToDateLedger {
+    year: Option<i32>,
+    net: i64,      // 年初来の損益
+    withheld: i64, // 年初来の源泉徴収税額
+}
+
+impl YearToDateLedger {
+    /// 損益を記帳し、今回の源泉徴収税額 (負の場合は還付額) を返す。
+    pub fn post(&mut self, date: NaiveDate, amount: i64) -> i64 {
+        if self.year != Some(date.year()) {
+            *self = Self {
+                year: Some(date.year()),
+                ..Default::default()
+            };
+        }
+        self.net += amount;
+        let withheld = TaxRule::for_date(date).withhold(self.net).total();
+        let delta = withheld - self.withheld;
+        self.withheld = withheld;
+        delta
+    }
+
+    /// 年初来の源泉徴収税額
+    pub fn withheld(&self) -> i64 {
+        self.withheld
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_for_date() {
+        let rule = |y, m, d| TaxRule::for_date(NaiveDate::from_ymd_opt(y, m, d).unwrap());
+        assert_eq!(rule(2012, 12, 31).reconstruction_tax, 0);
+        assert_eq!(rule(2013, 1, 1).income_tax, 7_000);
+        assert_eq!(rule(2013, 1, 1).reconstruction_tax, 147);
+        assert_eq!(rule(2024, 6, 1).income_tax, 15_000);
+        assert_eq!(rule(2037, 12, 31).reconstruction_tax, 315);
+        assert_eq!(rule(2038, 1, 1).reconstruction_tax, 0);
+    }
+
+    #[test]
+    fn test_withhold_truncates_each_component() {
+        // 3,000 円の配当: 所得税 450 + 復興 9 (9.45) + 住民税 150
+        let w = TaxRule::for_year(2024).withhold(3_000);
+        assert_eq!(
+            (w.income_tax, w.reconstruction_tax, w.resident_tax),
+            (450, 9, 150)
+        );
+        assert_eq!(w.total(), 609);
+
+        // 合算税率 20.315% では 9,151 円になるが、税目ごとに切り捨てると 9,150 円
+        assert_eq!(TaxRule::for_year(2024).withhold(45_050).total(), 9_150);
+        assert_eq!(TaxRule::for_year(2024).withhold(-1_000).total(), 0);
+    }
+
+    #[test]
+    fn test_year_to_date_ledger() {
+        let d = |y, m| NaiveDate::from_ymd_opt(y, m, 1).unwrap();
+        let mut ledger = YearToDateLedger::default();
+
+        assert_eq!(ledger.post(d(2024, 1), 100_000), 20_315);
+        // 損失が出ると徴収済みの税額を還付する
+        assert_eq!(ledger.post(d(2024, 3), -60_000), -12_189);
+        assert_eq!(ledger.withheld(), 8_126);
+        assert_eq!(ledger.post(d(2024, 5), -60_000), -8_126);
+        // 年初来の損失を超えるまでは徴収しない
+        assert_eq!(ledger.post(d(2024, 7), 10_000), 0);
+        assert_eq!(ledger.withheld(), 0);
+        // 年が変わると通算をやり直す
+        assert_eq!(ledger.post(d(2025, 1), 10_000), 2_031);
+    }
+}
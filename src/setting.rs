@@ -17,12 +17,15 @@ lazy_static! {
         map.insert("purchase_price", "平均取得価額");
         map.insert("realized_profit_and_loss", "実現損益");
         map.insert("realized_profit_and_loss_after_tax", "実現損益(税引)");
+        map.insert("withholding_tax", "源泉徴収税額");
+        map.insert("withholding_balance", "年初来源泉徴収税額");
         map.insert("total_realized_profit_and_loss", "合計実現損益");
         map.insert("total_realized_profit_and_loss_after_tax", "合計実現損益(税引)");
         map.insert("product", "商品");
         map.insert("currency", "受取通貨");
         map.insert("unit_price", "単価");
         map.insert("dividends_before_tax", "配当・分配金");
+        map.insert("foreign_taxes", "外国税額");
         map.insert("taxes", "税額");
         map.insert("net_amount_received", "受取金額");
         map.insert("total_dividends_before_tax", "合計配当・分配金");
@@ -34,6 +37,7 @@ lazy_static! {
         map.insert("fund_name", "ファンド名");
         map.insert("dividends", "分配金");
         map.insert("exchange_rate", "為替レート");
+        map.insert("source_file", "取込元");
 
         map
     };
@@ -51,11 +55,13 @@ lazy_static! {
         "purchase_price",                           // 平均取得価額
         "realized_profit_and_loss",                 // 実現損益
         "taxes",                                    // 税額
+        "foreign_taxes",                            // 外国税額
         "total_dividends_before_tax",               // 配当・分配金合計(税引前)
         "total_net_amount_received",                // 受取金額合計
         "total_realized_profit_and_loss",           // 合計実現損益
         "total_taxes",                              // 税額合計
         "withholding_tax",                          // 源泉徴収税額
+        "withholding_balance",                      // 年初来源泉徴収税額
         "unit_price",                               // 単価
         "cancellation_unit_price_yen",              // 解約単価［円］
         "cancellation_amount_yen",                  // 解約額［円］
@@ -67,33 +73,57 @@ lazy_static! {
         "settlement_date", // 受取金
         "trade_date",      // 約定日
     ].iter().cloned().collect();
-
-    pub static ref STOCK_INFO_LINKS: Vec<(&'static str, &'static str)> = vec![
-        ("楽天証券", RAKUTEN_URL),
-        ("SBI証券", SBI_URL),
-        ("株探", KABUTAN_URL),
-        ("Yahoo! Finance", YAHOO_URL),
-        ("日経", NIKKEI_URL),
-        ("バフェットコード", BUFFETT_CODE_URL),
-        ("みんかぶ", MINKABU_URL),
-        ("IR BANK", IR_BANK_URL),
-        ("銘柄スカウター", SCOUTER_URL),
-        ("ザイマニ", ZAIMANI_URL),
-    ];
 }
 
-pub const TAX_RATE: f64 = 0.20315;
-
-pub const MINKABU_URL: &'static str = "https://minkabu.jp/stock/{}/";
-pub const KABUTAN_URL: &'static str = "https://kabutan.jp/stock/?code={}";
-pub const YAHOO_URL: &'static str = "https://finance.yahoo.co.jp/quote/{}";
-pub const NIKKEI_URL: &'static str = "https://www.nikkei.com/nkd/company/?scode={}";
-pub const BUFFETT_CODE_URL: &'static str = "https://www.buffett-code.com/company/{}";
-pub const RAKUTEN_URL: &'static str =
-    "https://www.rakuten-sec.co.jp/web/market/search/quote.html?ric={}.T";
-pub const IR_BANK_URL: &'static str = "https://irbank.net/{}";
-pub const ZAIMANI_URL: &'static str = "https://zaimani.com/search/?_sf_s={}";
-pub const SCOUTER_URL: &'static str = "https://monex.ifis.co.jp/index.php?sa=report_index&bcode={}";
-pub const SBI_URL: &'static str = "https://site3.sbisec.co.jp/ETGate/?_ControlID=WPLETsiR001Control&_DataStoreID=DSWPLETsiR001Control&_PageID=WPLETsiR001Ilst10&_ActionID=getDetailOfStockPriceJP&s_rkbn=1&i_stock_sec=%94%43%93%56%93%B0&i_dom_flg=1&i_exchange_code=JPN&i_output_type=0&stock_sec_code_mul={}";
-
-pub const SHOKEN_WEB_API_URL: &'static str = "https://shoken-webapp-api-b4a1.shuttle.app";
+/// 初期設定の銘柄情報サイトへのリンク (名前, URL, 表示するマーケットカテゴリ)
+///
+/// 楽天証券と SBI 証券の URL は東証の内国株式を前提にしているので、それ以外では表示しない。
+pub const DEFAULT_LINK_PROVIDERS: [(&str, &str, &[&str]); 10] = [
+    ("楽天証券", RAKUTEN_URL, TSE_DOMESTIC_MARKETS),
+    ("SBI証券", SBI_URL, TSE_DOMESTIC_MARKETS),
+    ("株探", KABUTAN_URL, &[]),
+    ("Yahoo! Finance", YAHOO_URL, &[]),
+    ("日経", NIKKEI_URL, &[]),
+    ("バフェットコード", BUFFETT_CODE_URL, &[]),
+    ("みんかぶ", MINKABU_URL, &[]),
+    ("IR BANK", IR_BANK_URL, &[]),
+    ("銘柄スカウター", SCOUTER_URL, &[]),
+    ("ザイマニ", ZAIMANI_URL, &[]),
+];
+
+const TSE_DOMESTIC_MARKETS: &[&str] = &["プライム", "スタンダード", "グロース"];
+
+pub const MINKABU_URL: &str = "https://minkabu.jp/stock/{code}/";
+pub const KABUTAN_URL: &str = "https://kabutan.jp/stock/?code={code}";
+pub const YAHOO_URL: &str = "https://finance.yahoo.co.jp/quote/{code}";
+pub const NIKKEI_URL: &str = "https://www.nikkei.com/nkd/company/?scode={code}";
+pub const BUFFETT_CODE_URL: &str = "https://www.buffett-code.com/company/{code}";
+pub const RAKUTEN_URL: &str =
+    "https://www.rakuten-sec.co.jp/web/market/search/quote.html?ric={code}.T";
+pub const IR_BANK_URL: &str = "https://irbank.net/{code}";
+pub const ZAIMANI_URL: &str = "https://zaimani.com/search/?_sf_s={code}";
+pub const SCOUTER_URL: &str = "https://monex.ifis.co.jp/index.php?sa=report_index&bcode={code}";
+pub const SBI_URL: &str = "https://site3.sbisec.co.jp/ETGate/?_ControlID=WPLETsiR001Control&_DataStoreID=DSWPLETsiR001Control&_PageID=WPLETsiR001Ilst10&_ActionID=getDetailOfStockPriceJP&s_rkbn=1&i_stock_sec=%94%43%93%56%93%B0&i_dom_flg=1&i_exchange_code=JPN&i_output_type=0&stock_sec_code_mul={code}";
+
+/// アクセストークンの期限が切れる何秒前に更新するか
+pub const SESSION_REFRESH_MARGIN_SECS: i64 = 5 * 60;
+
+/// 通信エラーでアクセストークンを更新できなかったときに再試行するまでの時間 [ms] (失敗するたびに倍にする)
+pub const SESSION_REFRESH_RETRY_MS: u32 = 5_000;
+
+/// 更新を再試行するまでの最大の時間 [ms]
+pub const SESSION_REFRESH_RETRY_MAX_MS: u32 = 5 * 60 * 1000;
+
+/// 銘柄情報のキャッシュの有効期限 [秒]
+pub const STOCK_CACHE_TTL_SECS: i64 = 60 * 60 * 24;
+
+/// 期限切れの銘柄情報を、通信できないときのために残しておく日数
+pub const STOCK_CACHE_RETENTION_DAYS: i64 = 30;
+
+/// 銘柄情報のキャッシュに残す最大件数 (超えた分は古いものから削除する)
+pub const STOCK_CACHE_MAX_ENTRIES: usize = 200;
+
+/// 受取金をサーバーに保存するときのタイムアウト [ms] (行数が多いと時間がかかるため既定より長くする)
+pub const RECEIPTS_SYNC_TIMEOUT_MS: u32 = 60_000;
+
+pub const SHOKEN_WEB_API_URL: &str = "https://shoken-webapp-api-b4a1.shuttle.app";